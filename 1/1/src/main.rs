@@ -3,38 +3,36 @@
  * Dmitry Vasiliev <dima@hlabs.org>
  */
 
-extern crate "rustc-serialize" as serialize;
+extern crate codec;
 
-use serialize::hex::{FromHex, ToHex};
-use serialize::base64::{STANDARD, FromBase64, ToBase64};
+use codec::{Encoding, STANDARD};
 
 
 fn hex_to_bytes(hex: &str) -> Vec<u8> {
-    match hex.from_hex() {
+    match Encoding::Hex.decode(hex.as_bytes()) {
         Ok(bytes) => bytes,
         Err(error) => panic!("Error converting from Hex: {:?}", error)
     }
 }
 
 fn bytes_to_base64(bytes: &[u8]) -> String {
-    bytes.to_base64(STANDARD)
+    Encoding::Base64(STANDARD).encode(bytes)
 }
 
 fn base64_to_bytes(base64: &str) -> Vec<u8> {
-    match base64.from_base64() {
+    match Encoding::Base64(STANDARD).decode(base64.as_bytes()) {
         Ok(bytes) => bytes,
         Err(error) => panic!("Error converting from Base64: {:?}", error)
     }
 }
 
 fn bytes_to_hex(bytes: &[u8]) -> String {
-    bytes.to_hex()
+    Encoding::Hex.encode(bytes)
 }
 
 /*
  * Main entry point
  */
-#[cfg(not(test))]
 fn main() {
     let hex = "49276d206b696c6c696e6720796f757220627261696e206c696b65206120\
                706f69736f6e6f7573206d757368726f6f6d";
@@ -43,7 +41,7 @@ fn main() {
     let base64 = bytes_to_base64(bytes.as_slice());
     println!("Bytes   => {}", String::from_utf8(bytes).unwrap());
     println!("Base64  => {}", base64);
-    let bytes2 = base64_to_bytes(base64.as_slice());
+    let bytes2 = base64_to_bytes(&base64);
     let hex2 = bytes_to_hex(bytes2.as_slice());
     println!("Bytes 2 => {}", String::from_utf8(bytes2).unwrap());
     println!("Hex 2   => {}", hex2);
@@ -65,7 +63,7 @@ mod test {
                          zIG11c2hyb29t";
         let base64 = bytes_to_base64(hex_to_bytes(ex_hex).as_slice());
         let hex = bytes_to_hex(base64_to_bytes(ex_base64).as_slice());
-        assert_eq!(base64.as_slice(), ex_base64);
-        assert_eq!(hex.as_slice(), ex_hex);
+        assert_eq!(base64, ex_base64);
+        assert_eq!(hex, ex_hex);
     }
 }
@@ -3,10 +3,9 @@
  * Dmitry Vasiliev <dima@hlabs.org>
  */
 
-extern crate serialize;
+extern crate codec;
 
-#[cfg(not(test))]
-use serialize::hex::{FromHex, ToHex};
+use codec::Encoding;
 
 /*
  * XOR two equal-length buffers
@@ -21,7 +20,6 @@ fn xor_buffers(s1: &[u8], s2: &[u8]) -> Vec<u8> {
 /*
  * Main entry point
  */
-#[cfg(not(test))]
 fn main() {
     use std::str;
 
@@ -29,13 +27,13 @@ fn main() {
     let input = "686974207468652062756c6c277320657965";
     println!("Key           => {}", key);
     println!("Input         => {}", input);
-    let key_bytes = key.from_hex().unwrap();
-    let input_bytes = input.from_hex().unwrap();
+    let key_bytes = Encoding::Hex.decode(key.as_bytes()).unwrap();
+    let input_bytes = Encoding::Hex.decode(input.as_bytes()).unwrap();
     println!("Key bytes     => {:?}", key_bytes);
     let input_str = str::from_utf8(input_bytes.as_slice()).unwrap();
     println!("Input bytes   => {}", input_str);
     let output = xor_buffers(input_bytes.as_slice(), key_bytes.as_slice());
-    println!("Output        => {}", output.as_slice().to_hex());
+    println!("Output        => {}", Encoding::Hex.encode(&output));
     let output_str = str::from_utf8(output.as_slice()).unwrap();
     println!("Output bytes  => {}", output_str);
 }
@@ -45,15 +43,17 @@ fn main() {
  */
 #[cfg(test)]
 mod test {
+    use codec::Encoding;
     use super::xor_buffers;
-    use serialize::hex::{FromHex, ToHex};
 
     #[test]
     fn test_xor_buffers() {
-        let key = "1c0111001f010100061a024b53535009181c".from_hex().unwrap();
-        let input = "686974207468652062756c6c277320657965".from_hex().unwrap();
+        let key = Encoding::Hex.decode(
+            b"1c0111001f010100061a024b53535009181c").unwrap();
+        let input = Encoding::Hex.decode(
+            b"686974207468652062756c6c277320657965").unwrap();
         let output = xor_buffers(input.as_slice(), key.as_slice());
-        assert_eq!(output.as_slice().to_hex().as_slice(),
+        assert_eq!(Encoding::Hex.encode(&output),
                    "746865206b696420646f6e277420706c6179");
     }
 }
@@ -3,12 +3,12 @@
  * Dmitry Vasiliev <dima@hlabs.org>
  */
 
-extern crate serialize;
+extern crate codec;
 
 extern crate single_char_xor_lib;
 
-use single_char_xor_lib::{decrypt, SingleCharKey};
-use serialize::hex::FromHex;
+use codec::Encoding;
+use single_char_xor_lib::decrypt;
 
 /*
  * Main entry point
@@ -16,16 +16,18 @@ use serialize::hex::FromHex;
 fn main() {
     let input = "1b37373331363f78151b7f2b783431333d78397828372d363c\
                  78373e783a393b3736";
-    let encrypted = input.from_hex().unwrap();
+    let encrypted = Encoding::Hex.decode(input.as_bytes()).unwrap();
     println!("Input        => \"{}\"\n\
               Binary input => {:?}",
              input, encrypted);
-    match decrypt(encrypted.as_slice()) {
-        SingleCharKey::Found(key, decrypted) => {
+    match decrypt(encrypted.as_slice()).into_iter().next() {
+        Some((key, score, decrypted)) => {
             println!("Key  => '{}', ({})\n\
+                      Score => {}\n\
                       Text => \"{}\"",
-                     key as char, key, String::from_utf8(decrypted).unwrap());
+                     key as char, key, score,
+                     String::from_utf8_lossy(decrypted.as_slice()));
         }
-        SingleCharKey::NotFound => panic!("No decryption key found")
+        None => panic!("No decryption key found")
     }
 }
@@ -3,41 +3,55 @@
  * Dmitry Vasiliev <dima@hlabs.org>
  */
 
-extern crate serialize;
+extern crate codec;
 
 extern crate single_char_xor_lib;
 
-use std::path::Path;
-use std::io::fs::File;
-use std::io::BufferedReader;
-use serialize::hex::FromHex;
-use single_char_xor_lib::{decrypt, SingleCharKey};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use codec::Encoding;
+use single_char_xor_lib::{decrypt, Candidate};
 
 /*
  * Find a line encrypted with single-character XOR cipher
+ *
+ * Every line is decrypted with its best key and the line with the best score
+ * is selected.
  */
 fn find_encrypted_line(file: File) {
-    let mut reader = BufferedReader::new(file);
+    let reader = BufReader::new(file);
+    let mut best: Option<(usize, Candidate)> = None;
     for (n, line) in reader.lines().enumerate() {
-        let encrypted = line.unwrap().from_hex().unwrap();
-        match decrypt(encrypted.as_slice()) {
-            SingleCharKey::Found(key, decrypted) => {
-                println!("Found encrypted string at line {}:\n\
-                         Key  => '{}' ({})\n\
-                         Text => \"{}\"", n + 1, key as char, key,
-                         String::from_utf8(decrypted).unwrap());
+        let line = line.unwrap();
+        let encrypted = Encoding::Hex.decode(line.as_bytes()).unwrap();
+        let candidates = decrypt(encrypted.as_slice());
+        if let Some(candidate) = candidates.into_iter().next() {
+            let is_better = match best {
+                Some((_, (_, score, _))) => candidate.1 > score,
+                None => true
+            };
+            if is_better {
+                best = Some((n, candidate));
             }
-            SingleCharKey::NotFound => ()
         }
     }
+    match best {
+        Some((n, (key, score, decrypted))) => {
+            println!("Found encrypted string at line {}:\n\
+                     Key   => '{}' ({})\n\
+                     Score => {}\n\
+                     Text  => \"{}\"", n + 1, key as char, key, score,
+                     String::from_utf8_lossy(decrypted.as_slice()));
+        }
+        None => println!("No encrypted string found")
+    }
 }
 
 /*
  * Main entry point
  */
 fn main() {
-    let path = Path::new("strings.txt");
-    match File::open(&path) {
+    match File::open("strings.txt") {
         Ok(file) => find_encrypted_line(file),
         Err(err) => panic!("Unable to open strings.txt: {}", err)
     }
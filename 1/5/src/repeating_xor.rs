@@ -3,10 +3,9 @@
  * Dmitry Vasiliev <dima@hlabs.org>
  */
 
-extern crate serialize;
+extern crate codec;
 
-#[cfg(not(test))]
-use serialize::hex::ToHex;
+use codec::Encoding;
 
 /*
  * Encrypt buffer with mult-character key
@@ -18,7 +17,6 @@ fn xor_by_key(buffer: &[u8], key: &[u8]) -> Vec<u8> {
 /*
  * Main entry point
  */
-#[cfg(not(test))]
 fn main() {
     let key = b"ICE";
     let input = b"Burning 'em, if you ain't quick and nimble\n\
@@ -27,7 +25,7 @@ fn main() {
               Input => {:?}",
               key, input);
     let encrypted = xor_by_key(input, key);
-    println!("Encrypted => \"{}\"", encrypted.to_hex());
+    println!("Encrypted => \"{}\"", Encoding::Hex.encode(&encrypted));
     let decrypted = xor_by_key(encrypted.as_slice(), key);
     println!("Decrypted => \"{}\"", String::from_utf8(decrypted).unwrap());
 }
@@ -37,8 +35,8 @@ fn main() {
  */
 #[cfg(test)]
 mod test {
+    use codec::Encoding;
     use super::xor_by_key;
-    use serialize::hex::ToHex;
 
     #[test]
     fn test_xor_by_key() {
@@ -46,7 +44,7 @@ mod test {
         let input = b"Burning 'em, if you ain't quick and nimble\n\
                       I go crazy when I hear a cymbal";
         let encrypted = xor_by_key(input, key);
-        assert_eq!(encrypted.to_hex().as_slice(),
+        assert_eq!(Encoding::Hex.encode(&encrypted),
                    "0b3637272a2b2e63622c2e69692a23693a2a3c\
                     6324202d623d63343c2a26226324272765272a\
                     282b2f20430a652e2c652a3124333a653e2b20\
@@ -3,21 +3,21 @@
  * Dmitry Vasiliev <dima@hlabs.org>
  */
 
-extern crate serialize;
+extern crate codec;
 
 extern crate aes_lib;
 
-use std::path::Path;
-use std::io::fs::File;
+use std::fs::File;
+use std::io::Read;
 
-use serialize::base64::FromBase64;
+use codec::{Encoding, STANDARD};
 
 use aes_lib::decrypt_aes_ecb;
 
 fn decrypt_aes_ecb_file(mut file: File, key: &[u8]) -> Vec<u8> {
-    let data = file.read_to_end().unwrap();
-    let text = String::from_utf8(data).unwrap();
-    let encrypted = text.from_base64().unwrap();
+    let mut data = Vec::new();
+    file.read_to_end(&mut data).unwrap();
+    let encrypted = Encoding::Base64(STANDARD).decode(&data).unwrap();
     decrypt_aes_ecb(encrypted.as_slice(), key)
 }
 
@@ -26,8 +26,7 @@ fn decrypt_aes_ecb_file(mut file: File, key: &[u8]) -> Vec<u8> {
  */
 fn main() {
     let key = b"YELLOW SUBMARINE";
-    let path = Path::new("aes_ecb_encrypted.txt");
-    let decrypted = match File::open(&path) {
+    let decrypted = match File::open("aes_ecb_encrypted.txt") {
         Ok(file) => decrypt_aes_ecb_file(file, key),
         Err(err) => panic!("Unable to open aes_ecb_encrypted.txt: {}", err)
     };
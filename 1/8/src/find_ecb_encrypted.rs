@@ -3,90 +3,58 @@
  * Dmitry Vasiliev <dima@hlabs.org>
  */
 
-extern crate serialize;
+extern crate codec;
+extern crate aes_lib;
 
-use std::collections::HashSet;
-#[cfg(not(test))]
 use std::path::Path;
-#[cfg(not(test))]
-use std::io::fs::File;
-#[cfg(not(test))]
-use std::io::BufferedReader;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
 
-#[cfg(not(test))]
-use serialize::hex::FromHex;
+use codec::Encoding;
+use aes_lib::AES_BLOCK_SIZE;
+use aes_lib::ecb::{rank_lines, BlockAnalysis};
 
-#[cfg(not(test))]
 enum ECBEncryptedLine {
-    Found(uint, String),
+    Found(usize, String, BlockAnalysis),
     NotFound
 }
 
 /*
  * Find 128 bit ECB encrypted line in a file
  */
-#[cfg(not(test))]
 fn find_ecb_encrypted_line(file: File) -> ECBEncryptedLine {
-    let mut reader = BufferedReader::new(file);
-    for (line_num, result) in reader.lines().enumerate() {
-        let line = result.unwrap();
-        let bin = line.as_slice().from_hex().unwrap();
-        if is_buffer_ecb_encrypted(bin.as_slice()) {
-            // Return the first found line.
-            return ECBEncryptedLine::Found(line_num + 1, line);
-        }
+    let reader = BufReader::new(file);
+    let lines: Vec<String> = reader.lines().map(|r| r.unwrap()).collect();
+    let bins: Vec<Vec<u8>> = lines.iter().map(|line| {
+        Encoding::Hex.decode(line.as_bytes()).unwrap()
+    }).collect();
+    // Return the line with the highest ECB score
+    match rank_lines(bins.as_slice(), AES_BLOCK_SIZE).into_iter().next() {
+        Some((i, analysis)) if analysis.score > 0.0 =>
+            ECBEncryptedLine::Found(i + 1, lines[i].clone(), analysis),
+        _ => ECBEncryptedLine::NotFound
     }
-    ECBEncryptedLine::NotFound
-}
-
-/*
- * Is buffer ECB encrypted?
- */
-#[inline]
-fn is_buffer_ecb_encrypted(buffer: &[u8]) -> bool {
-    static ECB_BLOCK_SIZE: uint = 16;
-    let mut blocks = HashSet::new();
-    buffer.chunks(ECB_BLOCK_SIZE).any(|b| !blocks.insert(b))
 }
 
 /*
  * Main entry point
  */
-#[cfg(not(test))]
 fn main() {
     let path = Path::new("ciphertexts.txt");
-    let result = match File::open(&path) {
+    let result = match File::open(path) {
         Ok(file) => find_ecb_encrypted_line(file),
         Err(err) => panic!("Unable to open {}: {}", path.display(), err)
     };
     match result {
-        ECBEncryptedLine::Found(line_num, text) =>
+        ECBEncryptedLine::Found(line_num, text, analysis) => {
             println!("Found ECB encrypted text at line {}: {}",
-                     line_num, text),
+                     line_num, text);
+            for repeated in analysis.repeated.iter() {
+                println!("Block repeated at positions {:?}",
+                         repeated.positions);
+            }
+            println!("Score: {:.2}", analysis.score);
+        },
         ECBEncryptedLine::NotFound => println!("No ECB encrypted text found")
     }
 }
-
-/*
- * Tests
- */
-#[cfg(test)]
-mod test {
-    use super::is_buffer_ecb_encrypted;
-    use serialize::hex::FromHex;
-
-    #[test]
-    fn test_is_buffer_ecb_encrypted() {
-        let buffer = "000102030405060708090a0b0c0d0e0f\
-                      101112131415161718191a1b1c1d1e1f\
-                      202122232425262728292a2b2c2d2e2f";
-        let bin = buffer.as_slice().from_hex().unwrap();
-        assert_eq!(is_buffer_ecb_encrypted(bin.as_slice()), false);
-
-        let buffer = "000102030405060708090a0b0c0d0e0f\
-                      101112131415161718191a1b1c1d1e1f\
-                      000102030405060708090a0b0c0d0e0f";
-        let bin = buffer.as_slice().from_hex().unwrap();
-        assert_eq!(is_buffer_ecb_encrypted(bin.as_slice()), true);
-    }
-}
@@ -3,25 +3,23 @@
  * Dmitry Vasiliev <dima@hlabs.org>
  */
 
-extern crate serialize;
-
 extern crate aes_lib;
+extern crate codec;
 
+use std::fs;
 use std::path::Path;
-use std::io::fs::File;
-
-use serialize::base64::FromBase64;
 
 use aes_lib::{decrypt_aes_cbc, encrypt_aes_cbc};
+use codec::{Encoding, STANDARD};
 
-fn read_hex_file(path: &Path) -> Vec<u8> {
-    match File::open(path) {
-        Ok(mut file) => {
-            let data = file.read_to_end().unwrap();
-            let text = String::from_utf8(data).unwrap();
-            text.from_base64().unwrap()
-        },
+fn read_base64_file(path: &Path) -> Vec<u8> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
         Err(err) => panic!("Unable to open {}: {}", path.display(), err)
+    };
+    match Encoding::Base64(STANDARD).decode(data.as_slice()) {
+        Ok(decoded) => decoded,
+        Err(err) => panic!("Invalid data in {}: {}", path.display(), err)
     }
 }
 
@@ -30,14 +28,13 @@ fn read_hex_file(path: &Path) -> Vec<u8> {
  */
 fn main() {
     let path = Path::new("10.txt");
-    let data = read_hex_file(&path);
+    let data = read_base64_file(path);
     let key = b"YELLOW SUBMARINE";
     let iv: Vec<u8> = [0u8; 16].to_vec();
-    let decrypted = decrypt_aes_cbc(data.as_slice(), key.as_slice(),
-                                    iv.as_slice());
+    let decrypted = decrypt_aes_cbc(data.as_slice(), key, iv.as_slice());
     println!("Decrypted => \"{}\"",
              String::from_utf8(decrypted.clone()).unwrap());
-    assert_eq!(data, encrypt_aes_cbc(decrypted.as_slice(), key.as_slice(),
+    assert_eq!(data, encrypt_aes_cbc(decrypted.as_slice(), key,
                                      iv.as_slice()));
     println!("Encryption OK!");
 }
@@ -4,23 +4,25 @@
  */
 
 extern crate aes_lib;
+extern crate rand;
 
-use std::rand::{random, thread_rng, Rng, Rand};
-use std::fmt;
-use std::fmt::{Show, Formatter};
-use std::collections::HashSet;
-use std::io::File;
+use std::fs;
+
+use rand::{random, thread_rng, Rng};
+use rand::distributions::{Distribution, Standard};
 
 use aes_lib::{AES_BLOCK_SIZE, encrypt_aes_ecb, encrypt_aes_cbc};
+use aes_lib::ecb::best_alignment;
 
-#[derive(PartialEq, Eq)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Eq, Debug)]
 enum Mode {
     ECB,
     CBC
 }
 
-impl Rand for Mode {
-    fn rand<R: Rng>(rng: &mut R) -> Mode {
+impl Distribution<Mode> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Mode {
         match rng.gen() {
             true => Mode::ECB,
             false => Mode::CBC
@@ -28,30 +30,21 @@ impl Rand for Mode {
     }
 }
 
-impl Show for Mode {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        match *self {
-            Mode::ECB => write!(f, "ECB"),
-            Mode::CBC => write!(f, "CBC"),
-        }
-    }
-}
-
 #[inline]
 fn random_bytes(len: usize) -> Vec<u8> {
-    range(0, len).map(|_| random::<u8>()).collect()
+    (0..len).map(|_| random::<u8>()).collect()
 }
 
 #[inline]
 fn random_uint(low: usize, high: usize) -> usize {
-    thread_rng().gen_range(low, high)
+    thread_rng().gen_range(low..high)
 }
 
 fn aes_oracle(input: &[u8]) -> (Vec<u8>, Mode) {
     let key = random_bytes(AES_BLOCK_SIZE);
     let prepend = random_bytes(random_uint(5, 10));
     let append = random_bytes(random_uint(5, 10));
-    let data = prepend + input + append.as_slice();
+    let data = [prepend.as_slice(), input, append.as_slice()].concat();
     match random::<Mode>() {
         Mode::ECB =>
             (encrypt_aes_ecb(data.as_slice(), key.as_slice()), Mode::ECB),
@@ -63,16 +56,19 @@ fn aes_oracle(input: &[u8]) -> (Vec<u8>, Mode) {
     }
 }
 
+/*
+ * The random prefix can shift the repeated blocks so all the alignments are
+ * tried
+ */
 fn guess_aes_mode(buffer: &[u8]) -> Mode {
-    let mut blocks = HashSet::new();
-    match buffer.chunks(AES_BLOCK_SIZE).any(|b| !blocks.insert(b)) {
+    match best_alignment(buffer, AES_BLOCK_SIZE).score > 0.0 {
         true => Mode::ECB,
         false => Mode::CBC
     }
 }
 
 fn read_example_text() -> Vec<u8> {
-    File::open(&Path::new("text.txt")).read_to_end().unwrap()
+    fs::read("text.txt").unwrap()
 }
 
 fn main() {
@@ -4,12 +4,26 @@
  */
 
 extern crate aes_lib;
+extern crate codec;
+extern crate rand;
 
-use std::rand::random;
+use rand::random;
 
 use aes_lib::{AES_BLOCK_SIZE, decrypt_aes_ecb, encrypt_aes_ecb};
+use codec::kv::{self, Duplicates, ParseError};
 
 
+// Known plaintext of the encoded profile around the email
+struct Layout {
+    before: &'static str,
+    after: &'static str,
+    role: &'static str
+}
+
+static PROFILE_LAYOUT: Layout = Layout{before: "email=",
+                                       after: "&uid=10&role=",
+                                       role: "user"};
+
 struct Profile {
     key: Vec<u8>,
 }
@@ -17,65 +31,90 @@ struct Profile {
 impl Profile {
     fn new() -> Profile {
         let key = random_bytes(AES_BLOCK_SIZE);
-        Profile{key: key}
+        Profile{key}
     }
 
     fn profile_for(&self, email: &str) -> Vec<u8> {
-        if email.contains("&") || email.contains("=") {
-            panic!("Invalid character in email: {}", email);
-        }
         let map = [("email".to_string(), email.to_string()),
                    ("uid".to_string(), "10".to_string()),
                    ("role".to_string(), "user".to_string())];
-        let encoded = encode_kv(&map);
-        encrypt_aes_ecb(encoded.as_slice(), self.key.as_slice())
+        let encoded = kv::encode(&map);
+        encrypt_aes_ecb(encoded.as_bytes(), self.key.as_slice())
     }
 
-    fn decrypt(&self, encrypted: &[u8]) -> Vec<(String, String)> {
+    fn decrypt(&self, encrypted: &[u8])
+            -> Result<Vec<(String, String)>, ParseError> {
         let encoded = decrypt_aes_ecb(encrypted, self.key.as_slice());
-        parse_kv(encoded.as_slice())
-    }
-
-    #[cfg(not(test))]
-    fn make_admin_profile(&self) -> Vec<u8> {
-        let encrypted = self.profile_for("foooo@bar.com");
-        let enc_admin = self.profile_for(concat!("f@bar.com.",
-            "admin\x0b\x0b\x0b\x0b\x0b\x0b\x0b\x0b\x0b\x0b\x0b"));
-        encrypted.slice_to(32).to_vec() + enc_admin.slice(16, 32)
+        kv::parse(encoded.as_slice(), Duplicates::Reject)
     }
 }
 
 fn random_bytes(len: usize) -> Vec<u8> {
-    range(0, len).map(|_| random::<u8>()).collect()
+    (0..len).map(|_| random::<u8>()).collect()
 }
 
-fn parse_kv(string: &[u8]) -> Vec<(String, String)> {
-    let kv = string.split(|&c| c == b'&');
-    kv.map(|kv| {
-        let pair: Vec<&[u8]> = kv.split(|&c| c == b'=').collect();
-        (String::from_utf8(pair[0].to_vec()).unwrap(),
-         String::from_utf8(pair[1].to_vec()).unwrap())
-        }).collect()
+#[inline]
+fn fill(len: usize) -> String {
+    "a".repeat(len)
 }
 
-fn encode_kv(map: &[(String, String)]) -> Vec<u8> {
-    let mut bytes = Vec::new();
-    for item in map.iter() {
-        bytes.push_all(item.0.clone().into_bytes().as_slice());
-        bytes.push(b'=');
-        bytes.push_all(item.1.clone().into_bytes().as_slice());
-        bytes.push(b'&');
+/*
+ * Number of bytes which align the data length to the block boundary
+ */
+#[inline]
+fn align(len: usize, block_size: usize) -> usize {
+    (block_size - len % block_size) % block_size
+}
+
+/*
+ * Make the encrypted profile with the role
+ *
+ * The first profile has the email which puts the start of the role value at
+ * the block boundary, its blocks before the role value are taken. The second
+ * profile has the email which starts at the block boundary and consists of
+ * the role value with padding, the blocks of the email are pasted after the
+ * blocks of the first profile. The role should have no reserved bytes since
+ * they are quoted inside the email.
+ */
+fn cut_and_paste<F>(profile_for: F, layout: &Layout, role: &str,
+                    block_size: usize) -> Option<Vec<u8>>
+        where F: Fn(&str) -> Vec<u8> {
+    // Check if the aligned profile is padded by the full block
+    let known = layout.before.len() + layout.after.len() + layout.role.len();
+    let len = align(known, block_size);
+    let full_padding = profile_for(&fill(len)).len() > known + len;
+
+    let mut value = role.as_bytes().to_vec();
+    let pad = align(value.len(), block_size);
+    match (pad, full_padding) {
+        (0, false) => (),
+        (0, true) => value.extend(vec![block_size as u8; block_size]),
+        _ => value.extend(vec![pad as u8; pad])
+    }
+    let value = String::from_utf8(value).ok()?;
+    if kv::quote(&value) != value {
+        return None;
     }
-    // Remove last '&'
-    bytes.pop();
-    bytes
+    let len = align(layout.before.len(), block_size);
+    let start = layout.before.len() + len;
+    let email = fill(len) + &value;
+    let pasted = profile_for(&email)[start..start + value.len()].to_vec();
+
+    let len = align(layout.before.len() + layout.after.len(), block_size);
+    let end = layout.before.len() + len + layout.after.len();
+    let cut = profile_for(&fill(len))[..end].to_vec();
+    Some([cut, pasted].concat())
 }
 
-#[cfg(not(test))]
 fn main() {
     let profile = Profile::new();
-    let encrypted = profile.make_admin_profile();
-    println!("Decrypted: {:?}", profile.decrypt(encrypted.as_slice()));
+    let encrypted = cut_and_paste(|email| profile.profile_for(email),
+                                  &PROFILE_LAYOUT, "admin", AES_BLOCK_SIZE)
+        .unwrap();
+    match profile.decrypt(encrypted.as_slice()) {
+        Ok(pairs) => println!("Decrypted: {:?}", pairs),
+        Err(err) => println!("Invalid profile: {}", err)
+    }
 }
 
 /*
@@ -83,26 +122,33 @@ fn main() {
  */
 #[cfg(test)]
 mod test {
-    use super::{Profile, parse_kv, encode_kv};
+    use aes_lib::AES_BLOCK_SIZE;
+    use super::{Profile, PROFILE_LAYOUT, cut_and_paste};
 
     #[test]
-    fn test_parse_encode_kv() {
-        let map = [("foo".to_string(), "bar".to_string()),
-                   ("baz".to_string(), "qux".to_string()),
-                   ("zap".to_string(), "zazzle".to_string())];
-        let encoded: Vec<u8> = b"foo=bar&baz=qux&zap=zazzle".to_vec();
-        assert_eq!(map.to_vec(), parse_kv(encoded.as_slice()));
-        assert_eq!(encode_kv(&map), encoded);
+    fn test_profile() {
+        let profile = Profile::new();
+        let email = "foo@bar.com&role=admin";
+        let encrypted = profile.profile_for(email);
+        assert_eq!(profile.decrypt(encrypted.as_slice()),
+                   Ok([("email".to_string(), email.to_string()),
+                       ("uid".to_string(), "10".to_string()),
+                       ("role".to_string(), "user".to_string())].to_vec()));
     }
 
     #[test]
-    fn test_profile() {
+    fn test_cut_and_paste() {
         let profile = Profile::new();
-        let email = "foo@bar.com";
-        let encrypted = profile.profile_for(email.as_slice());
-        assert_eq!(profile.decrypt(encrypted.as_slice()),
-                   [("email".to_string(), email.to_string()),
-                    ("uid".to_string(), "10".to_string()),
-                    ("role".to_string(), "user".to_string())].to_vec());
+        let roles = ["admin", "", "0123456789abcdef", "administrator of all"];
+        for &role in roles.iter() {
+            let encrypted = cut_and_paste(|email| profile.profile_for(email),
+                                          &PROFILE_LAYOUT, role,
+                                          AES_BLOCK_SIZE).unwrap();
+            let pairs = profile.decrypt(encrypted.as_slice()).unwrap();
+            assert_eq!(pairs[2], ("role".to_string(), role.to_string()));
+        }
+        assert_eq!(cut_and_paste(|email| profile.profile_for(email),
+                                 &PROFILE_LAYOUT, "admin&uid=0",
+                                 AES_BLOCK_SIZE), None);
     }
 }
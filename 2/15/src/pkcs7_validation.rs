@@ -7,14 +7,14 @@ static AES_BLOCK_SIZE: u8 = 16;
 
 fn remove_pkcs7_padding(data: &[u8]) -> Option<&[u8]> {
     let len = data.len();
-    if (len == 0) || (len % AES_BLOCK_SIZE as usize != 0) {
+    if (len == 0) || !len.is_multiple_of(AES_BLOCK_SIZE as usize) {
         return None;
     }
     match data.last() {
         Some(&last) if last > 0 && last < AES_BLOCK_SIZE => {
             let data_len = len - last as usize;
-            match data.slice_from(data_len).iter().all(|&c| c == last) {
-                true => Some(data.slice_to(data_len)),
+            match data[data_len..].iter().all(|&c| c == last) {
+                true => Some(&data[..data_len]),
                 false => None
             }
         }
@@ -1,62 +1,126 @@
-/* AES CBC bitflipping attack
+/* AES CBC and CTR bitflipping attack
  *
  * Dmitry Vasiliev <dima@hlabs.org>
  */
 
 extern crate aes_lib;
+extern crate codec;
+extern crate rand;
 
-use std::rand::random;
+use rand::random;
 
-use aes_lib::{AES_BLOCK_SIZE, encrypt_aes_cbc, decrypt_aes_cbc};
+use aes_lib::{AES_BLOCK_SIZE, encrypt_aes_ecb, decrypt_aes_ecb,
+              encrypt_aes_cbc, decrypt_aes_cbc, encrypt_aes_ctr,
+              decrypt_aes_ctr};
+use aes_lib::bitflip::{plan, Plan, PlanError};
+use aes_lib::oracle::Mode;
+use codec::{Encoding, COOKIE_RESERVED};
+
+static PREPEND: &str = "comment1=cooking%20MCs;userdata=";
+static APPEND: &str = ";comment2=%20like%20a%20pound%20of%20bacon";
 
 struct State {
+    mode: Mode,
     key: Vec<u8>,
     iv: Vec<u8>,
+    nonce: u64,
 }
 
 impl State {
-    fn new() -> State {
+    fn new(mode: Mode) -> State {
         let key = random_bytes(AES_BLOCK_SIZE);
         let iv = random_bytes(AES_BLOCK_SIZE);
-        State{key: key, iv: iv}
+        State{mode, key, iv, nonce: random()}
     }
 
     fn encrypt(&self, data: &str) -> Vec<u8> {
-        let quoted = data.replace(";", "%3B").replace("=", "%3D");
-        let prepend = "comment1=cooking%20MCs;userdata=";
-        let append = ";comment2=%20like%20a%20pound%20of%20bacon";
-        let to_enc = prepend.to_string() + quoted.as_slice() + append;
-        encrypt_aes_cbc(to_enc.into_bytes().as_slice(), self.key.as_slice(),
-                        self.iv.as_slice())
+        let quoted = Encoding::Percent(COOKIE_RESERVED)
+            .encode(data.as_bytes());
+        let to_enc = PREPEND.to_string() + quoted.as_str() + APPEND;
+        let key = self.key.as_slice();
+        match self.mode {
+            Mode::ECB => encrypt_aes_ecb(to_enc.as_bytes(), key),
+            Mode::CBC =>
+                encrypt_aes_cbc(to_enc.as_bytes(), key, self.iv.as_slice()),
+            Mode::CTR => encrypt_aes_ctr(to_enc.as_bytes(), key, self.nonce)
+        }
     }
 
     fn decrypt(&self, encrypted: &[u8]) -> Vec<u8> {
-        decrypt_aes_cbc(encrypted, self.key.as_slice(), self.iv.as_slice())
+        let key = self.key.as_slice();
+        match self.mode {
+            Mode::ECB => decrypt_aes_ecb(encrypted, key),
+            Mode::CBC => decrypt_aes_cbc(encrypted, key, self.iv.as_slice()),
+            Mode::CTR => decrypt_aes_ctr(encrypted, key, self.nonce)
+        }
     }
 
     fn is_admin(&self, dec: &[u8]) -> bool {
         let s = String::from_utf8_lossy(dec);
-        s.as_slice().contains(";admin=true;")
+        s.contains(";admin=true;")
     }
 }
 
-fn random_bytes(len: uint) -> Vec<u8> {
-    range(0, len).map(|_| random::<u8>()).collect()
+fn random_bytes(len: usize) -> Vec<u8> {
+    (0..len).map(|_| random::<u8>()).collect()
 }
 
-fn add_admin(state: &State) -> Vec<u8> {
-    let mut enc = state.encrypt("1234567890123456?admin?true");
-    // Replace first '?' (0x3f) with ';' (0x3b)
-    enc[32] ^= 4;
-    // Replace second '?' (0x3f) with '=' (0x3d)
-    enc[38] ^= 2;
-    enc
+/*
+ * The filler block is scrambled by the CBC flips so the admin block isn't
+ * affected
+ */
+fn add_admin(state: &State) -> Result<(Vec<u8>, Plan), PlanError> {
+    let filler = "A".repeat(AES_BLOCK_SIZE);
+    let mut enc = state.encrypt((filler + "?admin?true").as_str());
+    let plan = plan(state.mode, PREPEND.len() + AES_BLOCK_SIZE,
+                    b"?admin?true", b";admin=true", AES_BLOCK_SIZE)?;
+    plan.apply(enc.as_mut_slice());
+    Ok((enc, plan))
 }
 
 fn main() {
-    let state = State::new();
-    let enc = add_admin(&state);
-    let dec = state.decrypt(enc.as_slice());
-    println!("Decrypted: {}", String::from_utf8_lossy(dec.as_slice()));
-    println!("Is admin? {}", state.is_admin(dec.as_slice()));
+    for mode in [Mode::CBC, Mode::CTR] {
+        let state = State::new(mode);
+        let (enc, plan) = match add_admin(&state) {
+            Ok(result) => result,
+            Err(err) => panic!("Unable to plan the attack: {}", err)
+        };
+        let dec = state.decrypt(enc.as_slice());
+        println!("{:?} flips: {:?}", mode, plan.flips);
+        println!("Scrambled blocks: {:?}", plan.scrambled);
+        println!("Decrypted: {}", String::from_utf8_lossy(dec.as_slice()));
+        println!("Is admin? {}", state.is_admin(dec.as_slice()));
+    }
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod test {
+    use aes_lib::oracle::Mode;
+    use aes_lib::bitflip::PlanError;
+    use super::{add_admin, State};
+
+    #[test]
+    fn test_add_admin() {
+        for mode in [Mode::CBC, Mode::CTR] {
+            let state = State::new(mode);
+            let enc = state.encrypt(";admin=true;");
+            assert!(!state.is_admin(state.decrypt(enc.as_slice()).as_slice()));
+            let (enc, _) = add_admin(&state).unwrap();
+            assert!(state.is_admin(state.decrypt(enc.as_slice()).as_slice()));
+        }
+        // ECB blocks are independent so the flips can't be planned
+        let state = State::new(Mode::ECB);
+        let enc = state.encrypt("data");
+        assert!(String::from_utf8_lossy(&state.decrypt(enc.as_slice()))
+                .contains("userdata=data;"));
+        // The user data is quoted like the rest of the cookie
+        let enc = state.encrypt("a b;c=100%");
+        assert!(String::from_utf8_lossy(&state.decrypt(enc.as_slice()))
+                .contains("userdata=a%20b%3Bc%3D100%25;"));
+        assert_eq!(add_admin(&state).err(),
+                   Some(PlanError::UnsupportedMode(Mode::ECB)));
+    }
 }
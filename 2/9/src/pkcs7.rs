@@ -3,7 +3,7 @@
  * Dmitry Vasiliev <dima@hlabs.org>
  */
 
-use std::iter::repeat;
+use std::iter::repeat_n;
 
 
 fn pkcs7(data: &[u8], size: u8) -> Vec<u8> {
@@ -15,7 +15,7 @@ fn pkcs7(data: &[u8], size: u8) -> Vec<u8> {
         r
     } else {
         let pad = size - len as u8;
-        r.extend(repeat(pad).take(pad as usize));
+        r.extend(repeat_n(pad, pad as usize));
         r
     }
 }
@@ -23,7 +23,6 @@ fn pkcs7(data: &[u8], size: u8) -> Vec<u8> {
 /*
  * Main entry point
  */
-#[cfg(not(test))]
 fn main() {
     let input = b"YELLOW SUBMARINE";
     println!("Input     => {:?}", input);
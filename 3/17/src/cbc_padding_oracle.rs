@@ -3,124 +3,75 @@
  * Dmitry Vasiliev <dima@hlabs.org>
  */
 
-extern crate serialize;
+extern crate codec;
+extern crate rand;
 
 extern crate aes_lib;
 
-use serialize::base64::FromBase64;
-use std::rand::random;
-use std::iter::repeat;
-
-use aes_lib::{AES_BLOCK_SIZE, encrypt_aes_cbc, decrypt_aes_cbc_raw,
-    remove_pkcs7_padding};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
 
+use codec::{Encoding, STANDARD};
+use rand::random;
 
-static LINES: [&'static str; 10] = [
-    "MDAwMDAwTm93IHRoYXQgdGhlIHBhcnR5IGlzIGp1bXBpbmc=",
-    "MDAwMDAxV2l0aCB0aGUgYmFzcyBraWNrZWQgaW4gYW5kIHRoZSBWZWdhJ3MgYXJlIHB1bXBpbic=",
-    "MDAwMDAyUXVpY2sgdG8gdGhlIHBvaW50LCB0byB0aGUgcG9pbnQsIG5vIGZha2luZw==",
-    "MDAwMDAzQ29va2luZyBNQydzIGxpa2UgYSBwb3VuZCBvZiBiYWNvbg==",
-    "MDAwMDA0QnVybmluZyAnZW0sIGlmIHlvdSBhaW4ndCBxdWljayBhbmQgbmltYmxl",
-    "MDAwMDA1SSBnbyBjcmF6eSB3aGVuIEkgaGVhciBhIGN5bWJhbA==",
-    "MDAwMDA2QW5kIGEgaGlnaCBoYXQgd2l0aCBhIHNvdXBlZCB1cCB0ZW1wbw==",
-    "MDAwMDA3SSdtIG9uIGEgcm9sbCwgaXQncyB0aW1lIHRvIGdvIHNvbG8=",
-    "MDAwMDA4b2xsaW4nIGluIG15IGZpdmUgcG9pbnQgb2g=",
-    "MDAwMDA5aXRoIG15IHJhZy10b3AgZG93biBzbyBteSBoYWlyIGNhbiBibG93",
-    ];
+use aes_lib::{AES_BLOCK_SIZE, encrypt_aes_cbc, decrypt_aes_cbc,
+    remove_pkcs7_padding};
+use aes_lib::padding_oracle::{PaddingOracle, Strategy, decrypt_with,
+                              encrypt, is_cbc_padding_valid};
 
 struct State {
     key: Vec<u8>,
     iv: Vec<u8>,
+    // Base64 encoded lines
+    lines: Vec<String>
 }
 
 impl State {
-    fn new() -> State {
+    fn new(lines: Vec<String>) -> State {
         let key = random_bytes(AES_BLOCK_SIZE);
         let iv = random_bytes(AES_BLOCK_SIZE);
-        State{key: key, iv: iv}
+        State{key, iv, lines}
     }
 
     fn encrypt(&self) -> Vec<u8> {
-        let i = random::<u8>() % LINES.len() as u8;
-        let line = LINES[i as uint].from_base64().unwrap();
+        let i = random::<usize>() % self.lines.len();
+        let line = Encoding::Base64(STANDARD)
+            .decode(self.lines[i].as_bytes()).unwrap();
         encrypt_aes_cbc(line.as_slice(), self.key.as_slice(),
                         self.iv.as_slice())
     }
+}
 
+impl PaddingOracle for State {
     fn is_padding_valid(&self, encrypted: &[u8]) -> bool {
-        let dec = decrypt_aes_cbc_raw(encrypted, self.key.as_slice(),
-                                      self.iv.as_slice());
-        match dec.last() {
-            Some(&last) if last > 0 && (last as uint) <= AES_BLOCK_SIZE => {
-                let data_len = dec.len() - last as uint;
-                dec.slice_from(data_len).iter().all(|&c| c == last)
-            }
-            _ => false
-        }
-    }
-
-    fn decrypt(&self, enc: &[u8]) -> Vec<u8> {
-        let blocks: Vec<Vec<u8>> = enc.chunks(AES_BLOCK_SIZE).map(
-            |block| self.decrypt_block(block)).collect();
-        let enc_it = self.iv.iter().chain(enc.iter());
-        let dec_it = blocks.iter().flat_map(|block| block.iter());
-        let dec = enc_it.zip(dec_it).map(|(&c1, &c2)| c1 ^ c2).collect();
-        remove_pkcs7_padding(dec)
-    }
-
-    fn decrypt_block(&self, block: &[u8]) -> Vec<u8> {
-        let mut dec: Vec<u8> = repeat(0u8).take(AES_BLOCK_SIZE).collect();
-        let mut tmp: Vec<u8> = repeat(0u8).take(AES_BLOCK_SIZE).chain(
-                                    block.iter().map(|&c| c)).collect();
-        let mut i = 1;
-        // Guess the last character
-        for c in range(0u16, 256) {
-            tmp[AES_BLOCK_SIZE - i] = c as u8;
-            if self.is_padding_valid(tmp.as_slice()) {
-                break;
-            }
-        }
-        // Guess the size of the padding
-        while i < AES_BLOCK_SIZE {
-            let is_padding_longer = range(0u16, 256).any(|c| {
-                tmp[AES_BLOCK_SIZE - i - 1] = c as u8;
-                !self.is_padding_valid(tmp.as_slice())
-                });
-            tmp[AES_BLOCK_SIZE - i - 1] = 0;
-            if !is_padding_longer {
-                break;
-            }
-            i += 1;
-        }
-        for j in range(1, i + 1) {
-            dec[AES_BLOCK_SIZE - j] = i as u8 ^ tmp[AES_BLOCK_SIZE - j];
-        }
-        // Guess all the characters
-        for j in range(i, AES_BLOCK_SIZE) {
-            let next = j + 1;
-            for k in range(1, next) {
-                let c = tmp[AES_BLOCK_SIZE - k];
-                tmp[AES_BLOCK_SIZE - k] = c ^ j as u8 ^ next as u8;
-            }
-            for c in range(0u16, 256) {
-                tmp[AES_BLOCK_SIZE - next] = c as u8;
-                if self.is_padding_valid(tmp.as_slice()) {
-                    dec[AES_BLOCK_SIZE - next] = next as u8 ^ c as u8;
-                    break;
-                }
-            }
-        }
-        dec
+        is_cbc_padding_valid(encrypted, self.key.as_slice(),
+                             self.iv.as_slice())
     }
 }
 
-fn random_bytes(len: uint) -> Vec<u8> {
-    range(0, len).map(|_| random::<u8>()).collect()
+fn random_bytes(len: usize) -> Vec<u8> {
+    (0..len).map(|_| random::<u8>()).collect()
 }
 
 fn main() {
-    let state = State::new();
+    let file = BufReader::new(File::open("lines.txt").unwrap());
+    let lines: Vec<String> = file.lines().map(|line| line.unwrap())
+        .collect();
+    let state = State::new(lines);
     let enc = state.encrypt();
-    let dec = state.decrypt(enc.as_slice());
+    // Likely text first, the blocks are decrypted concurrently
+    let strategy = Strategy{threads: 4, ..Strategy::english()};
+    let outcome = decrypt_with(&state, state.iv.as_slice(), enc.as_slice(),
+                               &strategy);
+    let dec = remove_pkcs7_padding(outcome.value.unwrap());
     println!("Decrypted: {}", String::from_utf8_lossy(dec.as_slice()));
+    println!("Queries: {}", outcome.queries);
+
+    // Encrypt the chosen plaintext (CBC-R), the first block is the IV
+    let outcome = encrypt(&state, b"Chosen by the attacker", AES_BLOCK_SIZE);
+    let forged = outcome.value.unwrap();
+    let (iv, data) = forged.split_at(AES_BLOCK_SIZE);
+    let dec = decrypt_aes_cbc(data, state.key.as_slice(), iv);
+    println!("Forged: {}", String::from_utf8_lossy(dec.as_slice()));
+    println!("Queries: {}", outcome.queries);
 }
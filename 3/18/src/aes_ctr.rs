@@ -3,20 +3,21 @@
  * Dmitry Vasiliev <dima@hlabs.org>
  */
 
-extern crate serialize;
+extern crate codec;
 
 extern crate aes_lib;
 
-use serialize::base64::FromBase64;
+use codec::{Encoding, STANDARD};
 
 use aes_lib::{decrypt_aes_ctr, encrypt_aes_ctr};
 
 
-static ENCODED: &'static str =
+static ENCODED: &str =
     "L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==";
 
 fn main() {
-    let encrypted = ENCODED.from_base64().unwrap();
+    let encrypted = Encoding::Base64(STANDARD).decode(ENCODED.as_bytes())
+        .unwrap();
     let key = b"YELLOW SUBMARINE";
     let nonce = 0u64;
     let dec = decrypt_aes_ctr(encrypted.as_slice(), key, nonce);
@@ -1,99 +1,108 @@
 /* Breaking fixed nonce CTR mode
+ *
+ * Usage: fixed_nonce_ctr_substitutions [LINE:OFFSET:TEXT]...
+ *
+ * The arguments are the plaintext guesses for the uncertain key bytes.
  *
  * Dmitry Vasiliev <dima@hlabs.org>
  */
 
-extern crate serialize;
+extern crate codec;
+extern crate rand;
 
 extern crate aes_lib;
+extern crate single_char_xor_lib;
 
-use std::io::BufferedReader;
-use std::io::File;
-use std::rand::random;
-use std::iter::AdditiveIterator;
-use serialize::base64::FromBase64;
+use std::env;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::process;
+
+use codec::{Encoding, STANDARD};
+use rand::random;
 
 use aes_lib::{AES_BLOCK_SIZE, encrypt_aes_ctr};
+use single_char_xor_lib::{FixedNonceBreaker, GuessError, EnglishModel,
+                          Scoring};
 
 
 fn encrypt_texts() -> Vec<Vec<u8>> {
     let key = random_bytes(AES_BLOCK_SIZE);
-    let path = Path::new("texts.txt");
-    let mut file = BufferedReader::new(File::open(&path));
+    let file = BufReader::new(File::open("texts.txt").unwrap());
     file.lines().map(|line| {
-        let text = line.unwrap().from_base64().unwrap();
+        let line = line.unwrap();
+        let text = Encoding::Base64(STANDARD).decode(line.as_bytes())
+            .unwrap();
         encrypt_aes_ctr(text.as_slice(), key.as_slice(), 0)
     }).collect()
 }
 
-fn random_bytes(len: uint) -> Vec<u8> {
-    range(0, len).map(|_| random::<u8>()).collect()
-}
-
-/* Convert array of texts to array of columns
- */
-fn get_text_columns(texts: &[Vec<u8>]) -> Vec<Vec<u8>> {
-    let max = texts.iter().map(|t| t.len()).max().unwrap();
-    range(0, max).map(|i| {
-        texts.iter().filter_map(|text| {
-            text.get(i).cloned()
-        }).collect()
-    }).collect()
-}
-
-/* Number of lowercase or uppercase ASCII letters when we XOR current character
- * with all the other characters in the same column
- */
-fn char_score(c: u8, col: &[u8]) -> uint {
-    col.iter().map(|&c2|
-        match (c ^ c2) as char {
-            'a'...'z' | 'A'...'Z' => 1,
-            _ => 0
-        }
-    ).sum()
-}
-
-/* Return index of the possible space character
- */
-fn space_col_index(col: &[u8]) -> uint {
-    col.iter().enumerate().map(|(i, &c)| {
-        (char_score(c, col), i)
-    }).max().unwrap().1
+fn random_bytes(len: usize) -> Vec<u8> {
+    (0..len).map(|_| random::<u8>()).collect()
 }
 
-/* As the key is the same XOR between two encrypted texts is equal to XOR of
- * the plain texts.
- *
- * Also if we XOR an ASCII letter with the space character it will switch the
- * letter between lowercase and uppercase.
- *
- * So if we XOR a byte with other bytes in the same column and get lots of
- * lowercase or uppercase ASCII letters then probably current byte is the space
- * character.
+/*
+ * Parse the plaintext guess LINE:OFFSET:TEXT, the lines are numbered from 1
+ * as in the output
  */
-fn find_key(texts: &[Vec<u8>]) -> Vec<u8> {
-    let cols = get_text_columns(texts);
-    let mut key: Vec<u8> = cols.iter().map(|col| {
-        col[space_col_index(col.as_slice())] ^ ' ' as u8
-    }).collect();
-    // We don't have spaces as first characters but we can guess it from the
-    // context
-    key[0] = texts[0][0] ^ 'i' as u8;
-    key
-}
-
-fn decrypt_texts(texts: &[Vec<u8>], key: &[u8]) -> Vec<Vec<u8>> {
-    texts.iter().map(|text| {
-        text.iter().zip(key.iter()).map(|(&c, &k)| c ^ k).collect()
-    }).collect()
+fn parse_guess(arg: &str) -> Result<(usize, usize, String), String> {
+    let parts: Vec<&str> = arg.splitn(3, ':').collect();
+    let invalid = || format!("Invalid guess {:?}, expected LINE:OFFSET:TEXT",
+                             arg);
+    if parts.len() != 3 {
+        return Err(invalid());
+    }
+    match (parts[0].parse::<usize>(), parts[1].parse::<usize>()) {
+        (Ok(line), Ok(offset)) if line > 0 =>
+            Ok((line - 1, offset, parts[2].to_string())),
+        _ => Err(invalid())
+    }
 }
 
 fn main() {
     let encrypted = encrypt_texts();
-    let key = find_key(encrypted.as_slice());
-    let decrypted = decrypt_texts(encrypted.as_slice(), key.as_slice());
+    let model = EnglishModel::new(Scoring::LogLikelihood);
+    let mut breaker = FixedNonceBreaker::new(encrypted.as_slice(), &model);
+    // The guesses fix the uncertain key bytes, e.g. the tail of the longest
+    // line which has a single sample
+    for arg in env::args().skip(1) {
+        let result = parse_guess(arg.as_str()).and_then(|(line, off, text)| {
+            breaker.guess(line, off, text.as_bytes()).map_err(|err| {
+                match err {
+                    GuessError::NoCiphertext(_) =>
+                        format!("No ciphertext at line {}", line + 1),
+                    err => err.to_string()
+                }
+            })
+        });
+        if let Err(err) = result {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
+    let keystream = breaker.solve();
+    println!("Uncertain key bytes: {:?}", keystream.low_confidence(0.5));
+    let decrypted = keystream.decrypt(encrypted.as_slice());
     for (i, text) in decrypted.iter().enumerate() {
         println!("{}: \"{}\"", i + 1,
                  String::from_utf8_lossy(text.as_slice()));
     }
 }
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod test {
+    use super::parse_guess;
+
+    #[test]
+    fn test_parse_guess() {
+        assert_eq!(parse_guess("38:33:turn,"),
+                   Ok((37, 33, "turn,".to_string())));
+        assert_eq!(parse_guess("1:0:a:b"), Ok((0, 0, "a:b".to_string())));
+        assert!(parse_guess("0:0:I").is_err());
+        assert!(parse_guess("1:x:I").is_err());
+        assert!(parse_guess("1:0").is_err());
+    }
+}
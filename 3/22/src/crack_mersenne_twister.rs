@@ -4,18 +4,20 @@
  */
 
 extern crate libc;
+extern crate rand;
 
 extern crate mersenne_twister;
 
-use std::rand::random;
-use std::io::timer::sleep;
-use std::time::duration::Duration;
 use std::ptr;
+use std::thread::sleep;
+use std::time::Duration;
+
 use libc::time_t;
+use rand::random;
 
 use mersenne_twister::MersenneTwister;
 
-extern {
+extern "C" {
     fn time(tloc: *const time_t) -> time_t;
 }
 
@@ -24,29 +26,28 @@ fn timestamp() -> time_t {
 }
 
 fn get_random_value() -> (u32, time_t) {
-    sleep(Duration::seconds(random::<u8>() as i64));
+    sleep(Duration::from_secs(random::<u8>() as u64));
     let seed = timestamp();
     let mut rng = MersenneTwister::new(seed);
-    sleep(Duration::seconds(random::<u8>() as i64));
+    sleep(Duration::from_secs(random::<u8>() as u64));
     (rng.rand_u32(), seed)
 }
 
 fn guess_seed(val: u32) -> Option<time_t> {
     let mut seed = timestamp();
-    for _ in range(0u64, 1000000) {
+    for _ in 0u64..1000000 {
         let mut rng = MersenneTwister::new(seed);
         if rng.rand_u32() == val {
             return Some(seed);
         }
         seed -= 1;
     }
-    return None
+    None
 }
 
 /*
  * Main entry point
  */
-#[cfg(not(test))]
 fn main() {
     let (val, exp_seed) = get_random_value();
     println!("Random value: {}, Expected seed: {}", val, exp_seed);
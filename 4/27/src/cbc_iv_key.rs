@@ -0,0 +1,148 @@
+/* Recover the key from CBC with IV=Key
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+extern crate aes_lib;
+extern crate codec;
+extern crate rand;
+
+use std::fmt;
+
+use rand::random;
+
+use aes_lib::{AES_BLOCK_SIZE, encrypt_aes_cbc, decrypt_aes_cbc_raw,
+              remove_pkcs7_padding};
+use codec::{Encoding, COOKIE_RESERVED};
+
+static PREPEND: &str = "comment1=cooking%20MCs;userdata=";
+static APPEND: &str = ";comment2=%20like%20a%20pound%20of%20bacon";
+
+#[derive(Debug)]
+enum DecryptError {
+    // The raw plaintext which contains non-ASCII bytes
+    InvalidAscii(Vec<u8>)
+}
+
+impl fmt::Display for DecryptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecryptError::InvalidAscii(ref plaintext) =>
+                write!(f, "Invalid ASCII in plaintext: {:?}",
+                       String::from_utf8_lossy(plaintext))
+        }
+    }
+}
+
+struct State {
+    key: Vec<u8>,
+    iv: Vec<u8>
+}
+
+impl State {
+    /*
+     * The key is reused as IV if iv_key is true
+     */
+    fn new(iv_key: bool) -> State {
+        let key = random_bytes(AES_BLOCK_SIZE);
+        let iv = match iv_key {
+            true => key.clone(),
+            false => random_bytes(AES_BLOCK_SIZE)
+        };
+        State{key, iv}
+    }
+
+    fn encrypt(&self, data: &str) -> Vec<u8> {
+        let quoted = Encoding::Percent(COOKIE_RESERVED)
+            .encode(data.as_bytes());
+        let to_enc = PREPEND.to_string() + quoted.as_str() + APPEND;
+        encrypt_aes_cbc(to_enc.as_bytes(), self.key.as_slice(),
+                        self.iv.as_slice())
+    }
+
+    /*
+     * Decrypt and validate the plaintext, the invalid plaintext is returned
+     * with the error
+     */
+    fn decrypt(&self, encrypted: &[u8]) -> Result<Vec<u8>, DecryptError> {
+        let dec = decrypt_aes_cbc_raw(encrypted, self.key.as_slice(),
+                                      self.iv.as_slice());
+        if !dec.is_ascii() {
+            return Err(DecryptError::InvalidAscii(dec));
+        }
+        Ok(remove_pkcs7_padding(dec))
+    }
+}
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    (0..len).map(|_| random::<u8>()).collect()
+}
+
+/*
+ * Send C1, 0, C1 followed by the rest of the ciphertext, the first plaintext
+ * block is P1 = D(C1) ^ IV and the third one is D(C1) so IV = P1 ^ P3.
+ * The IV is the key if it decrypts the original ciphertext to ASCII.
+ */
+fn recover_key(state: &State) -> Option<Vec<u8>> {
+    let bs = AES_BLOCK_SIZE;
+    let enc = state.encrypt("");
+    if enc.len() < 3 * bs {
+        return None;
+    }
+    let first = &enc[..bs];
+    let modified = [first, vec![0; bs].as_slice(), first, &enc[3 * bs..]]
+        .concat();
+    let dec = match state.decrypt(modified.as_slice()) {
+        Err(DecryptError::InvalidAscii(dec)) => dec,
+        Ok(_) => return None
+    };
+    let key: Vec<u8> = dec[..bs].iter().zip(dec[2 * bs..3 * bs].iter())
+        .map(|(&c1, &c2)| c1 ^ c2).collect();
+    let check = decrypt_aes_cbc_raw(enc.as_slice(), key.as_slice(),
+                                    key.as_slice());
+    match check.starts_with(PREPEND.as_bytes()) {
+        true => Some(key),
+        false => None
+    }
+}
+
+fn main() {
+    let state = State::new(true);
+    let modified = vec![0; 3 * AES_BLOCK_SIZE];
+    if let Err(err) = state.decrypt(modified.as_slice()) {
+        println!("Oracle error: {}", err);
+    }
+    match recover_key(&state) {
+        Some(key) => {
+            println!("Recovered key: {:?}", key);
+            println!("Is valid? {}", key == state.key);
+        },
+        None => println!("Unable to recover the key")
+    }
+    let state = State::new(false);
+    println!("Separate IV recovered? {}", recover_key(&state).is_some());
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod test {
+    use super::{recover_key, State};
+
+    #[test]
+    fn test_recover_key() {
+        let state = State::new(true);
+        assert_eq!(recover_key(&state), Some(state.key.clone()));
+        assert_eq!(recover_key(&State::new(false)), None);
+    }
+
+    #[test]
+    fn test_quoting() {
+        let state = State::new(false);
+        let enc = state.encrypt("a b;admin=true");
+        assert_eq!(state.decrypt(enc.as_slice()).unwrap(),
+                   b"comment1=cooking%20MCs;userdata=a%20b%3Badmin%3Dtrue;\
+                     comment2=%20like%20a%20pound%20of%20bacon".to_vec());
+    }
+}
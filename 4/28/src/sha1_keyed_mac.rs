@@ -0,0 +1,36 @@
+/* Implement a SHA-1 keyed MAC
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+extern crate codec;
+extern crate hash_lib;
+extern crate rand;
+
+use codec::Encoding;
+use rand::random;
+
+use hash_lib::{sha1_mac, sha1_mac_verify};
+
+static MESSAGE: &[u8] = b"comment1=cooking%20MCs;userdata=foo;\
+                          comment2=%20like%20a%20pound%20of%20bacon";
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    (0..len).map(|_| random::<u8>()).collect()
+}
+
+fn main() {
+    let key = random_bytes(16);
+    let mac = sha1_mac(key.as_slice(), MESSAGE);
+    println!("MAC: {}", Encoding::Hex.encode(mac.as_slice()));
+    println!("Is valid? {}", sha1_mac_verify(key.as_slice(), MESSAGE,
+                                             mac.as_slice()));
+    let mut tampered = MESSAGE.to_vec();
+    tampered[32..35].copy_from_slice(b"bar");
+    println!("Is tampered message valid? {}",
+             sha1_mac_verify(key.as_slice(), tampered.as_slice(),
+                             mac.as_slice()));
+    let forged = sha1_mac(random_bytes(16).as_slice(), MESSAGE);
+    println!("Is MAC without the key valid? {}",
+             sha1_mac_verify(key.as_slice(), MESSAGE, forged.as_slice()));
+}
@@ -0,0 +1,27 @@
+/* Break a SHA-1 keyed MAC using length extension
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+extern crate hash_lib;
+extern crate rand;
+
+use rand::{random, thread_rng, Rng};
+
+use hash_lib::{Sha1, PrefixMacOracle};
+
+static MESSAGE: &[u8] = b"comment1=cooking%20MCs;userdata=foo;\
+                          comment2=%20like%20a%20pound%20of%20bacon";
+
+// Maximum length of the secret key
+static MAX_KEY_LEN: usize = 64;
+
+fn main() {
+    let len = thread_rng().gen_range(1..=MAX_KEY_LEN);
+    let key: Vec<u8> = (0..len).map(|_| random::<u8>()).collect();
+    let oracle = PrefixMacOracle::<Sha1>::new(key.as_slice());
+    match oracle.extend(MESSAGE, b";admin=true", MAX_KEY_LEN) {
+        Some(forgery) => println!("{}", forgery),
+        None => println!("Unable to forge the MAC")
+    }
+}
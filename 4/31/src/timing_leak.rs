@@ -0,0 +1,374 @@
+/* HMAC-SHA1 timing leak
+ *
+ * The server checks the signature of the file with an early-exit comparison
+ * which sleeps after every matching byte, so the response time grows with
+ * the length of the matching prefix. The signature is recovered byte by byte
+ * choosing the byte with the longest filtered response time. Challenge 31
+ * uses 50 ms delay and challenge 32 uses 5 ms delay.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+extern crate codec;
+extern crate hash_lib;
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, SocketAddr};
+use std::time::{Duration, Instant};
+
+use codec::Encoding;
+use codec::kv::{self, Duplicates};
+use hash_lib::{hmac_sha1, insecure_compare, SHA1_DIGEST_SIZE};
+
+pub static DEFAULT_PORT: u16 = 9000;
+pub static SIGNATURE_SIZE: usize = SHA1_DIGEST_SIZE;
+
+pub const OK: u16 = 200;
+pub const BAD_REQUEST: u16 = 400;
+pub const NOT_FOUND: u16 = 404;
+pub const INTERNAL_ERROR: u16 = 500;
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        OK => "OK",
+        BAD_REQUEST => "Bad Request",
+        NOT_FOUND => "Not Found",
+        _ => "Internal Server Error"
+    }
+}
+
+pub struct Server {
+    key: Vec<u8>,
+    // Sleep after every matching byte of the signature
+    delay: Duration
+}
+
+impl Server {
+    pub fn new(key: &[u8], delay: Duration) -> Server {
+        Server{key: key.to_vec(), delay}
+    }
+
+    pub fn signature(&self, file: &[u8]) -> Vec<u8> {
+        hmac_sha1(self.key.as_slice(), file)
+    }
+
+    /*
+     * Status of the response to the request target
+     * /test?file=FILE&signature=HEX
+     */
+    pub fn handle(&self, target: &str) -> u16 {
+        let (path, query) = match target.find('?') {
+            Some(pos) => (&target[..pos], &target[pos + 1..]),
+            None => (target, "")
+        };
+        if path != "/test" {
+            return NOT_FOUND;
+        }
+        let params = match kv::parse(query.as_bytes(), Duplicates::Reject) {
+            Ok(params) => params,
+            Err(_) => return BAD_REQUEST
+        };
+        let param = |name: &str| {
+            params.iter().find(|(key, _)| key == name).map(|(_, v)| v)
+        };
+        let (file, signature) = match (param("file"), param("signature")) {
+            (Some(file), Some(signature)) => (file, signature),
+            _ => return BAD_REQUEST
+        };
+        let signature = match Encoding::Hex.decode(signature.as_bytes()) {
+            Ok(signature) => signature,
+            Err(_) => return BAD_REQUEST
+        };
+        let expected = self.signature(file.as_bytes());
+        match insecure_compare(signature.as_slice(), expected.as_slice(),
+                               self.delay) {
+            true => OK,
+            false => INTERNAL_ERROR
+        }
+    }
+
+    /*
+     * Handle the connections one by one so the requests don't affect the
+     * timing of each other
+     */
+    pub fn serve(&self, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            // The client may disconnect before the response
+            let _ = self.respond(stream?);
+        }
+        Ok(())
+    }
+
+    fn respond(&self, stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(&stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        // Skip the headers
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 0 && header.trim() != "" {
+            header.clear();
+        }
+        let status = match request_line.split_whitespace().nth(1) {
+            Some(target) => self.handle(target),
+            None => BAD_REQUEST
+        };
+        let mut stream = &stream;
+        write!(stream, "HTTP/1.1 {} {}\r\nContent-Length: 0\r\n\
+                        Connection: close\r\n\r\n", status, reason(status))?;
+        stream.flush()
+    }
+}
+
+pub fn request_target(file: &str, signature: &[u8]) -> String {
+    let params = [("file".to_string(), file.to_string()),
+                  ("signature".to_string(),
+                   Encoding::Hex.encode(signature))];
+    format!("/test?{}", kv::encode(&params))
+}
+
+/*
+ * Send GET request and return the status and the time until the status line
+ * of the response
+ */
+pub fn send_request(addr: &SocketAddr, target: &str)
+        -> io::Result<(u16, Duration)> {
+    let stream = TcpStream::connect(addr)?;
+    stream.set_nodelay(true)?;
+    let request = format!("GET {} HTTP/1.1\r\nHost: {}\r\n\
+                           Connection: close\r\n\r\n", target, addr);
+    let start = Instant::now();
+    (&stream).write_all(request.as_bytes())?;
+    let mut status_line = String::new();
+    BufReader::new(&stream).read_line(&mut status_line)?;
+    let elapsed = start.elapsed();
+    match status_line.split_whitespace().nth(1).map(|s| s.parse()) {
+        Some(Ok(status)) => Ok((status, elapsed)),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData,
+                                format!("Invalid status line: {:?}",
+                                        status_line)))
+    }
+}
+
+// Estimation of the response time from the noisy samples
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Filter {
+    Median,
+    // Mean without the fraction of the smallest and the largest samples
+    TrimmedMean(f64)
+}
+
+impl Filter {
+    pub fn apply(&self, samples: &[Duration]) -> Duration {
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        match *self {
+            Filter::Median => median(sorted.as_slice()),
+            Filter::TrimmedMean(trim) => trimmed_mean(sorted.as_slice(), trim)
+        }
+    }
+}
+
+fn median(sorted: &[Duration]) -> Duration {
+    let n = sorted.len();
+    match n % 2 {
+        0 => (sorted[n / 2 - 1] + sorted[n / 2]) / 2,
+        _ => sorted[n / 2]
+    }
+}
+
+fn trimmed_mean(sorted: &[Duration], trim: f64) -> Duration {
+    let cut = (sorted.len() as f64 * trim) as usize;
+    let kept = match sorted.len() > 2 * cut {
+        true => &sorted[cut..sorted.len() - cut],
+        false => sorted
+    };
+    kept.iter().sum::<Duration>() / kept.len() as u32
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Attack {
+    // Requests per candidate byte in every round
+    pub samples: usize,
+    pub filter: Filter,
+    // Additional rounds if the longest time isn't clearly separated
+    pub retries: usize
+}
+
+impl Attack {
+    /*
+     * Recover the signature of the length byte by byte, the measure function
+     * returns whether the signature is valid and the response time. The
+     * progress function is called with the recovered prefix after every
+     * byte.
+     */
+    pub fn recover<M, P, E>(&self, len: usize, mut measure: M,
+                            mut progress: P) -> Result<Option<Vec<u8>>, E>
+            where M: FnMut(&[u8]) -> Result<(bool, Duration), E>,
+                  P: FnMut(&[u8]) {
+        let mut signature = vec![0u8; len];
+        let all: Vec<u8> = (0..=255).collect();
+        for pos in 0..len {
+            let mut timings = vec![Vec::new(); 256];
+            for round in 0..=self.retries {
+                if self.sample(&mut signature, pos, all.as_slice(),
+                               &mut timings, &mut measure)? {
+                    progress(signature.as_slice());
+                    return Ok(Some(signature));
+                }
+                let (best, second, separated) = self.rank(&timings);
+                signature[pos] = best;
+                if round == self.retries || !separated {
+                    continue;
+                }
+                // A few noise spikes may outweigh the delay, so the best
+                // byte has to win against the second byte once again
+                let mut fresh = vec![Vec::new(); 256];
+                if self.sample(&mut signature, pos, &[best, second],
+                               &mut fresh, &mut measure)? {
+                    progress(signature.as_slice());
+                    return Ok(Some(signature));
+                }
+                let confirmed = self.filter.apply(&fresh[best as usize])
+                    > self.filter.apply(&fresh[second as usize]);
+                for byte in [best, second] {
+                    let samples = &fresh[byte as usize];
+                    timings[byte as usize].extend_from_slice(samples);
+                }
+                signature[pos] = best;
+                if confirmed {
+                    break;
+                }
+            }
+            progress(&signature[..=pos]);
+        }
+        Ok(None)
+    }
+
+    /*
+     * Add the samples of the candidate bytes at the position to the timings,
+     * returns true if a valid signature is found
+     */
+    fn sample<M, E>(&self, signature: &mut [u8], pos: usize,
+                    candidates: &[u8], timings: &mut [Vec<Duration>],
+                    measure: &mut M) -> Result<bool, E>
+            where M: FnMut(&[u8]) -> Result<(bool, Duration), E> {
+        for _ in 0..self.samples {
+            // Interleave the candidates to spread the noise evenly
+            for &byte in candidates.iter() {
+                signature[pos] = byte;
+                let (valid, time) = measure(signature)?;
+                if valid {
+                    return Ok(true);
+                }
+                timings[byte as usize].push(time);
+            }
+        }
+        Ok(false)
+    }
+
+    /*
+     * The best and the second byte by the filtered time and whether the best
+     * byte is separated: the gap to the second byte is larger than the
+     * spread of the other bytes above the median
+     */
+    fn rank(&self, timings: &[Vec<Duration>]) -> (u8, u8, bool) {
+        let mut times: Vec<(Duration, u8)> = timings.iter().enumerate()
+            .map(|(byte, samples)| {
+                (self.filter.apply(samples.as_slice()), byte as u8)
+            }).collect();
+        times.sort();
+        let (best, best_byte) = times[times.len() - 1];
+        let (second, second_byte) = times[times.len() - 2];
+        let median = times[times.len() / 2].0;
+        (best_byte, second_byte, best - second > second - median)
+    }
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+    use mersenne_twister::MersenneTwister;
+    use super::{Server, Attack, Filter, request_target, send_request};
+    use super::{OK, BAD_REQUEST, NOT_FOUND, INTERNAL_ERROR, SIGNATURE_SIZE};
+
+    fn millis(values: &[u64]) -> Vec<Duration> {
+        values.iter().map(|&ms| Duration::from_millis(ms)).collect()
+    }
+
+    #[test]
+    fn test_filters() {
+        let samples = millis(&[7, 1, 1000, 5, 3]);
+        assert_eq!(Filter::Median.apply(samples.as_slice()),
+                   Duration::from_millis(5));
+        assert_eq!(Filter::TrimmedMean(0.2).apply(samples.as_slice()),
+                   Duration::from_millis(5));
+        assert_eq!(Filter::TrimmedMean(0.0).apply(samples.as_slice()),
+                   Duration::from_micros(203200));
+        let samples = millis(&[4, 1, 3, 2]);
+        assert_eq!(Filter::Median.apply(samples.as_slice()),
+                   Duration::from_micros(2500));
+    }
+
+    #[test]
+    fn test_handle() {
+        let server = Server::new(b"key", Duration::ZERO);
+        let signature = server.signature(b"foo");
+        assert_eq!(signature.len(), SIGNATURE_SIZE);
+        let target = request_target("foo", signature.as_slice());
+        assert_eq!(server.handle(target.as_str()), OK);
+        let target = request_target("bar", signature.as_slice());
+        assert_eq!(server.handle(target.as_str()), INTERNAL_ERROR);
+        assert_eq!(server.handle("/test?file=foo&signature=xyz"),
+                   BAD_REQUEST);
+        assert_eq!(server.handle("/test?file=foo"), BAD_REQUEST);
+        assert_eq!(server.handle("/other?file=foo"), NOT_FOUND);
+    }
+
+    #[test]
+    fn test_serve() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = Server::new(b"key", Duration::from_millis(1));
+        let signature = server.signature(b"foo");
+        thread::spawn(move || server.serve(listener));
+        let target = request_target("foo", signature.as_slice());
+        let (status, time) = send_request(&addr, target.as_str()).unwrap();
+        assert_eq!(status, OK);
+        assert!(time >= Duration::from_millis(SIGNATURE_SIZE as u64));
+        let target = request_target("foo", &[0; SIGNATURE_SIZE]);
+        let (status, _) = send_request(&addr, target.as_str()).unwrap();
+        assert_eq!(status, INTERNAL_ERROR);
+    }
+
+    #[test]
+    fn test_recover() {
+        let server = Server::new(b"secret key", Duration::ZERO);
+        let expected = server.signature(b"foo");
+        let delay = 1000;
+        let mut rng = MersenneTwister::new(&[0x123, 0x234][..]);
+        // Simulated time: the delay for every matching byte, the jitter up
+        // to the delay and rare spikes
+        let measure = |signature: &[u8]| -> Result<(bool, Duration), ()> {
+            let matched = signature.iter().zip(expected.iter())
+                .take_while(|(a, b)| a == b).count() as u64;
+            let mut time = matched * delay + rng.rand_u32() as u64 % delay;
+            if rng.rand_u32().is_multiple_of(50) {
+                time += 20 * delay;
+            }
+            Ok((matched == SIGNATURE_SIZE as u64,
+                Duration::from_micros(time)))
+        };
+        let mut recovered = 0;
+        let attack = Attack{samples: 3, filter: Filter::Median, retries: 3};
+        let signature = attack.recover(SIGNATURE_SIZE, measure, |prefix| {
+            recovered = prefix.len();
+        }).unwrap();
+        assert_eq!(signature, Some(expected));
+        assert_eq!(recovered, SIGNATURE_SIZE);
+    }
+}
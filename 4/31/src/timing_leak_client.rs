@@ -0,0 +1,128 @@
+/* Recover the HMAC-SHA1 signature from the timing leak server
+ *
+ * Every candidate byte is requested several times and the byte with the
+ * longest filtered response time is chosen. More samples are required for
+ * the shorter delay of the server.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+extern crate codec;
+extern crate getopts;
+extern crate timing_leak;
+
+use std::env;
+use std::io::{stdout, stderr, Write};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::process;
+use std::str::FromStr;
+use getopts::{Options, Matches};
+
+use codec::Encoding;
+use timing_leak::{Attack, Filter, DEFAULT_PORT, OK, SIGNATURE_SIZE};
+use timing_leak::{request_target, send_request};
+
+static USAGE: &str = "Usage: timing_leak_client [OPTIONS]";
+
+static DEFAULT_SAMPLES: usize = 5;
+static DEFAULT_TRIM: f64 = 0.2;
+static DEFAULT_RETRIES: usize = 3;
+
+fn options() -> Options {
+    let mut opts = Options::new();
+    opts.optopt("", "host", "server host (default: 127.0.0.1)", "HOST");
+    opts.optopt("p", "port", "server port (default: 9000)", "PORT");
+    opts.optopt("f", "file", "file to sign (default: foo)", "FILE");
+    opts.optopt("s", "samples", "requests per byte in every round \
+                (default: 5)", "COUNT");
+    opts.optopt("", "filter", "filter of the samples: median or trimmed \
+                (default: median)", "FILTER");
+    opts.optopt("", "trim", "fraction of the samples trimmed from both \
+                sides (default: 0.2)", "FRACTION");
+    opts.optopt("r", "retries", "additional rounds if the byte isn't \
+                clear (default: 3)", "COUNT");
+    opts.optflag("h", "help", "print this help");
+    opts
+}
+
+fn opt_number<T: FromStr>(matches: &Matches, name: &str, default: T)
+        -> Result<T, String> {
+    match matches.opt_str(name) {
+        Some(value) => value.parse().map_err(|_| {
+            format!("Invalid value of --{}: {}", name, value)
+        }),
+        None => Ok(default)
+    }
+}
+
+fn opt_filter(matches: &Matches) -> Result<Filter, String> {
+    let trim = opt_number(matches, "trim", DEFAULT_TRIM)?;
+    if !(0.0..0.5).contains(&trim) {
+        return Err(format!("Invalid trim fraction: {}", trim));
+    }
+    match matches.opt_str("filter") {
+        Some(ref name) if name.as_str() == "trimmed" =>
+            Ok(Filter::TrimmedMean(trim)),
+        Some(ref name) if name.as_str() != "median" =>
+            Err(format!("Unknown filter: {}", name)),
+        _ => Ok(Filter::Median)
+    }
+}
+
+fn server_addr(matches: &Matches) -> Result<SocketAddr, String> {
+    let host = matches.opt_str("host")
+        .unwrap_or_else(|| "127.0.0.1".to_string());
+    let port = opt_number(matches, "port", DEFAULT_PORT)?;
+    (host.as_str(), port).to_socket_addrs().map_err(|err| err.to_string())?
+        .next().ok_or(format!("Unknown host: {}", host))
+}
+
+fn run(matches: &Matches) -> Result<(), String> {
+    let addr = server_addr(matches)?;
+    let file = matches.opt_str("file").unwrap_or_else(|| "foo".to_string());
+    let samples = opt_number(matches, "samples", DEFAULT_SAMPLES)?;
+    if samples == 0 {
+        return Err("Number of samples must be positive".to_string());
+    }
+    let attack = Attack{
+        samples,
+        filter: opt_filter(matches)?,
+        retries: opt_number(matches, "retries", DEFAULT_RETRIES)?
+    };
+    let measure = |signature: &[u8]| {
+        let target = request_target(file.as_str(), signature);
+        send_request(&addr, target.as_str())
+            .map(|(status, time)| (status == OK, time))
+    };
+    let progress = |prefix: &[u8]| {
+        print!("\r{}", Encoding::Hex.encode(prefix));
+        let _ = stdout().flush();
+    };
+    let signature = attack.recover(SIGNATURE_SIZE, measure, progress)
+        .map_err(|err| err.to_string())?;
+    println!();
+    match signature {
+        Some(signature) => println!("Signature of {:?}: {}", file,
+                                    Encoding::Hex.encode(&signature)),
+        None => println!("Unable to recover the signature")
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let opts = options();
+    let result = opts.parse(&args[1..]).map_err(|err| {
+        err.to_string()
+    }).and_then(|matches| {
+        if matches.opt_present("help") {
+            println!("{}", opts.usage(USAGE));
+            return Ok(());
+        }
+        run(&matches)
+    });
+    if let Err(err) = result {
+        let _ = writeln!(&mut stderr(), "timing_leak_client: {}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,84 @@
+/* HTTP server which leaks the HMAC-SHA1 signature through the timing
+ *
+ * GET /test?file=FILE&signature=HEX returns 200 if the signature is valid
+ * and 500 otherwise. The signature is compared byte by byte with the delay
+ * after every matching byte.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+extern crate codec;
+extern crate getopts;
+extern crate rand;
+extern crate timing_leak;
+
+use std::env;
+use std::io::{stderr, Write};
+use std::net::TcpListener;
+use std::process;
+use std::time::Duration;
+use getopts::{Options, Matches};
+use rand::random;
+
+use codec::Encoding;
+use timing_leak::{Server, DEFAULT_PORT};
+
+static USAGE: &str = "Usage: timing_leak_server [OPTIONS]";
+
+// Delay after every matching byte in milliseconds
+static DEFAULT_DELAY: f64 = 50.0;
+static KEY_SIZE: usize = 16;
+
+fn options() -> Options {
+    let mut opts = Options::new();
+    opts.optopt("p", "port", "port to listen on (default: 9000)", "PORT");
+    opts.optopt("d", "delay", "delay after every matching byte in \
+                milliseconds (default: 50)", "MS");
+    opts.optopt("k", "key", "HMAC key in hex (default: random)", "KEY");
+    opts.optflag("h", "help", "print this help");
+    opts
+}
+
+fn run(matches: &Matches) -> Result<(), String> {
+    let port = match matches.opt_str("port") {
+        Some(port) => port.parse().map_err(|_| {
+            format!("Invalid port: {}", port)
+        })?,
+        None => DEFAULT_PORT
+    };
+    let delay = match matches.opt_str("delay") {
+        Some(delay) => delay.parse().ok().filter(|&ms: &f64| ms >= 0.0)
+            .ok_or(format!("Invalid delay: {}", delay))?,
+        None => DEFAULT_DELAY
+    };
+    let key = match matches.opt_str("key") {
+        Some(key) => Encoding::Hex.decode(key.as_bytes())
+            .map_err(|err| format!("Invalid key: {}", err))?,
+        None => (0..KEY_SIZE).map(|_| random::<u8>()).collect()
+    };
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .map_err(|err| err.to_string())?;
+    println!("Listening on {} with {} ms delay",
+             listener.local_addr().map_err(|err| err.to_string())?, delay);
+    let server = Server::new(key.as_slice(),
+                             Duration::from_secs_f64(delay / 1000.0));
+    server.serve(listener).map_err(|err| err.to_string())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let opts = options();
+    let result = opts.parse(&args[1..]).map_err(|err| {
+        err.to_string()
+    }).and_then(|matches| {
+        if matches.opt_present("help") {
+            println!("{}", opts.usage(USAGE));
+            return Ok(());
+        }
+        run(&matches)
+    });
+    if let Err(err) = result {
+        let _ = writeln!(&mut stderr(), "timing_leak_server: {}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,660 @@
+/* Command-line toolbox for the challenges
+ *
+ * Every command reads the input from a file or from the standard input and
+ * writes the result to the standard output.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+extern crate getopts;
+extern crate libc;
+
+extern crate aes_lib;
+extern crate codec;
+extern crate mersenne_twister;
+extern crate single_char_xor_lib;
+
+use std::env;
+use std::fs;
+use std::io::{stdin, stdout, stderr, Read, Write};
+use std::process;
+use std::ptr;
+use std::str::FromStr;
+use getopts::{Options, Matches};
+use libc::time_t;
+
+use aes_lib::{AES_BLOCK_SIZE, encrypt_aes_ecb, decrypt_aes_ecb};
+use aes_lib::{encrypt_aes_cbc, decrypt_aes_cbc};
+use aes_lib::{encrypt_aes_ctr, decrypt_aes_ctr};
+use aes_lib::ecb::rank_lines;
+use codec::{Encoding, STANDARD, URL_SAFE, MIME, COOKIE_RESERVED, detect};
+use mersenne_twister::MersenneTwister;
+use single_char_xor_lib::{PlaintextModel, EnglishModel, Scoring};
+use single_char_xor_lib::{NgramModel, Utf8Model};
+use single_char_xor_lib::{decrypt_with, break_repeating_xor, guess_keysizes};
+use single_char_xor_lib::xor_with_key;
+use single_char_xor_lib::{FixedNonceBreaker, GuessError};
+
+
+static USAGE: &str = "Usage: cryptopals COMMAND [OPTIONS] [FILE]
+
+Commands:
+    encode                  Encode the input (base64 by default)
+    decode                  Decode the input
+    xor                     XOR the input with the repeating key
+    aes encrypt|decrypt     AES-128 encryption or decryption
+    break single-xor        Break single-character XOR of every line
+    break repeating-xor     Break repeating-key XOR
+    break fixed-nonce-ctr   Break lines encrypted by CTR with the same nonce
+    detect ecb              Find lines encrypted in ECB mode
+    mt clone                Clone MT19937 from 624 outputs, one per line
+    mt seed-search OUTPUT   Find timestamp seeds of the first MT19937 output
+
+The input is read from FILE or from the standard input. Formats: raw, auto
+(detect the encoding), hex, base64, base64url, mime, base32, base85 and
+percent.";
+
+// Number of the most probable key sizes to try for repeating-key XOR
+static MAX_KEYSIZES: usize = 5;
+// Key bytes with lower confidence should be checked manually
+static MIN_CONFIDENCE: f64 = 0.9;
+// Default range of the seeds in seconds before now
+static SEED_SEARCH_RANGE: i64 = 1000000;
+
+// Format of the input or output data
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Format {
+    Raw,
+    // Detect the encoding of the input, raw if not detected
+    Auto,
+    Encoded(Encoding)
+}
+
+type CommandResult = Result<(), String>;
+
+fn parse_format(name: &str) -> Result<Format, String> {
+    let encoding = match name {
+        "raw" => return Ok(Format::Raw),
+        "auto" => return Ok(Format::Auto),
+        "hex" => Encoding::Hex,
+        "base64" => Encoding::Base64(STANDARD),
+        "base64url" => Encoding::Base64(URL_SAFE),
+        "mime" => Encoding::Base64(MIME),
+        "base32" => Encoding::Base32,
+        "base85" => Encoding::Base85,
+        "percent" => Encoding::Percent(COOKIE_RESERVED),
+        _ => return Err(format!("Unknown format: {}", name))
+    };
+    Ok(Format::Encoded(encoding))
+}
+
+fn decode(data: &[u8], format: Format) -> Result<Vec<u8>, String> {
+    let encoding = match (format, detect(data)) {
+        (Format::Encoded(encoding), _) => encoding,
+        (Format::Auto, Some(encoding)) => encoding,
+        _ => return Ok(data.to_vec())
+    };
+    encoding.decode(data).map_err(|err| err.to_string())
+}
+
+fn decode_lines(data: &[u8], format: Format)
+        -> Result<Vec<Vec<u8>>, String> {
+    let encoding = match (format, detect(data)) {
+        (Format::Encoded(encoding), _) => encoding,
+        (Format::Auto, Some(encoding)) => encoding,
+        _ => return Ok(data.split(|&c| c == b'\n').map(|line| {
+            match line.last() {
+                Some(&b'\r') => line[..line.len() - 1].to_vec(),
+                _ => line.to_vec()
+            }
+        }).filter(|line| !line.is_empty()).collect())
+    };
+    encoding.decode_lines(data).map_err(|err| err.to_string())
+}
+
+fn encode(data: &[u8], format: Format) -> Vec<u8> {
+    match format {
+        Format::Encoded(encoding) => {
+            let mut encoded = encoding.encode(data).into_bytes();
+            encoded.push(b'\n');
+            encoded
+        },
+        _ => data.to_vec()
+    }
+}
+
+fn options() -> Options {
+    let mut opts = Options::new();
+    opts.optopt("i", "input-format", "format of the input", "FORMAT");
+    opts.optopt("o", "output-format", "format of the output", "FORMAT");
+    opts.optopt("k", "key", "key for xor and aes", "KEY");
+    opts.optopt("", "key-format", "format of the key and IV (default: raw)",
+                "FORMAT");
+    opts.optopt("m", "mode", "AES mode: ecb, cbc or ctr (default: ecb)",
+                "MODE");
+    opts.optopt("", "iv", "CBC IV (default: zero bytes)", "IV");
+    opts.optopt("", "nonce", "CTR nonce (default: 0)", "NONCE");
+    opts.optopt("", "model", "plaintext model: english, json or utf8",
+                "MODEL");
+    opts.optopt("", "min-keysize", "minimum key size (default: 2)", "SIZE");
+    opts.optopt("", "max-keysize", "maximum key size (default: 40)", "SIZE");
+    opts.optopt("", "count", "number of MT19937 outputs to predict",
+                "COUNT");
+    opts.optopt("", "from", "first seed to try (default: 1000000 seconds \
+                ago)", "SEED");
+    opts.optopt("", "to", "last seed to try (default: now)", "SEED");
+    opts.optmulti("g", "guess", "plaintext guess for fixed-nonce-ctr, \
+                  the lines are numbered from 1", "LINE:OFFSET:TEXT");
+    opts.optflag("h", "help", "print this help");
+    opts
+}
+
+fn opt_format(matches: &Matches, name: &str, default: Format)
+        -> Result<Format, String> {
+    match matches.opt_str(name) {
+        Some(format) => parse_format(format.as_str()),
+        None => Ok(default)
+    }
+}
+
+fn opt_number<T: FromStr>(matches: &Matches, name: &str, default: T)
+        -> Result<T, String> {
+    match matches.opt_str(name) {
+        Some(value) => value.parse().map_err(|_| {
+            format!("Invalid value of --{}: {}", name, value)
+        }),
+        None => Ok(default)
+    }
+}
+
+fn opt_bytes(matches: &Matches, name: &str)
+        -> Result<Option<Vec<u8>>, String> {
+    let format = opt_format(matches, "key-format", Format::Raw)?;
+    match matches.opt_str(name) {
+        Some(value) => decode(value.as_bytes(), format).map(Some),
+        None => Ok(None)
+    }
+}
+
+fn required_key(matches: &Matches) -> Result<Vec<u8>, String> {
+    match opt_bytes(matches, "key")? {
+        Some(ref key) if key.is_empty() => Err("Empty key".to_string()),
+        Some(key) => Ok(key),
+        None => Err("Option --key is required".to_string())
+    }
+}
+
+fn opt_model(matches: &Matches) -> Result<Box<dyn PlaintextModel>, String> {
+    match matches.opt_str("model") {
+        Some(ref name) if name.as_str() == "json" =>
+            Ok(Box::new(NgramModel::json())),
+        Some(ref name) if name.as_str() == "utf8" => Ok(Box::new(Utf8Model)),
+        Some(ref name) if name.as_str() != "english" =>
+            Err(format!("Unknown model: {}", name)),
+        _ => Ok(Box::new(EnglishModel::new(Scoring::LogLikelihood)))
+    }
+}
+
+/*
+ * Read the input from the file with the given position in the free
+ * arguments or from the standard input
+ */
+fn read_input(matches: &Matches, pos: usize) -> Result<Vec<u8>, String> {
+    let result = match matches.free.get(pos) {
+        Some(path) => fs::read(path),
+        None => {
+            let mut data = Vec::new();
+            stdin().read_to_end(&mut data).map(|_| data)
+        }
+    };
+    result.map_err(|err| err.to_string())
+}
+
+fn write_output(data: &[u8]) -> CommandResult {
+    stdout().write_all(data).map_err(|err| err.to_string())
+}
+
+fn action<'a>(matches: &'a Matches, actions: &[&str])
+        -> Result<&'a str, String> {
+    match matches.free.first() {
+        Some(action) if actions.contains(&action.as_str()) =>
+            Ok(action.as_str()),
+        Some(action) => Err(format!("Unknown action: {}", action)),
+        None => Err(format!("Action required: {}", actions.join(", ")))
+    }
+}
+
+fn encode_command(matches: &Matches) -> CommandResult {
+    let input = read_input(matches, 0)?;
+    let format = opt_format(matches, "input-format", Format::Raw)?;
+    let data = decode(input.as_slice(), format)?;
+    let base64 = Format::Encoded(Encoding::Base64(STANDARD));
+    let format = opt_format(matches, "output-format", base64)?;
+    write_output(encode(data.as_slice(), format).as_slice())
+}
+
+fn decode_command(matches: &Matches) -> CommandResult {
+    let input = read_input(matches, 0)?;
+    let format = opt_format(matches, "input-format", Format::Auto)?;
+    let data = decode(input.as_slice(), format)?;
+    let format = opt_format(matches, "output-format", Format::Raw)?;
+    write_output(encode(data.as_slice(), format).as_slice())
+}
+
+fn xor_command(matches: &Matches) -> CommandResult {
+    let input = read_input(matches, 0)?;
+    write_output(xor(matches, input.as_slice())?.as_slice())
+}
+
+fn xor(matches: &Matches, input: &[u8]) -> Result<Vec<u8>, String> {
+    let key = required_key(matches)?;
+    let format = opt_format(matches, "input-format", Format::Raw)?;
+    let data = decode(input, format)?;
+    let hex = Format::Encoded(Encoding::Hex);
+    let format = opt_format(matches, "output-format", hex)?;
+    let encrypted = xor_with_key(data.as_slice(), key.as_slice());
+    Ok(encode(encrypted.as_slice(), format))
+}
+
+fn aes_command(matches: &Matches) -> CommandResult {
+    let input = read_input(matches, 1)?;
+    write_output(aes(matches, input.as_slice())?.as_slice())
+}
+
+fn aes(matches: &Matches, input: &[u8]) -> Result<Vec<u8>, String> {
+    let is_encrypt = action(matches, &["encrypt", "decrypt"])? ==
+        "encrypt";
+    let key = required_key(matches)?;
+    if key.len() != AES_BLOCK_SIZE {
+        return Err(format!("Key should be {} bytes long", AES_BLOCK_SIZE));
+    }
+    let iv = match opt_bytes(matches, "iv")? {
+        Some(ref iv) if iv.len() != AES_BLOCK_SIZE =>
+            return Err(format!("IV should be {} bytes long", AES_BLOCK_SIZE)),
+        Some(iv) => iv,
+        None => [0u8; 16].to_vec()
+    };
+    let nonce = opt_number(matches, "nonce", 0u64)?;
+    let (input_format, output_format) = if is_encrypt {
+        (Format::Raw, Format::Encoded(Encoding::Base64(STANDARD)))
+    } else {
+        (Format::Auto, Format::Raw)
+    };
+    let format = opt_format(matches, "input-format", input_format)?;
+    let data = decode(input, format)?;
+    let (data, key, iv) = (data.as_slice(), key.as_slice(), iv.as_slice());
+    let mode = matches.opt_str("mode").unwrap_or("ecb".to_string());
+    let result = match (mode.as_str(), is_encrypt) {
+        ("ecb", true) => encrypt_aes_ecb(data, key),
+        ("ecb" | "cbc", false) if data.len() % AES_BLOCK_SIZE != 0 =>
+            return Err("Invalid size of encrypted data".to_string()),
+        ("ecb", false) => decrypt_aes_ecb(data, key),
+        ("cbc", true) => encrypt_aes_cbc(data, key, iv),
+        ("cbc", false) => decrypt_aes_cbc(data, key, iv),
+        ("ctr", true) => encrypt_aes_ctr(data, key, nonce),
+        ("ctr", false) => decrypt_aes_ctr(data, key, nonce),
+        _ => return Err(format!("Unknown mode: {}", mode))
+    };
+    let format = opt_format(matches, "output-format", output_format)?;
+    Ok(encode(result.as_slice(), format))
+}
+
+fn break_command(matches: &Matches) -> CommandResult {
+    let kind = action(matches, &["single-xor", "repeating-xor",
+                                      "fixed-nonce-ctr"])?;
+    let model = opt_model(matches)?;
+    let input = read_input(matches, 1)?;
+    let format = opt_format(matches, "input-format", Format::Auto)?;
+    match kind {
+        "single-xor" => {
+            let lines = decode_lines(input.as_slice(), format)?;
+            break_single_xor(lines.as_slice(), &*model)
+        },
+        "repeating-xor" => {
+            let data = decode(input.as_slice(), format)?;
+            break_repeating_key(matches, data.as_slice(), &*model)
+        },
+        _ => {
+            let lines = decode_lines(input.as_slice(), format)?;
+            break_fixed_nonce_ctr(matches, lines.as_slice(), &*model)
+        }
+    }
+}
+
+/*
+ * Find the line which looks like the best plaintext after decryption
+ */
+fn break_single_xor(lines: &[Vec<u8>], model: &dyn PlaintextModel)
+        -> CommandResult {
+    let mut best = None;
+    for (i, line) in lines.iter().enumerate() {
+        let candidates = decrypt_with(line.as_slice(), model);
+        if let Some((key, score, text)) = candidates.into_iter().next() {
+            let is_better = match best {
+                Some((_, _, best_score, _)) => score > best_score,
+                None => true
+            };
+            if is_better {
+                best = Some((i + 1, key, score, text));
+            }
+        }
+    }
+    match best {
+        Some((line_num, key, _, text)) => {
+            println!("Line {}, key 0x{:02x}: {}", line_num, key,
+                     String::from_utf8_lossy(text.as_slice()));
+            Ok(())
+        },
+        None => Err("No input".to_string())
+    }
+}
+
+fn break_repeating_key(matches: &Matches, data: &[u8],
+                       model: &dyn PlaintextModel) -> CommandResult {
+    let min = opt_number(matches, "min-keysize", 2usize)?;
+    let max = opt_number(matches, "max-keysize", 40usize)?;
+    let keysizes = guess_keysizes(data, min..max + 1);
+    let keysizes = keysizes.into_iter().take(MAX_KEYSIZES);
+    let candidate = match break_repeating_xor(data, keysizes, model)
+            .into_iter().next() {
+        Some(candidate) => candidate,
+        None => return Err("Input is too short".to_string())
+    };
+    let hex = Encoding::Hex.encode(candidate.key.as_slice());
+    println!("Key: {} ({})", hex,
+             String::from_utf8_lossy(candidate.key.as_slice()));
+    let low = candidate.low_confidence(MIN_CONFIDENCE);
+    if !low.is_empty() {
+        println!("Check key bytes at positions {:?}", low);
+    }
+    let format = opt_format(matches, "output-format", Format::Raw)?;
+    write_output(encode(candidate.text.as_slice(), format).as_slice())
+}
+
+/*
+ * Every column of the lines is a single-character XOR ciphertext, the
+ * guesses LINE:OFFSET:TEXT fix the key bytes
+ */
+fn break_fixed_nonce_ctr(matches: &Matches, lines: &[Vec<u8>],
+                         model: &dyn PlaintextModel) -> CommandResult {
+    if lines.iter().all(|line| line.is_empty()) {
+        return Err("No input".to_string());
+    }
+    let mut breaker = FixedNonceBreaker::new(lines, model);
+    for guess in matches.opt_strs("guess").iter() {
+        let (line, offset, text) = parse_guess(guess.as_str())?;
+        breaker.guess(line, offset, text.as_bytes()).map_err(|err| {
+            match err {
+                GuessError::NoCiphertext(_) =>
+                    format!("No line {}", line + 1),
+                err => err.to_string()
+            }
+        })?;
+    }
+    let keystream = breaker.solve();
+    let low = keystream.low_confidence(MIN_CONFIDENCE);
+    if !low.is_empty() {
+        println!("Check key bytes at positions {:?}", low);
+    }
+    for (i, line) in keystream.decrypt(lines).iter().enumerate() {
+        println!("{:02}: {}", i + 1, String::from_utf8_lossy(line));
+    }
+    Ok(())
+}
+
+/*
+ * Parse the plaintext guess LINE:OFFSET:TEXT, the lines are numbered from 1
+ */
+fn parse_guess(guess: &str) -> Result<(usize, usize, String), String> {
+    let parts: Vec<&str> = guess.splitn(3, ':').collect();
+    let invalid = || format!("Invalid guess: {}", guess);
+    if parts.len() != 3 {
+        return Err(invalid());
+    }
+    match (parts[0].parse::<usize>(), parts[1].parse::<usize>()) {
+        (Ok(line), Ok(offset)) if line > 0 =>
+            Ok((line - 1, offset, parts[2].to_string())),
+        _ => Err(invalid())
+    }
+}
+
+fn detect_command(matches: &Matches) -> CommandResult {
+    action(matches, &["ecb"])?;
+    let input = read_input(matches, 1)?;
+    write_output(detect_ecb(matches, input.as_slice())?.as_slice())
+}
+
+/*
+ * Report the lines likely encrypted in ECB mode from the most likely one
+ * with their repeated blocks
+ */
+fn detect_ecb(matches: &Matches, input: &[u8]) -> Result<Vec<u8>, String> {
+    let format = opt_format(matches, "input-format", Format::Auto)?;
+    let lines = decode_lines(input, format)?;
+    let mut report = String::new();
+    for (i, analysis) in rank_lines(lines.as_slice(), AES_BLOCK_SIZE) {
+        if analysis.score <= 0.0 {
+            break;
+        }
+        report += &format!("Line {} is encrypted in ECB mode, score {:.2}, \
+                            alignment offset {}\n", i + 1, analysis.score,
+                           analysis.offset);
+        for repeated in analysis.repeated.iter() {
+            report += &format!("    Block repeated {} times at positions \
+                                {:?}\n", repeated.positions.len(),
+                               repeated.positions);
+        }
+    }
+    Ok(report.into_bytes())
+}
+
+fn mt_command(matches: &Matches) -> CommandResult {
+    match action(matches, &["clone", "seed-search"])? {
+        "clone" => {
+            let input = read_input(matches, 1)?;
+            write_output(mt_clone(matches, input.as_slice())?.as_slice())
+        },
+        _ => {
+            let output = match matches.free.get(1) {
+                Some(value) => (value.parse::<u32>().map_err(|_| {
+                    format!("Invalid output: {}", value)
+                }))?,
+                None => return Err("MT19937 output required".to_string())
+            };
+            let now = timestamp() as i64;
+            let from = opt_number(matches, "from",
+                                       now - SEED_SEARCH_RANGE)?;
+            let to = opt_number(matches, "to", now)?;
+            let mut found = false;
+            for seed in from..to + 1 {
+                if MersenneTwister::new(seed).rand_u32() == output {
+                    println!("{}", seed);
+                    found = true;
+                }
+            }
+            if found {
+                Ok(())
+            } else {
+                Err("No seed found".to_string())
+            }
+        }
+    }
+}
+
+/*
+ * Predict the next outputs of MT19937 from the outputs of the input, one
+ * per line
+ */
+fn mt_clone(matches: &Matches, input: &[u8]) -> Result<Vec<u8>, String> {
+    let count = opt_number(matches, "count", 10usize)?;
+    let text = String::from_utf8_lossy(input);
+    let mut outputs = Vec::new();
+    let words = text.split(|c: char| c.is_whitespace())
+        .filter(|word| !word.is_empty());
+    for word in words {
+        match word.parse::<u32>() {
+            Ok(value) => outputs.push(value),
+            Err(_) => return Err(format!("Invalid output: {}", word))
+        }
+    }
+    let mut rng = match MersenneTwister::from_outputs(outputs.as_slice()) {
+        Some(rng) => rng,
+        None => return Err("At least 624 consecutive outputs of MT19937 \
+                            are required".to_string())
+    };
+    Ok((0..count).map(|_| format!("{}\n", rng.rand_u32()))
+       .collect::<String>().into_bytes())
+}
+
+extern "C" {
+    fn time(tloc: *const time_t) -> time_t;
+}
+
+fn timestamp() -> time_t {
+    unsafe {time(ptr::null())}
+}
+
+/*
+ * Main entry point
+ */
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let opts = options();
+    if args.len() < 2 || args[1].as_str() == "-h" ||
+            args[1].as_str() == "--help" {
+        println!("{}", opts.usage(USAGE));
+        return;
+    }
+    let result = opts.parse(&args[2..]).map_err(|err| {
+        err.to_string()
+    }).and_then(|matches| {
+        if matches.opt_present("help") {
+            println!("{}", opts.usage(USAGE));
+            return Ok(());
+        }
+        match args[1].as_str() {
+            "encode" => encode_command(&matches),
+            "decode" => decode_command(&matches),
+            "xor" => xor_command(&matches),
+            "aes" => aes_command(&matches),
+            "break" => break_command(&matches),
+            "detect" => detect_command(&matches),
+            "mt" => mt_command(&matches),
+            command => Err(format!("Unknown command: {}", command))
+        }
+    });
+    match result {
+        Ok(()) => (),
+        Err(err) => {
+            let _ = writeln!(&mut stderr(), "cryptopals: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod test {
+    use codec::{Encoding, STANDARD};
+    use getopts::Matches;
+    use mersenne_twister::MersenneTwister;
+    use super::{Format, parse_format, decode, decode_lines, encode};
+    use super::{parse_guess, options, xor, aes, mt_clone, detect_ecb};
+
+    static TEXT: &[u8] = b"Burning 'em, if you ain't quick and nimble";
+
+    fn parse(args: &[&str]) -> Matches {
+        options().parse(args).unwrap()
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(parse_format("raw"), Ok(Format::Raw));
+        assert_eq!(parse_format("base64"),
+                   Ok(Format::Encoded(Encoding::Base64(STANDARD))));
+        assert!(parse_format("base36").is_err());
+    }
+
+    #[test]
+    fn test_formats() {
+        assert_eq!(decode(b"SSdt", Format::Auto), Ok(b"I'm".to_vec()));
+        assert_eq!(decode(b"{raw}", Format::Auto), Ok(b"{raw}".to_vec()));
+        assert!(decode(b"SSd*", Format::Encoded(Encoding::Hex)).is_err());
+        assert_eq!(decode_lines(b"4927\r\n6d\n", Format::Auto),
+                   Ok(vec![b"I'".to_vec(), b"m".to_vec()]));
+        assert_eq!(decode_lines(b"a b\r\n\nc", Format::Raw),
+                   Ok(vec![b"a b".to_vec(), b"c".to_vec()]));
+        assert_eq!(encode(b"I'm", Format::Encoded(Encoding::Hex)),
+                   b"49276d\n".to_vec());
+    }
+
+    #[test]
+    fn test_parse_guess() {
+        assert_eq!(parse_guess("38:33:turn,"),
+                   Ok((37, 33, "turn,".to_string())));
+        assert_eq!(parse_guess("1:0:a:b"), Ok((0, 0, "a:b".to_string())));
+        assert!(parse_guess("0:0:I").is_err());
+        assert!(parse_guess("1:0").is_err());
+    }
+
+    #[test]
+    fn test_xor() {
+        let encrypted = xor(&parse(&["--key", "ICE"]), TEXT).unwrap();
+        assert_eq!(&encrypted[..8], b"0b363727");
+        let matches = parse(&["--key", "ICE", "-i", "hex", "-o", "raw"]);
+        assert_eq!(xor(&matches, encrypted.as_slice()), Ok(TEXT.to_vec()));
+        assert!(xor(&parse(&["--key", ""]), TEXT).is_err());
+    }
+
+    #[test]
+    fn test_aes() {
+        for &mode in ["ecb", "cbc", "ctr"].iter() {
+            let args = ["--key", "YELLOW SUBMARINE", "--mode", mode,
+                        "--iv", "0123456789abcdef", "--nonce", "7"];
+            let encrypt = parse(&[&["encrypt"], &args[..]].concat());
+            let decrypt = parse(&[&["decrypt"], &args[..]].concat());
+            let encrypted = aes(&encrypt, TEXT).unwrap();
+            assert_eq!(aes(&decrypt, encrypted.as_slice()),
+                       Ok(TEXT.to_vec()));
+        }
+        // The block modes don't decrypt the partial blocks
+        for &mode in ["ecb", "cbc"].iter() {
+            let matches = parse(&["decrypt", "--key", "YELLOW SUBMARINE",
+                                  "--mode", mode, "-i", "raw"]);
+            assert_eq!(aes(&matches, &[0; 17]),
+                       Err("Invalid size of encrypted data".to_string()));
+        }
+        let matches = parse(&["encrypt", "--key", "YELLOW", "--mode", "ecb"]);
+        assert!(aes(&matches, TEXT).is_err());
+    }
+
+    #[test]
+    fn test_mt_clone() {
+        let mut rng = MersenneTwister::new(5489u32);
+        let outputs: Vec<String> = (0..634)
+            .map(|_| rng.rand_u32().to_string()).collect();
+        let input = outputs[..624].join("\n");
+        let predicted = mt_clone(&parse(&["clone"]), input.as_bytes());
+        assert_eq!(predicted, Ok(format!("{}\n", outputs[624..].join("\n"))
+                                 .into_bytes()));
+        let input = outputs[..623].join(" ");
+        assert!(mt_clone(&parse(&["clone"]), input.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_detect_ecb() {
+        let block = "00112233445566778899aabbccddeeff";
+        let distinct: String = (0..48).map(|i| format!("{:02x}", i)).collect();
+        let input = format!("{}\n{}{}\n", distinct, "01".repeat(16),
+                            block.repeat(3));
+        let report = detect_ecb(&parse(&["ecb"]), input.as_bytes()).unwrap();
+        assert_eq!(String::from_utf8(report).unwrap(),
+                   "Line 2 is encrypted in ECB mode, score 0.67, \
+                    alignment offset 0\n    \
+                    Block repeated 3 times at positions [1, 2, 3]\n");
+        let report = detect_ecb(&parse(&["ecb"]), b"0011\n").unwrap();
+        assert!(report.is_empty());
+    }
+}
@@ -0,0 +1,98 @@
+/* Padding oracle attack benchmarks
+ *
+ * The attack strategies are compared by the average number of the oracle
+ * queries per byte of the lines corpus from the challenge 17.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+#[macro_use]
+extern crate criterion;
+extern crate aes_lib;
+extern crate codec;
+
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::Criterion;
+
+use aes_lib::encrypt_aes_cbc;
+use aes_lib::padding_oracle::{PaddingOracle, Strategy, decrypt_with,
+                              is_cbc_padding_valid};
+use codec::{Encoding, STANDARD};
+
+static KEY: &[u8] = b"YELLOW SUBMARINE";
+static IV: &[u8] = b"0123456789abcdef";
+
+static LINES_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"),
+                                  "/../../3/17/lines.txt");
+
+// Oracle which counts the requests
+struct Oracle {
+    requests: AtomicUsize
+}
+
+impl PaddingOracle for Oracle {
+    fn is_padding_valid(&self, ciphertext: &[u8]) -> bool {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        is_cbc_padding_valid(ciphertext, KEY, IV)
+    }
+
+    fn are_paddings_valid(&self, ciphertexts: &[Vec<u8>]) -> Vec<bool> {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        ciphertexts.iter().map(|c| is_cbc_padding_valid(c, KEY, IV))
+            .collect()
+    }
+}
+
+fn ciphertexts() -> Vec<Vec<u8>> {
+    let lines = fs::read_to_string(LINES_PATH).unwrap();
+    lines.lines().map(|line| {
+        let data = Encoding::Base64(STANDARD).decode(line.as_bytes())
+            .unwrap();
+        encrypt_aes_cbc(data.as_slice(), KEY, IV)
+    }).collect()
+}
+
+fn strategies() -> Vec<(&'static str, Strategy)> {
+    vec![("sequential", Strategy::new()),
+         ("english", Strategy::english()),
+         ("english_batch_16", Strategy{batch_size: 16,
+                                       ..Strategy::english()}),
+         ("english_threads_4", Strategy{threads: 4,
+                                        ..Strategy::english()})]
+}
+
+/*
+ * Print the average number of the queries and requests per byte
+ */
+fn report_queries() {
+    let ciphertexts = ciphertexts();
+    let total: usize = ciphertexts.iter().map(|c| c.len()).sum();
+    for (name, strategy) in strategies().iter() {
+        let oracle = Oracle{requests: AtomicUsize::new(0)};
+        let queries: usize = ciphertexts.iter().map(|ciphertext| {
+            decrypt_with(&oracle, IV, ciphertext, strategy).queries
+        }).sum();
+        let requests = oracle.requests.load(Ordering::Relaxed);
+        println!("{}: {:.1} queries/byte, {:.1} requests/byte", name,
+                 queries as f64 / total as f64,
+                 requests as f64 / total as f64);
+    }
+}
+
+fn bench_decrypt(c: &mut Criterion) {
+    report_queries();
+    let ciphertexts = ciphertexts();
+    for (name, strategy) in strategies().into_iter() {
+        c.bench_function(name, |b| b.iter(|| {
+            let oracle = Oracle{requests: AtomicUsize::new(0)};
+            for ciphertext in ciphertexts.iter() {
+                decrypt_with(&oracle, IV, ciphertext, &strategy);
+            }
+        }));
+    }
+}
+
+criterion_group!(benches, bench_decrypt);
+criterion_main!(benches);
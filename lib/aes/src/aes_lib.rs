@@ -3,15 +3,15 @@
  * Dmitry Vasiliev <dima@hlabs.org>
  */
 
-#![crate_name="aes_lib"]
-#![crate_type="lib"]
-
 extern crate libc;
-extern crate serialize;
 
-use std::iter::repeat;
+use std::iter::repeat_n;
 use libc::{c_int, c_uint};
 
+pub mod bitflip;
+pub mod ecb;
+pub mod oracle;
+pub mod padding_oracle;
 
 pub static AES_BLOCK_SIZE: usize = 16;
 
@@ -23,7 +23,7 @@ struct AesKey {
 }
 
 #[link(name="crypto")]
-extern {
+extern "C" {
     fn AES_set_decrypt_key(userKey: *const u8, bits: c_int,
                            key: *mut AesKey) -> c_int;
     fn AES_set_encrypt_key(userKey: *const u8, bits: c_int,
@@ -78,7 +78,7 @@ pub fn decrypt_aes_cbc_raw(encrypted: &[u8], key: &[u8], iv: &[u8])
     if iv.len() != AES_BLOCK_SIZE {
         panic!("Invalid IV size");
     }
-    if encrypted.len() % AES_BLOCK_SIZE != 0 {
+    if !encrypted.len().is_multiple_of(AES_BLOCK_SIZE) {
         panic!("Invalid size of encrypted data");
     }
     let mut data = encrypted.to_vec();
@@ -141,7 +141,8 @@ pub fn decrypt_aes_ctr(encrypted: &[u8], key: &[u8], nonce: u64) -> Vec<u8> {
         let nonce_str = u64_to_vec(nonce);
         let blocks = data.as_mut_slice().chunks_mut(AES_BLOCK_SIZE);
         for (i, block) in blocks.enumerate() {
-            let mut input = nonce_str.clone() + u64_to_vec(i as u64).as_slice();
+            let mut input = nonce_str.clone();
+            input.extend_from_slice(&u64_to_vec(i as u64));
             // Encrypt in-place
             unsafe {AES_encrypt(input.as_ptr(), input.as_mut_ptr(), &aes_key)};
             // XOR encrypted nonce/counter with the encrypted block in-place
@@ -155,11 +156,7 @@ pub fn decrypt_aes_ctr(encrypted: &[u8], key: &[u8], nonce: u64) -> Vec<u8> {
 
 #[inline]
 fn u64_to_vec(value: u64) -> Vec<u8> {
-    let mut buf = Vec::with_capacity(8);
-    match buf.write_le_u64(value) {
-        Ok(()) => buf,
-        Err(err) => panic!("Memory write error: {}", err)
-    }
+    value.to_le_bytes().to_vec()
 }
 
 /*
@@ -208,7 +205,7 @@ pub fn remove_pkcs7_padding(mut data: Vec<u8>) -> Vec<u8> {
     match data.last() {
         Some(&last) if last > 0 && (last as usize) < AES_BLOCK_SIZE => {
             let data_len = data.len() - last as usize;
-            if data.slice_from(data_len).iter().all(|&c| c == last) {
+            if data[data_len..].iter().all(|&c| c == last) {
                 data.truncate(data_len);
             }
             data
@@ -227,7 +224,7 @@ fn pkcs7_padding(data: &[u8]) -> Vec<u8> {
         0 => r,
         size => {
             let pad = AES_BLOCK_SIZE - size;
-            r.extend(repeat(pad as u8).take(pad));
+            r.extend(repeat_n(pad as u8, pad));
             r
         }
     }
@@ -238,32 +235,36 @@ fn pkcs7_padding(data: &[u8]) -> Vec<u8> {
  */
 #[cfg(test)]
 mod test {
-    use serialize::hex::FromHex;
+    use codec::Encoding;
     use super::{encrypt_aes_ecb, decrypt_aes_ecb};
     use super::{encrypt_aes_cbc, decrypt_aes_cbc};
     use super::{encrypt_aes_ctr, decrypt_aes_ctr};
 
+    fn from_hex(hex: &str) -> Vec<u8> {
+        Encoding::Hex.decode(hex.as_bytes()).unwrap()
+    }
+
     #[test]
     fn test_aes_ecb_decrypt() {
-        let key = "00000000000000000000000000000000".from_hex().unwrap();
-        let ciphertext = "0336763e966d92595a567cc9ce537f5e".from_hex().unwrap();
+        let key = from_hex("00000000000000000000000000000000");
+        let ciphertext = from_hex("0336763e966d92595a567cc9ce537f5e");
         assert_eq!(decrypt_aes_ecb(ciphertext.as_slice(), key.as_slice()),
-            "f34481ec3cc627bacd5dc3fb08f273e6".from_hex().unwrap());
+            from_hex("f34481ec3cc627bacd5dc3fb08f273e6"));
 
-        let key2 = "10a58869d74be5a374cf867cfb473859".from_hex().unwrap();
-        let ciphertext2 = "6d251e6944b051e04eaa6fb4dbf78465".from_hex().unwrap();
+        let key2 = from_hex("10a58869d74be5a374cf867cfb473859");
+        let ciphertext2 = from_hex("6d251e6944b051e04eaa6fb4dbf78465");
         assert_eq!(decrypt_aes_ecb(ciphertext2.as_slice(), key2.as_slice()),
-            "00000000000000000000000000000000".from_hex().unwrap());
+            from_hex("00000000000000000000000000000000"));
 
-        let key3 = "80000000000000000000000000000000".from_hex().unwrap();
-        let ciphertext3 = "0edd33d3c621e546455bd8ba1418bec8".from_hex().unwrap();
+        let key3 = from_hex("80000000000000000000000000000000");
+        let ciphertext3 = from_hex("0edd33d3c621e546455bd8ba1418bec8");
         assert_eq!(decrypt_aes_ecb(ciphertext3.as_slice(), key3.as_slice()),
-            "00000000000000000000000000000000".from_hex().unwrap());
+            from_hex("00000000000000000000000000000000"));
 
-        let key4 = "00000000000000000000000000000000".from_hex().unwrap();
-        let ciphertext4 = "3ad78e726c1ec02b7ebfe92b23d9ec34".from_hex().unwrap();
+        let key4 = from_hex("00000000000000000000000000000000");
+        let ciphertext4 = from_hex("3ad78e726c1ec02b7ebfe92b23d9ec34");
         assert_eq!(decrypt_aes_ecb(ciphertext4.as_slice(), key4.as_slice()),
-            "80000000000000000000000000000000".from_hex().unwrap());
+            from_hex("80000000000000000000000000000000"));
 
     }
 
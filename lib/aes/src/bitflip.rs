@@ -0,0 +1,154 @@
+/* Bit-flipping attack planner
+ *
+ * The known plaintext is replaced with the desired one by XOR of the
+ * ciphertext with the difference of the plaintexts. In CTR mode the byte of
+ * the ciphertext is flipped at the same offset. In CBC mode the byte of the
+ * previous ciphertext block is flipped and the previous plaintext block is
+ * scrambled. The offsets are relative to the start of the ciphertext without
+ * the IV.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+use std::fmt;
+
+use crate::oracle::Mode;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum PlanError {
+    // The known and desired plaintexts have the different length
+    LengthMismatch(usize, usize),
+    // The mode doesn't allow the bit flipping
+    UnsupportedMode(Mode),
+    // The CBC change of the first block requires the IV
+    FirstBlock,
+    // The block is both changed and scrambled
+    Overlap(usize)
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PlanError::LengthMismatch(known, desired) =>
+                write!(f, "Known plaintext has {} bytes, desired has {}",
+                       known, desired),
+            PlanError::UnsupportedMode(mode) =>
+                write!(f, "Bit flipping is impossible in {:?} mode", mode),
+            PlanError::FirstBlock =>
+                write!(f, "The first block can only be changed with the IV"),
+            PlanError::Overlap(block) =>
+                write!(f, "Block {} is both changed and scrambled", block)
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Plan {
+    // Offsets of the ciphertext bytes and their XOR differences
+    pub flips: Vec<(usize, u8)>,
+    // Indexes of the plaintext blocks scrambled as the side effect
+    pub scrambled: Vec<usize>
+}
+
+impl Plan {
+    pub fn apply(&self, ciphertext: &mut [u8]) {
+        for &(offset, delta) in self.flips.iter() {
+            ciphertext[offset] ^= delta;
+        }
+    }
+}
+
+/*
+ * Plan the replacement of the known plaintext at the offset
+ */
+pub fn plan(mode: Mode, offset: usize, known: &[u8], desired: &[u8],
+            block_size: usize) -> Result<Plan, PlanError> {
+    if known.len() != desired.len() {
+        return Err(PlanError::LengthMismatch(known.len(), desired.len()));
+    }
+    let changes = known.iter().zip(desired.iter()).enumerate()
+        .filter(|&(_, (&c1, &c2))| c1 != c2)
+        .map(|(i, (&c1, &c2))| (offset + i, c1 ^ c2));
+    let flips: Vec<(usize, u8)> = match mode {
+        Mode::ECB => return Err(PlanError::UnsupportedMode(mode)),
+        Mode::CTR => changes.collect(),
+        Mode::CBC => {
+            let mut flips = Vec::new();
+            for (pos, delta) in changes {
+                if pos < block_size {
+                    return Err(PlanError::FirstBlock);
+                }
+                flips.push((pos - block_size, delta));
+            }
+            flips
+        }
+    };
+    let mut scrambled: Vec<usize> = match mode {
+        Mode::CBC => flips.iter().map(|&(pos, _)| pos / block_size).collect(),
+        _ => Vec::new()
+    };
+    scrambled.dedup();
+    if let Some(&block) = scrambled.iter().find(|&&block| {
+        flips.iter().any(|&(pos, _)| (pos + block_size) / block_size == block)
+    }) {
+        return Err(PlanError::Overlap(block));
+    }
+    Ok(Plan{flips, scrambled})
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use super::{plan, PlanError};
+    use crate::oracle::Mode;
+    use crate::{encrypt_aes_cbc, decrypt_aes_cbc, encrypt_aes_ctr,
+                decrypt_aes_ctr};
+
+    static KEY: &[u8] = b"YELLOW SUBMARINE";
+    static IV: &[u8] = b"0123456789abcdef";
+    static TEXT: &[u8] = b"comment1=cooking%20MCs;userdata=AAAAAAAAAAAAAAAA\
+                           ?admin?true;comment2=%20like";
+
+    #[test]
+    fn test_cbc() {
+        let mut ciphertext = encrypt_aes_cbc(TEXT, KEY, IV);
+        let plan = plan(Mode::CBC, 48, b"?admin?true", b";admin=true",
+                        16).unwrap();
+        assert_eq!(plan.flips, vec![(32, 4), (38, 2)]);
+        assert_eq!(plan.scrambled, vec![2]);
+        plan.apply(&mut ciphertext);
+        let plaintext = decrypt_aes_cbc(&ciphertext, KEY, IV);
+        assert_eq!(&plaintext[..32], &TEXT[..32]);
+        assert_eq!(&plaintext[48..], b";admin=true;comment2=%20like");
+    }
+
+    #[test]
+    fn test_ctr() {
+        let mut ciphertext = encrypt_aes_ctr(TEXT, KEY, 0);
+        let plan = plan(Mode::CTR, 48, b"?admin?true", b";admin=true",
+                        16).unwrap();
+        assert_eq!(plan.flips, vec![(48, 4), (54, 2)]);
+        assert!(plan.scrambled.is_empty());
+        plan.apply(&mut ciphertext);
+        let plaintext = decrypt_aes_ctr(&ciphertext, KEY, 0);
+        assert_eq!(&plaintext[..48], &TEXT[..48]);
+        assert_eq!(&plaintext[48..], b";admin=true;comment2=%20like");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(plan(Mode::CBC, 0, b"abc", b"ab", 16),
+                   Err(PlanError::LengthMismatch(3, 2)));
+        assert_eq!(plan(Mode::ECB, 16, b"a", b"b", 16),
+                   Err(PlanError::UnsupportedMode(Mode::ECB)));
+        assert_eq!(plan(Mode::CBC, 15, b"a", b"b", 16),
+                   Err(PlanError::FirstBlock));
+        // The change of the block 2 scrambles the block 1
+        assert_eq!(plan(Mode::CBC, 30, b"abcd", b"ABCD", 16),
+                   Err(PlanError::Overlap(1)));
+        assert_eq!(plan(Mode::CTR, 5, b"abcd", b"abcd", 16).unwrap().flips,
+                   Vec::new());
+    }
+}
@@ -0,0 +1,227 @@
+/* ECB detection and block structure analysis
+ *
+ * ECB encrypts equal plaintext blocks to equal ciphertext blocks so the
+ * repeated ciphertext blocks reveal the mode. The buffer is split into blocks
+ * starting from the alignment offset, the bytes before the offset and the
+ * trailing incomplete block are ignored.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+// Block sizes of the common block ciphers
+pub static BLOCK_SIZES: [usize; 2] = [8, 16];
+
+// Block which occurs more than once and indexes of all its occurrences
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RepeatedBlock {
+    pub block: Vec<u8>,
+    pub positions: Vec<usize>
+}
+
+// Block structure of the buffer for the block size and alignment offset
+#[derive(Clone, PartialEq, Debug)]
+pub struct BlockAnalysis {
+    pub block_size: usize,
+    pub offset: usize,
+    // Number of the full blocks
+    pub blocks: usize,
+    // Repeated blocks ordered by the first occurrence
+    pub repeated: Vec<RepeatedBlock>,
+    // Likelihood of ECB from 0.0 to 1.0
+    pub score: f64
+}
+
+impl BlockAnalysis {
+    /*
+     * Number of blocks which repeat one of the previous blocks
+     */
+    pub fn repeats(&self) -> usize {
+        self.repeated.iter().map(|r| r.positions.len() - 1).sum()
+    }
+
+    /*
+     * Offset of the block in the buffer
+     */
+    pub fn block_offset(&self, index: usize) -> usize {
+        self.offset + index * self.block_size
+    }
+}
+
+/*
+ * Find repeated blocks of the buffer
+ */
+pub fn analyze_blocks(buffer: &[u8], block_size: usize, offset: usize)
+        -> BlockAnalysis {
+    if block_size == 0 {
+        panic!("Zero block size");
+    }
+    let data = if offset < buffer.len() { &buffer[offset..] } else { &[] };
+    let mut positions: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    let mut order = Vec::new();
+    let blocks = data.len() / block_size;
+    for (i, block) in data.chunks(block_size).take(blocks).enumerate() {
+        let seen = positions.entry(block).or_default();
+        if seen.is_empty() {
+            order.push(block);
+        }
+        seen.push(i);
+    }
+    let repeated: Vec<RepeatedBlock> = order.into_iter().filter_map(|b| {
+        let seen = positions.remove(b).unwrap();
+        match seen.len() {
+            1 => None,
+            _ => Some(RepeatedBlock{block: b.to_vec(), positions: seen})
+        }
+    }).collect();
+    let mut analysis = BlockAnalysis{block_size, offset, blocks, repeated,
+                                     score: 0.0};
+    analysis.score = ecb_score(&analysis);
+    analysis
+}
+
+/*
+ * Share of the blocks which repeat the previous ones in excess of the
+ * repeats expected for the random data
+ */
+fn ecb_score(analysis: &BlockAnalysis) -> f64 {
+    if analysis.blocks < 2 {
+        return 0.0;
+    }
+    let n = analysis.blocks as f64;
+    let pairs = n * (n - 1.0) / 2.0;
+    let expected = pairs / 2f64.powi(8 * analysis.block_size as i32);
+    let excess = analysis.repeats() as f64 - expected;
+    (excess / (n - 1.0)).clamp(0.0, 1.0)
+}
+
+/*
+ * Is the buffer likely to be ECB encrypted with the block size?
+ */
+pub fn is_ecb_encrypted(buffer: &[u8], block_size: usize) -> bool {
+    analyze_blocks(buffer, block_size, 0).score > 0.0
+}
+
+/*
+ * Analyze the buffer at all the alignment offsets and return the analysis
+ * with the highest score
+ */
+pub fn best_alignment(buffer: &[u8], block_size: usize) -> BlockAnalysis {
+    let mut analyses: Vec<BlockAnalysis> = (0..block_size).map(|offset| {
+        analyze_blocks(buffer, block_size, offset)
+    }).collect();
+    // The sort is stable so the smaller offset wins on equal scores
+    analyses.sort_by(compare_scores);
+    analyses.swap_remove(0)
+}
+
+/*
+ * Analyze the buffer with all the block sizes and alignment offsets and
+ * return the best analysis for every block size ranked by the score, the
+ * larger block size wins on equal scores since the repeats of the block are
+ * also the repeats of its halves
+ */
+pub fn detect_ecb(buffer: &[u8], block_sizes: &[usize])
+        -> Vec<BlockAnalysis> {
+    let mut sizes = block_sizes.to_vec();
+    sizes.sort_by(|a, b| b.cmp(a));
+    let mut analyses: Vec<BlockAnalysis> = sizes.into_iter()
+        .map(|size| best_alignment(buffer, size)).collect();
+    analyses.sort_by(compare_scores);
+    analyses
+}
+
+/*
+ * Rank the corpus lines by the likelihood of ECB, return the line indexes
+ * with their best analyses
+ */
+pub fn rank_lines<T: AsRef<[u8]>>(lines: &[T], block_size: usize)
+        -> Vec<(usize, BlockAnalysis)> {
+    let mut ranked: Vec<(usize, BlockAnalysis)> = lines.iter().enumerate()
+        .map(|(i, line)| (i, best_alignment(line.as_ref(), block_size)))
+        .collect();
+    ranked.sort_by(|first, second| compare_scores(&first.1, &second.1));
+    ranked
+}
+
+fn compare_scores(first: &BlockAnalysis, second: &BlockAnalysis)
+        -> Ordering {
+    second.score.partial_cmp(&first.score).unwrap_or(Ordering::Equal)
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use codec::Encoding;
+    use super::{analyze_blocks, is_ecb_encrypted, best_alignment,
+                detect_ecb, rank_lines, RepeatedBlock, BLOCK_SIZES};
+
+    fn blocks(ids: &[u8], size: usize) -> Vec<u8> {
+        ids.iter().flat_map(|&id| {
+            (0..size).map(move |i| id.wrapping_mul(37) ^ (i as u8 * 11))
+        }).collect()
+    }
+
+    #[test]
+    fn test_analyze_blocks() {
+        let data = blocks(&[1, 2, 1, 3, 2, 1], 16);
+        let analysis = analyze_blocks(data.as_slice(), 16, 0);
+        assert_eq!(analysis.blocks, 6);
+        assert_eq!(analysis.repeated, vec![
+            RepeatedBlock{block: data[..16].to_vec(),
+                          positions: vec![0, 2, 5]},
+            RepeatedBlock{block: data[16..32].to_vec(),
+                          positions: vec![1, 4]}
+        ]);
+        assert_eq!(analysis.repeats(), 3);
+        assert_eq!(analysis.score, 0.6);
+        assert_eq!(analysis.block_offset(5), 80);
+
+        let distinct = blocks(&[1, 2, 3, 4], 16);
+        assert_eq!(analyze_blocks(distinct.as_slice(), 16, 0).score, 0.0);
+        assert!(is_ecb_encrypted(data.as_slice(), 16));
+        assert!(!is_ecb_encrypted(distinct.as_slice(), 16));
+    }
+
+    #[test]
+    fn test_is_ecb_encrypted() {
+        let buffer = "000102030405060708090a0b0c0d0e0f\
+                      101112131415161718191a1b1c1d1e1f\
+                      202122232425262728292a2b2c2d2e2f";
+        let bin = Encoding::Hex.decode(buffer.as_bytes()).unwrap();
+        assert!(!is_ecb_encrypted(bin.as_slice(), 16));
+
+        let buffer = "000102030405060708090a0b0c0d0e0f\
+                      101112131415161718191a1b1c1d1e1f\
+                      000102030405060708090a0b0c0d0e0f";
+        let bin = Encoding::Hex.decode(buffer.as_bytes()).unwrap();
+        assert!(is_ecb_encrypted(bin.as_slice(), 16));
+    }
+
+    #[test]
+    fn test_alignment() {
+        let data = [b"prefix".to_vec(), blocks(&[1, 1, 2], 16)].concat();
+        assert!(!is_ecb_encrypted(data.as_slice(), 16));
+        let analysis = best_alignment(data.as_slice(), 16);
+        assert_eq!(analysis.offset, 6);
+        assert_eq!(analysis.repeats(), 1);
+
+        let ranked = detect_ecb(data.as_slice(), &BLOCK_SIZES);
+        assert_eq!(ranked[0].block_size, 16);
+        assert_eq!(ranked[0].offset, 6);
+    }
+
+    #[test]
+    fn test_rank_lines() {
+        let lines = vec![blocks(&[1, 2, 3, 4], 16),
+                         blocks(&[5, 6, 5, 7], 16),
+                         blocks(&[8, 8, 8, 8], 16)];
+        let ranked: Vec<usize> = rank_lines(lines.as_slice(), 16)
+            .into_iter().map(|(i, _)| i).collect();
+        assert_eq!(ranked, vec![2, 1, 0]);
+    }
+}
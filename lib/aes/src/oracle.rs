@@ -0,0 +1,482 @@
+/* Attacks against black-box encryption oracles
+ *
+ * The oracle encrypts the attacker-controlled input, possibly surrounded by
+ * the unknown prefix and suffix. Every attack returns the number of the
+ * oracle queries it used along with the result.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::collections::HashMap;
+
+use crate::ecb::is_ecb_encrypted;
+
+// Maximum block size tried by the block size detection
+static MAX_BLOCK_SIZE: usize = 256;
+// Number of the queries which should confirm the detected block size
+static CONFIRM_QUERIES: usize = 16;
+
+// Filler bytes of the attacker-controlled input
+static FILLER: u8 = b'A';
+static MARKERS: [u8; 2] = [b'B', b'C'];
+
+// Number of the marker blocks used to find the encrypted marker block
+static MARKER_RUN: usize = 3;
+// Number of the alignment attempts per block size byte
+static MAX_ALIGN_ROUNDS: usize = 64;
+
+pub trait EncryptionOracle {
+    fn encrypt(&self, input: &[u8]) -> Vec<u8>;
+}
+
+impl<F: Fn(&[u8]) -> Vec<u8>> EncryptionOracle for F {
+    fn encrypt(&self, input: &[u8]) -> Vec<u8> {
+        self(input)
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    ECB,
+    CBC,
+    CTR
+}
+
+// Result of the attack and number of the oracle queries
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Outcome<T> {
+    pub value: T,
+    pub queries: usize
+}
+
+/*
+ * Oracle wrapper which counts the queries, the padding oracles are counted
+ * as well
+ */
+pub struct CountingOracle<'a, O: ?Sized> {
+    oracle: &'a O,
+    queries: AtomicUsize
+}
+
+impl<'a, O: ?Sized> CountingOracle<'a, O> {
+    pub fn new(oracle: &'a O) -> CountingOracle<'a, O> {
+        CountingOracle{oracle, queries: AtomicUsize::new(0)}
+    }
+
+    pub fn queries(&self) -> usize {
+        self.queries.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn oracle(&self) -> &O {
+        self.oracle
+    }
+
+    pub(crate) fn count(&self, queries: usize) {
+        self.queries.fetch_add(queries, Ordering::Relaxed);
+    }
+
+    pub(crate) fn outcome<T>(&self, value: T) -> Outcome<T> {
+        Outcome{value, queries: self.queries()}
+    }
+}
+
+impl<O> EncryptionOracle for CountingOracle<'_, O>
+        where O: EncryptionOracle + ?Sized {
+    fn encrypt(&self, input: &[u8]) -> Vec<u8> {
+        self.count(1);
+        self.oracle.encrypt(input)
+    }
+}
+
+/*
+ * Detect the block size as the greatest common divisor of the ciphertext
+ * lengths, the divisor should stay the same for a number of queries since
+ * the random prefix can make all the lengths multiples of the larger size
+ */
+pub fn detect_block_size<O: EncryptionOracle + ?Sized>(oracle: &O)
+        -> Outcome<Option<usize>> {
+    let oracle = CountingOracle::new(oracle);
+    let input = vec![FILLER; MAX_BLOCK_SIZE + CONFIRM_QUERIES];
+    let first = oracle.encrypt(&[]).len();
+    let mut size = first;
+    let mut grown = false;
+    let mut unchanged = 0;
+    for len in 1..=input.len() {
+        let encrypted = oracle.encrypt(&input[..len]).len();
+        let divisor = gcd(size, encrypted);
+        grown = grown || encrypted != first;
+        unchanged = if divisor == size { unchanged + 1 } else { 0 };
+        size = divisor;
+        if grown && unchanged >= CONFIRM_QUERIES {
+            return oracle.outcome(Some(size));
+        }
+    }
+    oracle.outcome(None)
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    match b {
+        0 => a,
+        _ => gcd(b, a % b)
+    }
+}
+
+/*
+ * Detect the mode by the repeated blocks, three blocks of input give at
+ * least two repeated blocks whatever the prefix length is. Without the
+ * repeated blocks the mode is CTR if one more byte of input makes the output
+ * one byte longer since CTR doesn't pad, otherwise the mode is CBC.
+ */
+pub fn detect_mode<O: EncryptionOracle + ?Sized>(oracle: &O,
+                                                 block_size: usize)
+        -> Outcome<Mode> {
+    let oracle = CountingOracle::new(oracle);
+    let mut input = vec![FILLER; block_size * 3];
+    let encrypted = oracle.encrypt(&input);
+    if is_ecb_encrypted(&encrypted, block_size) {
+        return oracle.outcome(Mode::ECB);
+    }
+    input.push(FILLER);
+    let mode = match oracle.encrypt(&input).len() == encrypted.len() + 1 {
+        true => Mode::CTR,
+        false => Mode::CBC
+    };
+    oracle.outcome(mode)
+}
+
+/*
+ * Detect the length of the fixed prefix of the ECB oracle
+ *
+ * The input is the alignment filler followed by two equal marker blocks.
+ * The marker blocks are encrypted to the equal pair of blocks only when the
+ * filler aligns them, the second marker rules out the pairs made by the
+ * prefix or by the suffix which starts like the marker.
+ */
+pub fn detect_prefix_len<O: EncryptionOracle + ?Sized>(oracle: &O,
+                                                       block_size: usize)
+        -> Outcome<Option<usize>> {
+    let oracle = CountingOracle::new(oracle);
+    for n in 0..block_size {
+        let encrypted: Vec<Vec<u8>> = MARKERS.iter().map(|&marker| {
+            let input = [vec![FILLER; n],
+                         vec![marker; block_size * 2]].concat();
+            oracle.encrypt(&input)
+        }).collect();
+        let first = &encrypted[0];
+        let second = &encrypted[1];
+        let blocks = first.len().min(second.len()) / block_size;
+        let block = |buf: &[u8], i: usize| {
+            buf[i * block_size..(i + 1) * block_size].to_vec()
+        };
+        for i in 0..blocks.saturating_sub(1) {
+            if block(first, i) == block(first, i + 1)
+                    && block(second, i) == block(second, i + 1)
+                    && block(first, i) != block(second, i) {
+                return oracle.outcome(Some(i * block_size - n));
+            }
+        }
+    }
+    oracle.outcome(None)
+}
+
+/*
+ * Recover the unknown suffix of the ECB oracle byte at a time
+ *
+ * The function encrypts the input placed at the block boundary and returns
+ * the ciphertext from the start of the input or None if the oracle can't be
+ * aligned.
+ *
+ * The next unknown byte is shifted to the end of the block whose other bytes
+ * are already known, the block is then matched against the encryption of all
+ * the possible last bytes. The first padding byte is matched as 0x01 and the
+ * recovery stops when the next block is the padding 0x02 0x02.
+ */
+fn recover_aligned<F>(encrypt: F, block_size: usize) -> Option<Vec<u8>>
+        where F: Fn(&[u8]) -> Option<Vec<u8>> {
+    let mut known = vec![FILLER; block_size - 1];
+    loop {
+        let i = known.len() - (block_size - 1);
+        let shift = vec![FILLER; block_size - 1 - i % block_size];
+        let offset = i / block_size * block_size;
+        let target = encrypt(&shift)?;
+        if target.len() < offset + block_size {
+            return None;
+        }
+        let target = &target[offset..offset + block_size];
+        let window = &known[known.len() - (block_size - 1)..];
+        let mut found = None;
+        for c in 0..=255u8 {
+            let input = [window, &[c]].concat();
+            if &encrypt(&input)?[..block_size] == target {
+                found = Some(c);
+                break;
+            }
+        }
+        match found {
+            Some(1) if is_padding(&encrypt, &known, block_size)? => break,
+            Some(c) => known.push(c),
+            None => return None
+        }
+    }
+    Some(known[block_size - 1..].to_vec())
+}
+
+/*
+ * Check if the next unknown byte is the start of the padding, in this case
+ * the next block ends with 0x02 0x02
+ */
+fn is_padding<F>(encrypt: &F, known: &[u8], block_size: usize)
+        -> Option<bool>
+        where F: Fn(&[u8]) -> Option<Vec<u8>> {
+    if block_size < 2 {
+        return Some(true);
+    }
+    let i = known.len() + 1 - (block_size - 1);
+    let shift = vec![FILLER; block_size - 1 - i % block_size];
+    let offset = i / block_size * block_size;
+    let target = encrypt(&shift)?;
+    if target.len() < offset + block_size {
+        return Some(true);
+    }
+    let input = [&known[known.len() - (block_size - 2)..], &[2, 2]].concat();
+    let encrypted = encrypt(&input)?;
+    Some(encrypted[..block_size] == target[offset..offset + block_size])
+}
+
+/*
+ * Recover the unknown suffix of the ECB oracle with the fixed prefix
+ */
+pub fn recover_suffix<O: EncryptionOracle + ?Sized>(oracle: &O,
+                                                    block_size: usize,
+                                                    prefix_len: usize)
+        -> Outcome<Option<Vec<u8>>> {
+    let oracle = CountingOracle::new(oracle);
+    // Filler which aligns the start of the input to the block boundary
+    let align = (block_size - prefix_len % block_size) % block_size;
+    let start = prefix_len + align;
+    let suffix = recover_aligned(|input: &[u8]| {
+        let data = [vec![FILLER; align].as_slice(), input].concat();
+        let encrypted = oracle.encrypt(&data);
+        encrypted.get(start..).map(|tail| tail.to_vec())
+    }, block_size);
+    oracle.outcome(suffix)
+}
+
+/*
+ * Find the encryption of the marker block, it's the most frequent block of
+ * the encrypted marker run and its count grows with the run
+ */
+fn find_marker_block<O: EncryptionOracle + ?Sized>(oracle: &O,
+                                                   block_size: usize)
+        -> Option<Vec<u8>> {
+    let counts = |blocks: usize| {
+        let input = [vec![FILLER], vec![MARKERS[0]; block_size * blocks],
+                     vec![FILLER]].concat();
+        let mut counts: HashMap<Vec<u8>, usize> = HashMap::new();
+        for block in oracle.encrypt(&input).chunks(block_size) {
+            *counts.entry(block.to_vec()).or_insert(0) += 1;
+        }
+        counts
+    };
+    let short = counts(MARKER_RUN);
+    let long = counts(MARKER_RUN + 2);
+    long.into_iter().max_by_key(|(block, count)| {
+        (*count, *count - short.get(block).copied().unwrap_or(0).min(*count))
+    }).filter(|&(_, count)| count > 1).map(|(block, _)| block)
+}
+
+/*
+ * Oracle which aligns the input to the block boundary whatever the prefix is
+ *
+ * The input is preceded by the guard bytes, two marker blocks and the guard
+ * block. The guards bound the marker run so the ciphertext has two marker
+ * blocks in a row only if the marker is aligned. The encryption is repeated
+ * with the different number of the guard bytes until the marker is aligned,
+ * the number is kept for the fixed prefix.
+ */
+struct AligningOracle<'a, O: EncryptionOracle + ?Sized> {
+    oracle: &'a O,
+    block_size: usize,
+    marker: Vec<u8>,
+    guard: Cell<usize>
+}
+
+impl<O: EncryptionOracle + ?Sized> AligningOracle<'_, O> {
+    fn encrypt(&self, input: &[u8]) -> Option<Vec<u8>> {
+        let size = self.block_size;
+        for _ in 0..size * MAX_ALIGN_ROUNDS {
+            let data = [vec![FILLER; self.guard.get()],
+                        vec![MARKERS[0]; size * 2], vec![FILLER; size],
+                        input.to_vec()].concat();
+            let encrypted = self.oracle.encrypt(&data);
+            let mut blocks = encrypted.chunks(size);
+            let pos = blocks.position(|block| block == &self.marker[..]);
+            if let Some(i) = pos {
+                if blocks.next() == Some(&self.marker[..]) {
+                    return Some(encrypted[(i + 3) * size..].to_vec());
+                }
+            }
+            self.guard.set(self.guard.get() % size + 1);
+        }
+        None
+    }
+}
+
+/*
+ * Recover the unknown suffix of the ECB oracle with the unknown prefix,
+ * the prefix may change with every query
+ */
+pub fn recover_suffix_random_prefix<O>(oracle: &O, block_size: usize)
+        -> Outcome<Option<Vec<u8>>>
+        where O: EncryptionOracle + ?Sized {
+    let oracle = CountingOracle::new(oracle);
+    let marker = match find_marker_block(&oracle, block_size) {
+        Some(marker) => marker,
+        None => return oracle.outcome(None)
+    };
+    let aligning = AligningOracle{oracle: &oracle, block_size, marker,
+                                  guard: Cell::new(1)};
+    let suffix = recover_aligned(|input: &[u8]| aligning.encrypt(input),
+                                 block_size);
+    oracle.outcome(suffix)
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use mersenne_twister::MersenneTwister;
+    use super::{EncryptionOracle, CountingOracle, Mode, detect_block_size,
+                detect_mode, detect_prefix_len, recover_suffix,
+                recover_suffix_random_prefix};
+    use crate::{AES_BLOCK_SIZE, encrypt_aes_ecb, encrypt_aes_cbc,
+                encrypt_aes_ctr};
+
+    struct Oracle {
+        prefix: Vec<u8>,
+        suffix: Vec<u8>,
+        mode: Mode
+    }
+
+    impl EncryptionOracle for Oracle {
+        fn encrypt(&self, input: &[u8]) -> Vec<u8> {
+            let key = b"YELLOW SUBMARINE";
+            let data = [self.prefix.as_slice(), input,
+                        self.suffix.as_slice()].concat();
+            match self.mode {
+                Mode::ECB => encrypt_aes_ecb(&data, key),
+                Mode::CBC => encrypt_aes_cbc(&data, key, &[0u8; 16]),
+                Mode::CTR => encrypt_aes_ctr(&data, key, 0)
+            }
+        }
+    }
+
+    fn oracle(prefix_len: usize, mode: Mode) -> Oracle {
+        // The prefix ends and the suffix starts like the markers
+        let prefix = (0..prefix_len).map(|i| b'B' + (i % 2) as u8)
+            .collect();
+        let suffix = [b"BBBBBBBBBBBBBBBBBB".to_vec(),
+                      (0..=255u8).rev().collect()].concat();
+        Oracle{prefix, suffix, mode}
+    }
+
+    #[test]
+    fn test_detect_block_size() {
+        let outcome = detect_block_size(&oracle(5, Mode::CBC));
+        assert_eq!(outcome.value, Some(AES_BLOCK_SIZE));
+        assert!(outcome.queries > 1 && outcome.queries <= 34);
+    }
+
+    #[test]
+    fn test_detect_mode() {
+        for prefix_len in [0, 7, 16].iter() {
+            assert_eq!(detect_mode(&oracle(*prefix_len, Mode::ECB), 16),
+                       super::Outcome{value: Mode::ECB, queries: 1});
+            assert_eq!(detect_mode(&oracle(*prefix_len, Mode::CBC), 16),
+                       super::Outcome{value: Mode::CBC, queries: 2});
+            assert_eq!(detect_mode(&oracle(*prefix_len, Mode::CTR), 16),
+                       super::Outcome{value: Mode::CTR, queries: 2});
+        }
+    }
+
+    #[test]
+    fn test_detect_prefix_len() {
+        for prefix_len in [0, 1, 15, 16, 33].iter() {
+            let outcome = detect_prefix_len(&oracle(*prefix_len, Mode::ECB),
+                                            AES_BLOCK_SIZE);
+            assert_eq!(outcome.value, Some(*prefix_len));
+        }
+    }
+
+    #[test]
+    fn test_recover_suffix() {
+        // The suffix ends at the block boundary with the prefix of 14 bytes
+        for prefix_len in [0, 14, 21].iter() {
+            let target = oracle(*prefix_len, Mode::ECB);
+            let outcome = recover_suffix(&target, AES_BLOCK_SIZE,
+                                         *prefix_len);
+            assert_eq!(outcome.value, Some(target.suffix.clone()));
+            assert!(outcome.queries > target.suffix.len());
+        }
+    }
+
+    // Oracle with the new prefix of up to 47 bytes for every query
+    struct RandomPrefixOracle {
+        rng: RefCell<MersenneTwister>,
+        suffix: Vec<u8>
+    }
+
+    impl EncryptionOracle for RandomPrefixOracle {
+        fn encrypt(&self, input: &[u8]) -> Vec<u8> {
+            let next = || self.rng.borrow_mut().rand_u32() as u8;
+            let len = next() % 48;
+            let prefix: Vec<u8> = (0..len).map(|_| next()).collect();
+            let data = [prefix.as_slice(), input,
+                        self.suffix.as_slice()].concat();
+            encrypt_aes_ecb(&data, b"YELLOW SUBMARINE")
+        }
+    }
+
+    #[test]
+    fn test_recover_suffix_padding() {
+        // The suffix ends like the padding or has the padding bytes inside
+        let suffixes = [b"".to_vec(), b"\x01".to_vec(), b"a\x02\x02".to_vec(),
+                        [vec![0xffu8; 20], vec![1, 1]].concat()];
+        for suffix in suffixes.iter() {
+            let target = Oracle{prefix: b"prefix".to_vec(),
+                                suffix: suffix.clone(), mode: Mode::ECB};
+            let outcome = recover_suffix(&target, AES_BLOCK_SIZE, 6);
+            assert_eq!(outcome.value.as_ref(), Some(suffix));
+        }
+    }
+
+    #[test]
+    fn test_recover_suffix_random_prefix() {
+        let suffix = [b"Rollin' in my 5.0\n".to_vec(),
+                      (0..=255u8).step_by(7).collect(), vec![1]].concat();
+        let rng = RefCell::new(MersenneTwister::new(1u32));
+        let target = RandomPrefixOracle{rng, suffix: suffix.clone()};
+        assert_eq!(detect_block_size(&target).value, Some(AES_BLOCK_SIZE));
+        let outcome = recover_suffix_random_prefix(&target, AES_BLOCK_SIZE);
+        assert_eq!(outcome.value, Some(suffix));
+
+        // The fixed prefix is aligned by the same number of guard bytes
+        let target = oracle(21, Mode::ECB);
+        let outcome = recover_suffix_random_prefix(&target, AES_BLOCK_SIZE);
+        assert_eq!(outcome.value, Some(target.suffix.clone()));
+    }
+
+    #[test]
+    fn test_closure_oracle() {
+        let encrypt = |input: &[u8]| encrypt_aes_ecb(input, &[0u8; 16]);
+        let counting = CountingOracle::new(&encrypt);
+        assert_eq!(detect_block_size(&counting).value, Some(16));
+        assert_eq!(detect_mode(&counting, 16).value, Mode::ECB);
+        assert_eq!(counting.queries(), 19);
+    }
+}
@@ -0,0 +1,482 @@
+/* CBC padding oracle attack
+ *
+ * The oracle tells if the CBC ciphertext has the valid PKCS#7 padding after
+ * the decryption. The last block of the ciphertext is decrypted with the
+ * previous block chosen by the attacker, so the decryption of the block
+ * (before the XOR with the previous block) is recovered byte by byte from the
+ * end. The IV of the oracle only affects the first block which is never the
+ * last one in the queries.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+use std::cmp::Ordering;
+use std::thread;
+
+use crate::{AES_BLOCK_SIZE, decrypt_aes_cbc_raw};
+use crate::oracle::{CountingOracle, Outcome};
+
+// English letters frequencies in hundredths of percents from "a" to "z"
+static LETTERS_FREQ: [f64; 26] = [
+    804.0, 148.0, 334.0, 382.0, 1249.0, 240.0, 187.0, 505.0, 757.0, 16.0,
+    54.0, 407.0, 251.0, 723.0, 764.0, 214.0, 12.0, 628.0, 651.0, 928.0,
+    273.0, 105.0, 168.0, 23.0, 166.0, 9.0];
+
+// Shares of the byte classes in English text, the letters share is split
+// by case
+static LOWERCASE_SHARE: f64 = 0.76;
+static UPPERCASE_SHARE: f64 = 0.04;
+static SPACE_SHARE: f64 = 0.15;
+// Share of every digit, punctuation character and newline
+static OTHER_SHARE: f64 = 0.002;
+// Share of every padding byte, they are rare in the text but end every
+// ciphertext
+static PADDING_SHARE: f64 = 0.0001;
+
+pub trait PaddingOracle {
+    fn is_padding_valid(&self, ciphertext: &[u8]) -> bool;
+
+    /*
+     * Check a batch of ciphertexts, the oracles which accept many
+     * ciphertexts per request should override it
+     */
+    fn are_paddings_valid(&self, ciphertexts: &[Vec<u8>]) -> Vec<bool> {
+        ciphertexts.iter().map(|c| self.is_padding_valid(c)).collect()
+    }
+}
+
+impl<F: Fn(&[u8]) -> bool> PaddingOracle for F {
+    fn is_padding_valid(&self, ciphertext: &[u8]) -> bool {
+        self(ciphertext)
+    }
+}
+
+impl<O: PaddingOracle + ?Sized> PaddingOracle for CountingOracle<'_, O> {
+    fn is_padding_valid(&self, ciphertext: &[u8]) -> bool {
+        self.count(1);
+        self.oracle().is_padding_valid(ciphertext)
+    }
+
+    fn are_paddings_valid(&self, ciphertexts: &[Vec<u8>]) -> Vec<bool> {
+        self.count(ciphertexts.len());
+        self.oracle().are_paddings_valid(ciphertexts)
+    }
+}
+
+// Order of the guesses, size of the batches and number of the threads
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Strategy {
+    // Plaintext bytes from the most likely one or None to try the bytes of
+    // the forged block in the ascending order
+    pub order: Option<Vec<u8>>,
+    // Number of the ciphertexts per oracle request
+    pub batch_size: usize,
+    // Number of the blocks decrypted concurrently
+    pub threads: usize
+}
+
+impl Strategy {
+    /*
+     * Sequential guesses one at a time
+     */
+    pub fn new() -> Strategy {
+        Strategy{order: None, batch_size: 1, threads: 1}
+    }
+
+    /*
+     * Bytes from the most frequent one, the equally frequent bytes are tried
+     * in the ascending order
+     */
+    pub fn from_frequencies(frequencies: &[f64; 256]) -> Strategy {
+        let mut order: Vec<u8> = (0..=255u8).collect();
+        // The sort is stable so the ascending order of the ties is kept
+        order.sort_by(|&first, &second| {
+            frequencies[second as usize]
+                .partial_cmp(&frequencies[first as usize])
+                .unwrap_or(Ordering::Equal)
+        });
+        Strategy{order: Some(order), batch_size: 1, threads: 1}
+    }
+
+    /*
+     * Likely English text first, the byte of the previously recovered
+     * position is tried before the others since the padding bytes and
+     * doubled letters repeat
+     */
+    pub fn english() -> Strategy {
+        Strategy::from_frequencies(&english_frequencies())
+    }
+}
+
+impl Default for Strategy {
+    fn default() -> Strategy {
+        Strategy::new()
+    }
+}
+
+/*
+ * Byte frequencies of English text with the PKCS#7 padding
+ */
+pub fn english_frequencies() -> [f64; 256] {
+    let letters_sum: f64 = LETTERS_FREQ.iter().sum();
+    let mut frequencies = [0.0; 256];
+    for (i, &freq) in LETTERS_FREQ.iter().enumerate() {
+        frequencies[b'a' as usize + i] = LOWERCASE_SHARE * freq / letters_sum;
+        frequencies[b'A' as usize + i] = UPPERCASE_SHARE * freq / letters_sum;
+    }
+    frequencies[b' ' as usize] = SPACE_SHARE;
+    for &c in b"0123456789.,'\"-!?;:()\n".iter() {
+        frequencies[c as usize] = OTHER_SHARE;
+    }
+    for freq in frequencies[1..=AES_BLOCK_SIZE].iter_mut() {
+        *freq += PADDING_SHARE;
+    }
+    frequencies
+}
+
+/*
+ * Check the padding of the ciphertext decrypted by AES CBC with the key and
+ * IV, the oracle of the challenge 17
+ */
+pub fn is_cbc_padding_valid(ciphertext: &[u8], key: &[u8], iv: &[u8])
+        -> bool {
+    let dec = decrypt_aes_cbc_raw(ciphertext, key, iv);
+    match dec.last() {
+        Some(&last) if last > 0 && (last as usize) <= AES_BLOCK_SIZE => {
+            let data_len = dec.len() - last as usize;
+            dec[data_len..].iter().all(|&c| c == last)
+        }
+        _ => false
+    }
+}
+
+/*
+ * Bytes of the forged block to try for the position
+ */
+fn guesses(strategy: &Strategy, prev: Option<&[u8]>, decrypted: &[u8],
+           pos: usize, pad: u8) -> Vec<u8> {
+    match (&strategy.order, prev) {
+        (Some(order), Some(prev)) => {
+            let repeated = match pos + 1 < decrypted.len() {
+                true => Some(decrypted[pos + 1] ^ prev[pos + 1]),
+                false => None
+            };
+            let rest = order.iter().copied().filter(|&c| Some(c) != repeated);
+            repeated.into_iter().chain(rest)
+                .map(|c| c ^ prev[pos] ^ pad).collect()
+        },
+        _ => (0..=255u8).collect()
+    }
+}
+
+/*
+ * Recover the decryption of the block before the XOR with the previous block
+ *
+ * The previous block of the ciphertext is only used to order the guesses.
+ * The padding 0x01 is checked by changing the second-to-last byte since the
+ * valid padding can also be 0x02 0x02 and so on if the decrypted block ends
+ * like that.
+ */
+fn decrypt_block<O>(oracle: &O, block: &[u8], prev: Option<&[u8]>,
+                    strategy: &Strategy) -> Option<Vec<u8>>
+        where O: PaddingOracle + ?Sized {
+    let size = block.len();
+    let batch_size = strategy.batch_size.max(1);
+    let mut decrypted = vec![0u8; size];
+    let mut forged = vec![0u8; size * 2];
+    forged[size..].copy_from_slice(block);
+    for pos in (0..size).rev() {
+        let pad = (size - pos) as u8;
+        for i in pos + 1..size {
+            forged[i] = decrypted[i] ^ pad;
+        }
+        let guesses = guesses(strategy, prev, &decrypted, pos, pad);
+        let mut found = None;
+        for batch in guesses.chunks(batch_size) {
+            let candidates: Vec<Vec<u8>> = batch.iter().map(|&c| {
+                forged[pos] = c;
+                forged.clone()
+            }).collect();
+            let valid = oracle.are_paddings_valid(&candidates);
+            found = batch.iter().zip(valid).filter(|&(_, ok)| ok)
+                .map(|(&c, _)| c).find(|&c| {
+                    if pos != size - 1 || pos == 0 {
+                        return true;
+                    }
+                    forged[pos] = c;
+                    forged[pos - 1] ^= 0xff;
+                    let valid = oracle.is_padding_valid(&forged);
+                    forged[pos - 1] ^= 0xff;
+                    valid
+                });
+            if found.is_some() {
+                break;
+            }
+        }
+        forged[pos] = found?;
+        decrypted[pos] = found? ^ pad;
+    }
+    Some(decrypted)
+}
+
+/*
+ * Decrypt the ciphertext with the known IV, the padding isn't removed
+ */
+pub fn decrypt<O>(oracle: &O, iv: &[u8], ciphertext: &[u8])
+        -> Outcome<Option<Vec<u8>>>
+        where O: PaddingOracle + Sync + ?Sized {
+    decrypt_with(oracle, iv, ciphertext, &Strategy::new())
+}
+
+/*
+ * Decrypt the ciphertext with the strategy, the blocks are distributed
+ * between the threads
+ */
+pub fn decrypt_with<O>(oracle: &O, iv: &[u8], ciphertext: &[u8],
+                       strategy: &Strategy) -> Outcome<Option<Vec<u8>>>
+        where O: PaddingOracle + Sync + ?Sized {
+    let oracle = CountingOracle::new(oracle);
+    let size = iv.len();
+    if size == 0 || !ciphertext.len().is_multiple_of(size) {
+        return oracle.outcome(None);
+    }
+    let blocks: Vec<(&[u8], &[u8])> = ciphertext.chunks(size)
+        .scan(iv, |prev, block| {
+            let pair = (*prev, block);
+            *prev = block;
+            Some(pair)
+        }).collect();
+    let threads = strategy.threads.clamp(1, blocks.len().max(1));
+    let decrypted: Vec<Option<Vec<u8>>> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads).map(|t| {
+            let oracle = &oracle;
+            let blocks = &blocks;
+            scope.spawn(move || {
+                blocks.iter().skip(t).step_by(threads).map(|&(prev, block)| {
+                    decrypt_block(oracle, block, Some(prev), strategy)
+                        .map(|d| xor(&d, prev))
+                }).collect::<Vec<_>>()
+            })
+        }).collect();
+        let results: Vec<Vec<Option<Vec<u8>>>> = handles.into_iter()
+            .map(|handle| handle.join().unwrap()).collect();
+        // Restore the order of the blocks
+        (0..blocks.len()).map(|i| results[i % threads][i / threads].clone())
+            .collect()
+    });
+    let plaintext: Option<Vec<Vec<u8>>> = decrypted.into_iter().collect();
+    oracle.outcome(plaintext.map(|blocks| blocks.concat()))
+}
+
+/*
+ * Encrypt the plaintext without the key (CBC-R)
+ *
+ * The last block is chosen arbitrarily, every previous block is the
+ * decryption of the next block XORed with the plaintext of the next block.
+ * The first block of the result is the IV.
+ */
+pub fn encrypt<O: PaddingOracle + ?Sized>(oracle: &O, plaintext: &[u8],
+                                          block_size: usize)
+        -> Outcome<Option<Vec<u8>>> {
+    encrypt_with(oracle, plaintext, block_size, &Strategy::new())
+}
+
+/*
+ * Encrypt the plaintext with the strategy, the blocks depend on each other
+ * so only the batches are used
+ */
+pub fn encrypt_with<O: PaddingOracle + ?Sized>(oracle: &O, plaintext: &[u8],
+                                               block_size: usize,
+                                               strategy: &Strategy)
+        -> Outcome<Option<Vec<u8>>> {
+    let oracle = CountingOracle::new(oracle);
+    let padded = add_padding(plaintext, block_size);
+    let mut blocks = vec![vec![0u8; block_size]];
+    for block in padded.chunks(block_size).rev() {
+        let next = blocks.last().unwrap();
+        match decrypt_block(&oracle, next, None, strategy) {
+            Some(decrypted) => blocks.push(xor(&decrypted, block)),
+            None => return oracle.outcome(None)
+        }
+    }
+    blocks.reverse();
+    oracle.outcome(Some(blocks.concat()))
+}
+
+fn xor(first: &[u8], second: &[u8]) -> Vec<u8> {
+    first.iter().zip(second.iter()).map(|(&c1, &c2)| c1 ^ c2).collect()
+}
+
+/*
+ * PKCS#7 padding, the full block of padding is added to the aligned data
+ */
+fn add_padding(data: &[u8], block_size: usize) -> Vec<u8> {
+    let pad = block_size - data.len() % block_size;
+    [data, vec![pad as u8; pad].as_slice()].concat()
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use super::{PaddingOracle, Strategy, decrypt, decrypt_with, encrypt,
+                encrypt_with, add_padding, decrypt_block, english_frequencies,
+                is_cbc_padding_valid};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use crate::{encrypt_aes_cbc, decrypt_aes_cbc_raw};
+
+    static KEY: &[u8] = b"YELLOW SUBMARINE";
+    static IV: &[u8] = b"0123456789abcdef";
+
+    // Oracle with the fixed IV
+    fn is_padding_valid(ciphertext: &[u8]) -> bool {
+        is_cbc_padding_valid(ciphertext, KEY, IV)
+    }
+
+    // Byte-wise CBC with the toy block cipher which XORs the block with the
+    // key, the decryption of a block is the block XORed with the key
+    struct ToyOracle {
+        key: Vec<u8>
+    }
+
+    impl PaddingOracle for ToyOracle {
+        fn is_padding_valid(&self, ciphertext: &[u8]) -> bool {
+            let size = self.key.len();
+            let n = ciphertext.len();
+            let last: Vec<u8> = (0..size).map(|i| {
+                ciphertext[n - size + i] ^ self.key[i]
+                    ^ ciphertext[n - 2 * size + i]
+            }).collect();
+            let pad = last[size - 1] as usize;
+            pad > 0 && pad <= size
+                && last[size - pad..].iter().all(|&c| c as usize == pad)
+        }
+    }
+
+    #[test]
+    fn test_english_order() {
+        let mut order = Strategy::english().order.unwrap();
+        assert_eq!(order.len(), 256);
+        order.sort_unstable();
+        assert!(order.iter().enumerate().all(|(i, &c)| c as usize == i));
+
+        let order = Strategy::english().order.unwrap();
+        assert_eq!(&order[..7], b" etaoin");
+        // The padding bytes come before the other control characters
+        let rank = |c: u8| order.iter().position(|&o| o == c).unwrap();
+        assert!(rank(0x10) < rank(0x00));
+        assert!(rank(0x01) < rank(0x1f));
+        assert_eq!(english_frequencies()[0x80], 0.0);
+    }
+
+    #[test]
+    fn test_from_frequencies() {
+        let mut frequencies = [0.0; 256];
+        frequencies[200] = 2.0;
+        frequencies[7] = 1.0;
+        frequencies[3] = 1.0;
+        let order = Strategy::from_frequencies(&frequencies).order.unwrap();
+        assert_eq!(&order[..6], &[200, 3, 7, 0, 1, 2]);
+        assert_eq!(order[255], 255);
+    }
+
+    #[test]
+    fn test_decrypt() {
+        let texts: [&[u8]; 3] = [b"", b"Cooking MC's like a pound of bacon",
+                                 b"ends like padding\x02\x02"];
+        for &text in texts.iter() {
+            let padded = add_padding(text, 16);
+            let ciphertext = encrypt_aes_cbc(&padded, KEY, IV);
+            let outcome = decrypt(&is_padding_valid, IV, &ciphertext);
+            assert_eq!(outcome.value, Some(padded));
+            assert!(outcome.queries <= ciphertext.len() * 257);
+        }
+    }
+
+    #[test]
+    fn test_false_positive() {
+        // With the zero previous block the second-to-last byte is 0x02 so
+        // both 0x01 and 0x02 0x02 are valid endings
+        let oracle = ToyOracle{key: vec![0u8; 16]};
+        let mut block = vec![0x41u8; 16];
+        block[14] = 0x02;
+        for last in [0x00, 0x02, 0x03, 0xff].iter() {
+            block[15] = *last;
+            let strategy = Strategy::new();
+            assert_eq!(decrypt_block(&oracle, &block, None, &strategy),
+                       Some(block.clone()));
+        }
+    }
+
+    #[test]
+    fn test_block_sizes() {
+        for &size in [8usize, 12, 32].iter() {
+            let oracle = ToyOracle{key: (0..size as u8).map(|i| i * 7)
+                                       .collect()};
+            let text = b"The block size is not fixed";
+            let outcome = encrypt(&oracle, text, size);
+            let forged = outcome.value.unwrap();
+            assert_eq!(forged.len() % size, 0);
+            let (iv, ciphertext) = forged.split_at(size);
+            assert_eq!(decrypt(&oracle, iv, ciphertext).value,
+                       Some(add_padding(text, size)));
+        }
+    }
+
+    // Oracle which counts the requests with many ciphertexts
+    struct BatchOracle {
+        requests: AtomicUsize
+    }
+
+    impl PaddingOracle for BatchOracle {
+        fn is_padding_valid(&self, ciphertext: &[u8]) -> bool {
+            self.are_paddings_valid(&[ciphertext.to_vec()])[0]
+        }
+
+        fn are_paddings_valid(&self, ciphertexts: &[Vec<u8>]) -> Vec<bool> {
+            self.requests.fetch_add(1, Ordering::Relaxed);
+            ciphertexts.iter().map(|c| is_padding_valid(c)).collect()
+        }
+    }
+
+    #[test]
+    fn test_strategies() {
+        let padded = add_padding(b"Cooking MC's like a pound of bacon", 16);
+        let ciphertext = encrypt_aes_cbc(&padded, KEY, IV);
+        let sequential = decrypt(&is_padding_valid, IV, &ciphertext);
+        let english = decrypt_with(&is_padding_valid, IV, &ciphertext,
+                                   &Strategy::english());
+        assert_eq!(english.value, Some(padded.clone()));
+        assert!(english.queries * 4 < sequential.queries);
+
+        let parallel = Strategy{threads: 3, ..Strategy::english()};
+        let outcome = decrypt_with(&is_padding_valid, IV, &ciphertext,
+                                   &parallel);
+        assert_eq!(outcome, english);
+
+        let oracle = BatchOracle{requests: AtomicUsize::new(0)};
+        let batched = Strategy{batch_size: 16, ..Strategy::new()};
+        let outcome = decrypt_with(&oracle, IV, &ciphertext, &batched);
+        assert_eq!(outcome.value, Some(padded));
+        let requests = oracle.requests.load(Ordering::Relaxed);
+        assert!(requests * 8 < outcome.queries);
+
+        let forged = encrypt_with(&oracle, b"admin", 16, &batched).value;
+        let (iv, data) = forged.as_ref().unwrap().split_at(16);
+        assert_eq!(decrypt_aes_cbc_raw(data, KEY, iv),
+                   add_padding(b"admin", 16));
+    }
+
+    #[test]
+    fn test_encrypt() {
+        let text = b";admin=true;";
+        let forged = encrypt(&is_padding_valid, text, 16).value.unwrap();
+        // The fixed IV of the oracle garbles the first block
+        let (iv, ciphertext) = forged.split_at(16);
+        let decrypted = decrypt_aes_cbc_raw(ciphertext, KEY, iv);
+        assert_eq!(decrypted, add_padding(text, 16));
+        let garbled = decrypt_aes_cbc_raw(&forged, KEY, IV);
+        assert_eq!(&garbled[16..], decrypted.as_slice());
+    }
+}
@@ -0,0 +1,78 @@
+/* Base32 encoding (RFC 4648)
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+static CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/*
+ * Encode a group of up to 5 bytes, the partial group is padded
+ */
+pub fn encode_group(group: &[u8], out: &mut Vec<u8>) {
+    let n = group.iter().enumerate().fold(0u64, |n, (i, &c)| {
+        n | (c as u64) << (32 - i * 8)
+    });
+    let symbols = (group.len() * 8).div_ceil(5);
+    for i in 0..symbols {
+        out.push(CHARS[((n >> (35 - i * 5)) & 0x1f) as usize]);
+    }
+    for _ in symbols..8 {
+        out.push(b'=');
+    }
+}
+
+/*
+ * Decode a group of up to 8 symbols, only 2, 4, 5, 7 and 8 symbols make
+ * whole bytes
+ */
+pub fn decode_group(values: &[u8], out: &mut Vec<u8>) -> bool {
+    match values.len() {
+        2 | 4 | 5 | 7 | 8 => (),
+        _ => return false
+    }
+    let n = values.iter().enumerate().fold(0u64, |n, (i, &v)| {
+        n | (v as u64) << (35 - i * 5)
+    });
+    for i in 0..values.len() * 5 / 8 {
+        out.push((n >> (32 - i * 8)) as u8);
+    }
+    true
+}
+
+pub fn value(c: u8) -> Option<u8> {
+    match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'2'..=b'7' => Some(c - b'2' + 26),
+        _ => None
+    }
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use super::super::{Encoding, DecodeError};
+
+    #[test]
+    fn test_rfc4648_vectors() {
+        let vectors = [("", ""), ("f", "MY======"), ("fo", "MZXQ===="),
+                       ("foo", "MZXW6==="), ("foob", "MZXW6YQ="),
+                       ("fooba", "MZXW6YTB"), ("foobar", "MZXW6YTBOI======")];
+        for &(text, encoded) in vectors.iter() {
+            assert_eq!(Encoding::Base32.encode(text.as_bytes()), encoded);
+            assert_eq!(Encoding::Base32.decode(encoded.as_bytes()),
+                       Ok(text.as_bytes().to_vec()));
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(Encoding::Base32.decode(b"MZXW6YTb"),
+                   Err(DecodeError::InvalidByte(7, b'b')));
+        assert_eq!(Encoding::Base32.decode(b"MZX====="),
+                   Err(DecodeError::InvalidLength(0)));
+        assert_eq!(Encoding::Base32.decode(b"MZXW6=="),
+                   Err(DecodeError::InvalidPadding(5)));
+    }
+}
@@ -0,0 +1,145 @@
+/* Base64 encoding (RFC 4648 and RFC 2045)
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+static STANDARD_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ\
+                                         abcdefghijklmnopqrstuvwxyz\
+                                         0123456789+/";
+static URL_SAFE_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ\
+                                         abcdefghijklmnopqrstuvwxyz\
+                                         0123456789-_";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CharacterSet {
+    Standard,
+    UrlSafe
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Config {
+    pub char_set: CharacterSet,
+    // Add padding characters on encoding
+    pub pad: bool,
+    // Wrap the encoded text into lines of the length separated by CRLF
+    pub line_length: Option<usize>
+}
+
+pub static STANDARD: Config = Config{char_set: CharacterSet::Standard,
+                                     pad: true, line_length: None};
+pub static STANDARD_NO_PAD: Config = Config{char_set: CharacterSet::Standard,
+                                            pad: false, line_length: None};
+pub static URL_SAFE: Config = Config{char_set: CharacterSet::UrlSafe,
+                                     pad: true, line_length: None};
+pub static URL_SAFE_NO_PAD: Config = Config{char_set: CharacterSet::UrlSafe,
+                                            pad: false, line_length: None};
+pub static MIME: Config = Config{char_set: CharacterSet::Standard,
+                                 pad: true, line_length: Some(76)};
+
+fn chars(char_set: CharacterSet) -> &'static [u8] {
+    match char_set {
+        CharacterSet::Standard => STANDARD_CHARS,
+        CharacterSet::UrlSafe => URL_SAFE_CHARS
+    }
+}
+
+/*
+ * Encode a group of up to 3 bytes, the partial group is padded if required
+ */
+pub fn encode_group(group: &[u8], config: Config, out: &mut Vec<u8>) {
+    let chars = chars(config.char_set);
+    let n = group.iter().enumerate().fold(0u32, |n, (i, &c)| {
+        n | (c as u32) << (16 - i * 8)
+    });
+    let symbols = group.len() + 1;
+    for i in 0..symbols {
+        out.push(chars[((n >> (18 - i * 6)) & 0x3f) as usize]);
+    }
+    if config.pad {
+        for _ in symbols..4 {
+            out.push(b'=');
+        }
+    }
+}
+
+/*
+ * Decode a group of 2 to 4 symbols
+ */
+pub fn decode_group(values: &[u8], out: &mut Vec<u8>) {
+    let n = values.iter().enumerate().fold(0u32, |n, (i, &v)| {
+        n | (v as u32) << (18 - i * 6)
+    });
+    for i in 0..values.len() - 1 {
+        out.push((n >> (16 - i * 8)) as u8);
+    }
+}
+
+/*
+ * Value of the symbol in the character set
+ */
+pub fn value(c: u8, char_set: CharacterSet) -> Option<u8> {
+    match (c, char_set) {
+        (b'A'..=b'Z', _) => Some(c - b'A'),
+        (b'a'..=b'z', _) => Some(c - b'a' + 26),
+        (b'0'..=b'9', _) => Some(c - b'0' + 52),
+        (b'+', CharacterSet::Standard) => Some(62),
+        (b'/', CharacterSet::Standard) => Some(63),
+        (b'-', CharacterSet::UrlSafe) => Some(62),
+        (b'_', CharacterSet::UrlSafe) => Some(63),
+        _ => None
+    }
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use super::super::{Encoding, DecodeError};
+    use super::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD, MIME};
+
+    #[test]
+    fn test_rfc4648_vectors() {
+        let vectors = [("", ""), ("f", "Zg=="), ("fo", "Zm8="),
+                       ("foo", "Zm9v"), ("foob", "Zm9vYg=="),
+                       ("fooba", "Zm9vYmE="), ("foobar", "Zm9vYmFy")];
+        let base64 = Encoding::Base64(STANDARD);
+        for &(text, encoded) in vectors.iter() {
+            assert_eq!(base64.encode(text.as_bytes()), encoded);
+            assert_eq!(base64.decode(encoded.as_bytes()),
+                       Ok(text.as_bytes().to_vec()));
+        }
+    }
+
+    #[test]
+    fn test_configs() {
+        let data = [0xfbu8, 0xff, 0xbf, 0xfe];
+        assert_eq!(Encoding::Base64(STANDARD).encode(&data), "+/+//g==");
+        assert_eq!(Encoding::Base64(STANDARD_NO_PAD).encode(&data), "+/+//g");
+        assert_eq!(Encoding::Base64(URL_SAFE).encode(&data), "-_-__g==");
+        assert_eq!(Encoding::Base64(URL_SAFE_NO_PAD).encode(&data), "-_-__g");
+        assert_eq!(Encoding::Base64(URL_SAFE_NO_PAD).decode(b"-_-__g"),
+                   Ok(data.to_vec()));
+        assert_eq!(Encoding::Base64(URL_SAFE).decode(b"+/+//g=="),
+                   Err(DecodeError::InvalidByte(0, b'+')));
+
+        let mime = Encoding::Base64(MIME).encode(&[0u8; 60]);
+        let lines: Vec<&str> = mime.split("\r\n").collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].len(), 76);
+        assert_eq!(lines[1], "AAAA");
+    }
+
+    #[test]
+    fn test_padding_errors() {
+        let base64 = Encoding::Base64(STANDARD);
+        assert_eq!(base64.decode(b"Zg="),
+                   Err(DecodeError::InvalidPadding(2)));
+        assert_eq!(base64.decode(b"Z==="),
+                   Err(DecodeError::InvalidPadding(1)));
+        assert_eq!(base64.decode(b"Zg==Zg=="),
+                   Err(DecodeError::InvalidPadding(4)));
+        assert_eq!(base64.decode(b"Zm9vY"),
+                   Err(DecodeError::InvalidLength(4)));
+    }
+}
@@ -0,0 +1,85 @@
+/* Base85 encoding (Ascii85 without the <~ ~> delimiters)
+ *
+ * Every 4 bytes are encoded as 5 characters from '!' to 'u', the zero group
+ * is encoded as 'z'.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+static FIRST_CHAR: u8 = b'!';
+static MAX_VALUE: u8 = 84;
+
+/*
+ * Encode a group of up to 4 bytes, the partial group is encoded as if it was
+ * padded by zero bytes and then the extra characters are dropped
+ */
+pub fn encode_group(group: &[u8], out: &mut Vec<u8>) {
+    if group.len() == 4 && group.iter().all(|&c| c == 0) {
+        out.push(b'z');
+        return;
+    }
+    let mut n = group.iter().enumerate().fold(0u32, |n, (i, &c)| {
+        n | (c as u32) << (24 - i * 8)
+    });
+    let mut chars = [0u8; 5];
+    for i in (0..5).rev() {
+        chars[i] = (n % 85) as u8 + FIRST_CHAR;
+        n /= 85;
+    }
+    out.extend_from_slice(&chars[..group.len() + 1]);
+}
+
+/*
+ * Decode a group of 2 to 5 symbols, the partial group is padded by the
+ * maximum value
+ */
+pub fn decode_group(values: &[u8], out: &mut Vec<u8>) -> bool {
+    let n = (0..5).fold(0u64, |n, i| {
+        n * 85 + *values.get(i).unwrap_or(&MAX_VALUE) as u64
+    });
+    if n > 0xffffffff {
+        return false;
+    }
+    for i in 0..values.len() - 1 {
+        out.push((n >> (24 - i * 8)) as u8);
+    }
+    true
+}
+
+pub fn value(c: u8) -> Option<u8> {
+    match c {
+        b'!'..=b'u' => Some(c - FIRST_CHAR),
+        _ => None
+    }
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use super::super::{Encoding, DecodeError};
+
+    #[test]
+    fn test_base85() {
+        let text = b"Man is distinguished";
+        let encoded = "9jqo^BlbD-BleB1DJ+*+F(f,q";
+        assert_eq!(Encoding::Base85.encode(text), encoded);
+        assert_eq!(Encoding::Base85.decode(encoded.as_bytes()),
+                   Ok(text.to_vec()));
+        assert_eq!(Encoding::Base85.encode(b"\0\0\0\0\0"), "z!!");
+        assert_eq!(Encoding::Base85.decode(b"z!!"), Ok(vec![0u8; 5]));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(Encoding::Base85.decode(b"9jqo^v"),
+                   Err(DecodeError::InvalidByte(5, b'v')));
+        assert_eq!(Encoding::Base85.decode(b"9jqo^B"),
+                   Err(DecodeError::InvalidLength(5)));
+        assert_eq!(Encoding::Base85.decode(b"9jzo^"),
+                   Err(DecodeError::InvalidByte(2, b'z')));
+        assert_eq!(Encoding::Base85.decode(b"uuuuu"),
+                   Err(DecodeError::Overflow(0)));
+    }
+}
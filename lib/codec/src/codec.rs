@@ -0,0 +1,262 @@
+/* Binary-to-text encodings
+ *
+ * Hex, base64, base32, base85 (Ascii85) and percent-encoding with streaming
+ * encoders and decoders and detection of the encoding of challenge data
+ * files. The key-value cookies have their own codec.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+use std::fmt;
+
+pub use base64::{Config, CharacterSet, STANDARD, STANDARD_NO_PAD, URL_SAFE};
+pub use base64::{URL_SAFE_NO_PAD, MIME};
+pub use percent::COOKIE_RESERVED;
+pub use stream::{Encoder, Decoder};
+
+mod hex;
+mod base64;
+mod base32;
+mod base85;
+mod percent;
+mod stream;
+pub mod kv;
+
+// Error of decoding, every error has offset of the bad input
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DecodeError {
+    // Byte which doesn't belong to the encoding
+    InvalidByte(usize, u8),
+    // Incomplete group of symbols at the end of the input
+    InvalidLength(usize),
+    // Misplaced or wrong number of padding characters
+    InvalidPadding(usize),
+    // Group of symbols which doesn't fit into the bytes
+    Overflow(usize)
+}
+
+impl DecodeError {
+    pub fn offset(&self) -> usize {
+        match *self {
+            DecodeError::InvalidByte(offset, _) => offset,
+            DecodeError::InvalidLength(offset) => offset,
+            DecodeError::InvalidPadding(offset) => offset,
+            DecodeError::Overflow(offset) => offset
+        }
+    }
+
+    fn shift(self, delta: usize) -> DecodeError {
+        match self {
+            DecodeError::InvalidByte(offset, c) =>
+                DecodeError::InvalidByte(offset + delta, c),
+            DecodeError::InvalidLength(offset) =>
+                DecodeError::InvalidLength(offset + delta),
+            DecodeError::InvalidPadding(offset) =>
+                DecodeError::InvalidPadding(offset + delta),
+            DecodeError::Overflow(offset) =>
+                DecodeError::Overflow(offset + delta)
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::InvalidByte(offset, c) =>
+                write!(f, "Invalid byte 0x{:02x} at offset {}", c, offset),
+            DecodeError::InvalidLength(offset) =>
+                write!(f, "Incomplete input at offset {}", offset),
+            DecodeError::InvalidPadding(offset) =>
+                write!(f, "Invalid padding at offset {}", offset),
+            DecodeError::Overflow(offset) =>
+                write!(f, "Value overflow at offset {}", offset)
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Encoding {
+    Hex,
+    Base64(Config),
+    Base32,
+    Base85,
+    // Bytes to quote in addition to the percent sign and non-printable bytes
+    Percent(&'static [u8])
+}
+
+impl Encoding {
+    pub fn encode(&self, data: &[u8]) -> String {
+        let mut encoder = Encoder::new(*self);
+        let mut encoded = encoder.update(data);
+        encoded.push_str(&encoder.finish());
+        encoded
+    }
+
+    /*
+     * Decode the data, whitespace characters are ignored except for
+     * percent-encoding
+     */
+    pub fn decode(&self, data: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        let mut decoder = Decoder::new(*self);
+        let mut decoded = decoder.update(data)?;
+        decoded.extend_from_slice(decoder.finish()?.as_slice());
+        Ok(decoded)
+    }
+
+    /*
+     * Decode every non-empty line separately, the error offset is relative
+     * to the start of the data
+     */
+    pub fn decode_lines(&self, data: &[u8])
+            -> Result<Vec<Vec<u8>>, DecodeError> {
+        let mut lines = Vec::new();
+        let mut start = 0;
+        for line in data.split(|&c| c == b'\n') {
+            let line_start = start;
+            start += line.len() + 1;
+            if line.iter().all(|&c| is_whitespace(c)) {
+                continue;
+            }
+            match self.decode(line) {
+                Ok(decoded) => lines.push(decoded),
+                Err(err) => return Err(err.shift(line_start))
+            }
+        }
+        Ok(lines)
+    }
+}
+
+/*
+ * Detect the encoding of the data
+ *
+ * Every line is checked separately because challenge data files often have a
+ * separately encoded value on every line. Encodings are tried from the most
+ * restrictive alphabet to the least restrictive one: hex, base32, base64,
+ * percent-encoding and base85. Multi-line base64 is reported as the standard
+ * base64 which accepts line breaks as well.
+ */
+pub fn detect(data: &[u8]) -> Option<Encoding> {
+    let lines: Vec<Vec<u8>> = data.split(|&c| c == b'\n').map(|line| {
+        line.iter().filter(|&&c| !is_whitespace(c)).copied().collect()
+    }).filter(|line: &Vec<u8>| !line.is_empty()).collect();
+    if lines.is_empty() {
+        return None;
+    }
+    let lines = lines.as_slice();
+    if all_lines(lines, |line| line.len() % 2 == 0 && line.iter().all(|&c| {
+        hex::value(c).is_some()
+    })) {
+        return Some(Encoding::Hex);
+    }
+    if all_lines(lines, |line| line.len() % 8 == 0 && is_padded(line, 6, |c| {
+        base32::value(c).is_some()
+    })) {
+        return Some(Encoding::Base32);
+    }
+    for &(url_safe, config) in [(false, STANDARD), (true, URL_SAFE)].iter() {
+        let charset = config.char_set;
+        if all_lines(lines, |line| is_padded(line, 2, |c| {
+            base64::value(c, charset).is_some()
+        })) {
+            // Lines of the wrapped base64 can have any length
+            let len = lines.iter().fold(0, |len, line| len + line.len());
+            let padded = len % 4 == 0;
+            return Some(Encoding::Base64(match (url_safe, padded) {
+                (false, true) => STANDARD,
+                (false, false) => STANDARD_NO_PAD,
+                (true, true) => URL_SAFE,
+                (true, false) => URL_SAFE_NO_PAD
+            }));
+        }
+    }
+    if all_lines(lines, percent::has_escapes) {
+        return Some(Encoding::Percent(COOKIE_RESERVED));
+    }
+    if all_lines(lines, |line| line.iter().all(|&c| {
+        c == b'z' || base85::value(c).is_some()
+    })) {
+        return Some(Encoding::Base85);
+    }
+    None
+}
+
+fn all_lines<F: Fn(&[u8]) -> bool>(lines: &[Vec<u8>], check: F) -> bool {
+    lines.iter().all(|line| check(line.as_slice()))
+}
+
+/*
+ * Check that the line consists of the symbols followed by at most max_pad
+ * padding characters
+ */
+fn is_padded<F: Fn(u8) -> bool>(line: &[u8], max_pad: usize, is_symbol: F)
+        -> bool {
+    let len = line.iter().rev().take_while(|&&c| c == b'=').count();
+    let len = line.len() - len;
+    len > 0 && line.len() - len <= max_pad &&
+        line[..len].iter().all(|&c| is_symbol(c))
+}
+
+#[inline]
+fn is_whitespace(c: u8) -> bool {
+    c == b' ' || c == b'\t' || c == b'\r' || c == b'\n'
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use super::{Encoding, DecodeError, detect};
+    use super::{STANDARD, STANDARD_NO_PAD, URL_SAFE, MIME, COOKIE_RESERVED};
+
+    static ENCODINGS: [Encoding; 6] = [
+        Encoding::Hex, Encoding::Base64(STANDARD), Encoding::Base64(MIME),
+        Encoding::Base32, Encoding::Base85,
+        Encoding::Percent(COOKIE_RESERVED)];
+
+    #[test]
+    fn test_round_trip() {
+        let data: Vec<u8> = (0..300).map(|i| (i * 7) as u8).collect();
+        for encoding in ENCODINGS.iter() {
+            for len in 0..data.len() {
+                let encoded = encoding.encode(&data[..len]);
+                assert_eq!(encoding.decode(encoded.as_bytes()),
+                           Ok(data[..len].to_vec()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_lines() {
+        let data = b"49276d\n\n6b696c\r\n6c69x6e\n";
+        assert_eq!(Encoding::Hex.decode_lines(&data[..16]),
+                   Ok(vec![b"I'm".to_vec(), b"kil".to_vec()]));
+        assert_eq!(Encoding::Hex.decode_lines(data),
+                   Err(DecodeError::InvalidByte(20, b'x')));
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(b"49276d206b696c6c696e67\n0e3647e8592d35514a08\n"),
+                   Some(Encoding::Hex));
+        assert_eq!(detect(b"JBSWY3DP\nMZXW6===\n"), Some(Encoding::Base32));
+        assert_eq!(detect(b"SSdtIGJhY2sgYW5k\nSSdt\n"),
+                   Some(Encoding::Base64(STANDARD)));
+        assert_eq!(detect(b"SGk-P_8=\n"), Some(Encoding::Base64(URL_SAFE)));
+        assert_eq!(detect(b"SGkgdGhlcmU\n"),
+                   Some(Encoding::Base64(STANDARD_NO_PAD)));
+        assert_eq!(detect(b"userdata=a%3Badmin%3Dtrue\n"),
+                   Some(Encoding::Percent(COOKIE_RESERVED)));
+        assert_eq!(detect(b"9jqo^BlbD-BleB1DJ+*+F(f,q\n"),
+                   Some(Encoding::Base85));
+        assert_eq!(detect(b" \n\n"), None);
+        assert_eq!(detect(b"Hello, world!\n"), None);
+    }
+
+    #[test]
+    fn test_error_display() {
+        let err = DecodeError::InvalidByte(5, b'x');
+        assert_eq!(err.offset(), 5);
+        assert_eq!(format!("{}", err), "Invalid byte 0x78 at offset 5");
+    }
+}
@@ -0,0 +1,44 @@
+/* Hex encoding
+ *
+ * Lowercase digits are used for encoding, decoding is case-insensitive.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+static DIGITS: &[u8] = b"0123456789abcdef";
+
+pub fn encode_byte(c: u8, out: &mut Vec<u8>) {
+    out.push(DIGITS[(c >> 4) as usize]);
+    out.push(DIGITS[(c & 0xf) as usize]);
+}
+
+/*
+ * Value of the hex digit
+ */
+pub fn value(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None
+    }
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use super::super::{Encoding, DecodeError};
+
+    #[test]
+    fn test_hex() {
+        let hex = Encoding::Hex;
+        assert_eq!(hex.encode(b"I'm killing"), "49276d206b696c6c696e67");
+        assert_eq!(hex.decode(b"49276D206b69\n6c6c696e67"),
+                   Ok(b"I'm killing".to_vec()));
+        assert_eq!(hex.decode(b"4927g6"),
+                   Err(DecodeError::InvalidByte(4, b'g')));
+        assert_eq!(hex.decode(b"49 276"), Err(DecodeError::InvalidLength(5)));
+    }
+}
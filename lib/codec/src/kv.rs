@@ -0,0 +1,226 @@
+/* Key-value cookie encoding
+ *
+ * The pairs are encoded as key=value joined by '&', the percent sign and the
+ * separators inside the keys and values are quoted as '%' followed by two
+ * uppercase hex digits. The other bytes are kept as is.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::hex;
+
+static DIGITS: &[u8] = b"0123456789ABCDEF";
+
+// Bytes quoted inside the keys and values
+pub static KV_RESERVED: &[u8] = b"%&=";
+
+// Handling of the keys which occur more than once
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Duplicates {
+    Reject,
+    // Keep the first value
+    First,
+    // The later value replaces the earlier one
+    Last
+}
+
+// Error of parsing, every error has offset of the bad input
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ParseError {
+    // Pair without the '=' separator
+    MissingSeparator(usize),
+    // Separator inside the value
+    UnquotedSeparator(usize),
+    EmptyKey(usize),
+    // Percent sign which isn't followed by two hex digits
+    InvalidEscape(usize),
+    // Key or value which isn't valid UTF-8 after unquoting
+    InvalidUtf8(usize),
+    DuplicateKey(usize, String)
+}
+
+impl ParseError {
+    pub fn offset(&self) -> usize {
+        match *self {
+            ParseError::MissingSeparator(offset) => offset,
+            ParseError::UnquotedSeparator(offset) => offset,
+            ParseError::EmptyKey(offset) => offset,
+            ParseError::InvalidEscape(offset) => offset,
+            ParseError::InvalidUtf8(offset) => offset,
+            ParseError::DuplicateKey(offset, _) => offset
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::MissingSeparator(offset) =>
+                write!(f, "Missing '=' in pair at offset {}", offset),
+            ParseError::UnquotedSeparator(offset) =>
+                write!(f, "Unquoted '=' at offset {}", offset),
+            ParseError::EmptyKey(offset) =>
+                write!(f, "Empty key at offset {}", offset),
+            ParseError::InvalidEscape(offset) =>
+                write!(f, "Invalid escape sequence at offset {}", offset),
+            ParseError::InvalidUtf8(offset) =>
+                write!(f, "Invalid UTF-8 at offset {}", offset),
+            ParseError::DuplicateKey(offset, ref key) =>
+                write!(f, "Duplicate key {:?} at offset {}", key, offset)
+        }
+    }
+}
+
+/*
+ * Quote the reserved bytes of the key or value
+ */
+pub fn quote(data: &str) -> String {
+    let mut out = Vec::with_capacity(data.len());
+    for &c in data.as_bytes().iter() {
+        if KV_RESERVED.contains(&c) {
+            out.push(b'%');
+            out.push(DIGITS[(c >> 4) as usize]);
+            out.push(DIGITS[(c & 0xf) as usize]);
+        } else {
+            out.push(c);
+        }
+    }
+    // Only ASCII bytes are replaced
+    String::from_utf8(out).unwrap()
+}
+
+/*
+ * Unquote the key or value starting at the offset
+ */
+fn unquote(data: &[u8], offset: usize) -> Result<String, ParseError> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        if data[i] == b'%' {
+            let digits = data.get(i + 1..i + 3)
+                .and_then(|d| Some((hex::value(d[0])?, hex::value(d[1])?)));
+            match digits {
+                Some((high, low)) => out.push(high << 4 | low),
+                None => return Err(ParseError::InvalidEscape(offset + i))
+            }
+            i += 3;
+        } else {
+            out.push(data[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).map_err(|_| ParseError::InvalidUtf8(offset))
+}
+
+pub fn encode(pairs: &[(String, String)]) -> String {
+    let quoted: Vec<String> = pairs.iter().map(|(key, value)| {
+        format!("{}={}", quote(key), quote(value))
+    }).collect();
+    quoted.join("&")
+}
+
+pub fn parse(data: &[u8], duplicates: Duplicates)
+        -> Result<Vec<(String, String)>, ParseError> {
+    let mut pairs: Vec<(String, String)> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    if data.is_empty() {
+        return Ok(pairs);
+    }
+    let mut start = 0;
+    for pair in data.split(|&c| c == b'&') {
+        let offset = start;
+        start += pair.len() + 1;
+        let sep = match pair.iter().position(|&c| c == b'=') {
+            Some(sep) => sep,
+            None => return Err(ParseError::MissingSeparator(offset))
+        };
+        if sep == 0 {
+            return Err(ParseError::EmptyKey(offset));
+        }
+        let value = &pair[sep + 1..];
+        if let Some(pos) = value.iter().position(|&c| c == b'=') {
+            return Err(ParseError::UnquotedSeparator(offset + sep + 1 + pos));
+        }
+        let key = unquote(&pair[..sep], offset)?;
+        let value = unquote(value, offset + sep + 1)?;
+        match (index.get(&key), duplicates) {
+            (None, _) => {
+                index.insert(key.clone(), pairs.len());
+                pairs.push((key, value));
+            },
+            (Some(_), Duplicates::Reject) =>
+                return Err(ParseError::DuplicateKey(offset, key)),
+            (Some(_), Duplicates::First) => (),
+            (Some(&i), Duplicates::Last) => pairs[i].1 = value
+        }
+    }
+    Ok(pairs)
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use super::{encode, parse, quote, Duplicates, ParseError};
+
+    fn pairs(items: &[(&str, &str)]) -> Vec<(String, String)> {
+        items.iter().map(|&(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_encode_parse() {
+        let profile = pairs(&[("email", "foo@bar.com&role=admin"),
+                              ("uid", "10"), ("role", "user")]);
+        let encoded = "email=foo@bar.com%26role%3Dadmin&uid=10&role=user";
+        assert_eq!(encode(&profile), encoded);
+        assert_eq!(parse(encoded.as_bytes(), Duplicates::Reject),
+                   Ok(profile));
+        assert_eq!(quote("50% off"), "50%25 off");
+        assert_eq!(parse(b"", Duplicates::Reject), Ok(Vec::new()));
+        assert_eq!(parse(b"a=&b=%41", Duplicates::Reject),
+                   Ok(pairs(&[("a", ""), ("b", "A")])));
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let data = b"role=user&uid=10&role=admin";
+        assert_eq!(parse(data, Duplicates::Reject),
+                   Err(ParseError::DuplicateKey(17, "role".to_string())));
+        assert_eq!(parse(data, Duplicates::First),
+                   Ok(pairs(&[("role", "user"), ("uid", "10")])));
+        assert_eq!(parse(data, Duplicates::Last),
+                   Ok(pairs(&[("role", "admin"), ("uid", "10")])));
+    }
+
+    #[test]
+    fn test_errors() {
+        let errors: [(&[u8], ParseError); 6] = [
+            (b"foo=bar&baz", ParseError::MissingSeparator(8)),
+            (b"foo=bar&&a=b", ParseError::MissingSeparator(8)),
+            (b"foo=bar=baz", ParseError::UnquotedSeparator(7)),
+            (b"=bar", ParseError::EmptyKey(0)),
+            (b"foo=b%4", ParseError::InvalidEscape(5)),
+            (b"foo=bar&x=\xff", ParseError::InvalidUtf8(10))];
+        for (data, err) in errors.iter() {
+            assert_eq!(parse(data, Duplicates::Reject).as_ref(), Err(err));
+        }
+        assert_eq!(format!("{}", ParseError::EmptyKey(3)),
+                   "Empty key at offset 3");
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(map in prop::collection::btree_map(
+                ".+", ".*", 0..8)) {
+            let pairs: Vec<(String, String)> = map.into_iter().collect();
+            let encoded = encode(&pairs);
+            prop_assert_eq!(parse(encoded.as_bytes(), Duplicates::Reject),
+                            Ok(pairs));
+        }
+    }
+}
@@ -0,0 +1,76 @@
+/* Percent-encoding (RFC 3986)
+ *
+ * The reserved bytes, the percent sign itself and the bytes which aren't
+ * printable ASCII characters are quoted as '%' followed by two uppercase hex
+ * digits.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+use crate::hex;
+
+// Separators of the key-value pairs in the cookie strings
+pub static COOKIE_RESERVED: &[u8] = b";=";
+
+static DIGITS: &[u8] = b"0123456789ABCDEF";
+
+pub fn encode_byte(c: u8, reserved: &[u8], out: &mut Vec<u8>) {
+    if c == b'%' || c <= b' ' || c > b'~' || reserved.contains(&c) {
+        out.push(b'%');
+        out.push(DIGITS[(c >> 4) as usize]);
+        out.push(DIGITS[(c & 0xf) as usize]);
+    } else {
+        out.push(c);
+    }
+}
+
+/*
+ * Check that the line has at least one escape sequence and every percent
+ * sign starts a valid escape sequence
+ */
+pub fn has_escapes(line: &[u8]) -> bool {
+    let mut found = false;
+    for (i, &c) in line.iter().enumerate() {
+        if c == b'%' {
+            if i + 2 >= line.len() {
+                return false;
+            }
+            if hex::value(line[i + 1]).is_none() ||
+                    hex::value(line[i + 2]).is_none() {
+                return false;
+            }
+            found = true;
+        }
+    }
+    found
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use super::super::{Encoding, DecodeError};
+    use super::COOKIE_RESERVED;
+
+    #[test]
+    fn test_cookie_quoting() {
+        let percent = Encoding::Percent(COOKIE_RESERVED);
+        let data = "x;admin=true";
+        // The same quoting as in the CBC bitflipping challenge
+        assert_eq!(percent.encode(data.as_bytes()),
+                   data.replace(";", "%3B").replace("=", "%3D"));
+        assert_eq!(percent.encode(b"50% off\n"), "50%25%20off%0A");
+        assert_eq!(percent.decode(b"cooking%20MCs;x%3bz"),
+                   Ok(b"cooking MCs;x;z".to_vec()));
+    }
+
+    #[test]
+    fn test_errors() {
+        let percent = Encoding::Percent(COOKIE_RESERVED);
+        assert_eq!(percent.decode(b"a%3G"),
+                   Err(DecodeError::InvalidByte(3, b'G')));
+        assert_eq!(percent.decode(b"abc%3"),
+                   Err(DecodeError::InvalidLength(3)));
+    }
+}
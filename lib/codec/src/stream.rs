@@ -0,0 +1,308 @@
+/* Streaming encoder and decoder
+ *
+ * The input can be split at any position, every update returns the part of
+ * the output which is already known and the rest is returned on finish.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+use super::{Encoding, DecodeError, is_whitespace};
+use crate::hex;
+use crate::base64;
+use crate::base32;
+use crate::base85;
+use crate::percent;
+
+/*
+ * Number of bytes and symbols in a group
+ */
+fn group_size(encoding: Encoding) -> (usize, usize) {
+    match encoding {
+        Encoding::Hex => (1, 2),
+        Encoding::Base64(_) => (3, 4),
+        Encoding::Base32 => (5, 8),
+        Encoding::Base85 => (4, 5),
+        Encoding::Percent(_) => (1, 2)
+    }
+}
+
+fn encode_group(encoding: Encoding, group: &[u8], out: &mut Vec<u8>) {
+    match encoding {
+        Encoding::Hex => hex::encode_byte(group[0], out),
+        Encoding::Base64(config) => base64::encode_group(group, config, out),
+        Encoding::Base32 => base32::encode_group(group, out),
+        Encoding::Base85 => base85::encode_group(group, out),
+        Encoding::Percent(reserved) =>
+            percent::encode_byte(group[0], reserved, out)
+    }
+}
+
+pub struct Encoder {
+    encoding: Encoding,
+    // Bytes of the incomplete group
+    pending: Vec<u8>,
+    // Length of the current line
+    column: usize
+}
+
+impl Encoder {
+    pub fn new(encoding: Encoding) -> Encoder {
+        Encoder{encoding, pending: Vec::new(), column: 0}
+    }
+
+    pub fn update(&mut self, data: &[u8]) -> String {
+        let (size, _) = group_size(self.encoding);
+        self.pending.extend_from_slice(data);
+        let full = self.pending.len() / size * size;
+        let mut encoded = Vec::new();
+        for group in self.pending[..full].chunks(size) {
+            encode_group(self.encoding, group, &mut encoded);
+        }
+        self.pending = self.pending[full..].to_vec();
+        self.wrap(encoded)
+    }
+
+    /*
+     * Encode the incomplete group with padding if required
+     */
+    pub fn finish(mut self) -> String {
+        let mut encoded = Vec::new();
+        if !self.pending.is_empty() {
+            encode_group(self.encoding, self.pending.as_slice(),
+                         &mut encoded);
+        }
+        self.wrap(encoded)
+    }
+
+    fn wrap(&mut self, encoded: Vec<u8>) -> String {
+        let line_length = match self.encoding {
+            Encoding::Base64(config) => config.line_length,
+            _ => None
+        };
+        let out = match line_length {
+            Some(len) if len > 0 => {
+                let mut out = Vec::with_capacity(encoded.len() * 2);
+                for c in encoded.into_iter() {
+                    if self.column == len {
+                        out.extend_from_slice(b"\r\n");
+                        self.column = 0;
+                    }
+                    out.push(c);
+                    self.column += 1;
+                }
+                out
+            },
+            _ => encoded
+        };
+        String::from_utf8(out).unwrap()
+    }
+}
+
+/*
+ * Decoder of the encoded stream
+ *
+ * The error offsets are relative to the start of the stream, the decoder
+ * shouldn't be used after an error.
+ */
+pub struct Decoder {
+    encoding: Encoding,
+    // Offset of the next input byte
+    offset: usize,
+    // Symbol values of the current group
+    group: Vec<u8>,
+    // Offset of the first symbol of the current group
+    group_start: usize,
+    // Offset of the first padding character and number of padding characters
+    padding: Option<(usize, usize)>,
+    // Offset of the current percent escape sequence
+    escape: Option<usize>
+}
+
+impl Decoder {
+    pub fn new(encoding: Encoding) -> Decoder {
+        Decoder{encoding, offset: 0, group: Vec::new(),
+                group_start: 0, padding: None, escape: None}
+    }
+
+    pub fn update(&mut self, data: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        let mut decoded = Vec::new();
+        for &c in data.iter() {
+            let pos = self.offset;
+            self.offset += 1;
+            (match self.encoding {
+                Encoding::Percent(_) =>
+                    self.push_percent(c, pos, &mut decoded),
+                _ => self.push(c, pos, &mut decoded)
+            })?;
+        }
+        Ok(decoded)
+    }
+
+    /*
+     * Decode the incomplete group
+     */
+    pub fn finish(mut self) -> Result<Vec<u8>, DecodeError> {
+        let mut decoded = Vec::new();
+        if let Some(start) = self.escape {
+            return Err(DecodeError::InvalidLength(start));
+        }
+        if !self.group.is_empty() {
+            match self.padding {
+                Some((start, _)) =>
+                    return Err(DecodeError::InvalidPadding(start)),
+                None => self.decode_group(&mut decoded)?
+            }
+        }
+        Ok(decoded)
+    }
+
+    fn push(&mut self, c: u8, pos: usize, decoded: &mut Vec<u8>)
+            -> Result<(), DecodeError> {
+        let (_, symbols) = group_size(self.encoding);
+        if is_whitespace(c) {
+            return Ok(());
+        }
+        let padded = matches!(self.encoding,
+                              Encoding::Base64(_) | Encoding::Base32);
+        if c == b'=' && padded {
+            // At least one byte should be encoded before padding
+            if self.group.len() < 2 {
+                return Err(DecodeError::InvalidPadding(pos));
+            }
+            let padding = match self.padding {
+                Some((start, n)) => (start, n + 1),
+                None => (pos, 1)
+            };
+            self.padding = Some(padding);
+            if self.group.len() + padding.1 == symbols {
+                self.decode_group(decoded)?;
+            }
+            return Ok(());
+        }
+        if self.padding.is_some() {
+            return Err(DecodeError::InvalidPadding(pos));
+        }
+        if c == b'z' && self.encoding == Encoding::Base85 {
+            if !self.group.is_empty() {
+                return Err(DecodeError::InvalidByte(pos, c));
+            }
+            decoded.extend_from_slice(&[0u8; 4]);
+            return Ok(());
+        }
+        let value = match self.encoding {
+            Encoding::Hex => hex::value(c),
+            Encoding::Base64(config) => base64::value(c, config.char_set),
+            Encoding::Base32 => base32::value(c),
+            Encoding::Base85 => base85::value(c),
+            Encoding::Percent(_) => None
+        };
+        match value {
+            Some(value) => {
+                if self.group.is_empty() {
+                    self.group_start = pos;
+                }
+                self.group.push(value);
+            },
+            None => return Err(DecodeError::InvalidByte(pos, c))
+        }
+        if self.group.len() == symbols {
+            self.decode_group(decoded)?;
+        }
+        Ok(())
+    }
+
+    fn push_percent(&mut self, c: u8, pos: usize, decoded: &mut Vec<u8>)
+            -> Result<(), DecodeError> {
+        match self.escape {
+            None if c == b'%' => self.escape = Some(pos),
+            None => decoded.push(c),
+            Some(_) => match hex::value(c) {
+                Some(value) => {
+                    self.group.push(value);
+                    if self.group.len() == 2 {
+                        decoded.push(self.group[0] << 4 | self.group[1]);
+                        self.group.clear();
+                        self.escape = None;
+                    }
+                },
+                None => return Err(DecodeError::InvalidByte(pos, c))
+            }
+        }
+        Ok(())
+    }
+
+    fn decode_group(&mut self, decoded: &mut Vec<u8>)
+            -> Result<(), DecodeError> {
+        let is_valid = {
+            let values = self.group.as_slice();
+            match self.encoding {
+                Encoding::Hex if values.len() == 2 => {
+                    decoded.push(values[0] << 4 | values[1]);
+                    true
+                },
+                Encoding::Base64(_) if values.len() >= 2 => {
+                    base64::decode_group(values, decoded);
+                    true
+                },
+                Encoding::Base32 => base32::decode_group(values, decoded),
+                Encoding::Base85 if values.len() >= 2 => {
+                    if !base85::decode_group(values, decoded) {
+                        return Err(DecodeError::Overflow(self.group_start));
+                    }
+                    true
+                },
+                _ => false
+            }
+        };
+        if !is_valid {
+            return Err(DecodeError::InvalidLength(self.group_start));
+        }
+        self.group.clear();
+        Ok(())
+    }
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use super::super::{Encoding, DecodeError, STANDARD, MIME};
+    use super::{Encoder, Decoder};
+
+    #[test]
+    fn test_streaming() {
+        let data: Vec<u8> = (0..200).map(|i| (i * 13) as u8).collect();
+        let encodings = [Encoding::Hex, Encoding::Base64(MIME),
+                         Encoding::Base32, Encoding::Base85];
+        for &encoding in encodings.iter() {
+            let whole = encoding.encode(data.as_slice());
+            for &chunk in [1, 2, 3, 7, 64].iter() {
+                let mut encoder = Encoder::new(encoding);
+                let mut encoded = String::new();
+                for part in data.chunks(chunk) {
+                    encoded.push_str(&encoder.update(part));
+                }
+                encoded.push_str(&encoder.finish());
+                assert_eq!(encoded, whole);
+
+                let mut decoder = Decoder::new(encoding);
+                let mut decoded = Vec::new();
+                for part in whole.as_bytes().chunks(chunk) {
+                    decoded.extend(decoder.update(part).unwrap());
+                }
+                decoded.extend(decoder.finish().unwrap());
+                assert_eq!(decoded, data);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stream_error_offset() {
+        let mut decoder = Decoder::new(Encoding::Base64(STANDARD));
+        assert_eq!(decoder.update(b"SSdt"), Ok(b"I'm".to_vec()));
+        assert_eq!(decoder.update(b"IGJ"), Ok(Vec::new()));
+        assert_eq!(decoder.update(b"h*2sg"),
+                   Err(DecodeError::InvalidByte(8, b'*')));
+    }
+}
@@ -0,0 +1,41 @@
+/* Hash functions benchmarks
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+#[macro_use]
+extern crate criterion;
+extern crate hash_lib;
+
+use criterion::{Criterion, Throughput};
+
+use hash_lib::{sha1, md4, sha256};
+
+static SIZES: [usize; 2] = [64, 8192];
+
+fn bench_hash(c: &mut Criterion, name: &str, hash: fn(&[u8]) -> Vec<u8>) {
+    let mut group = c.benchmark_group(name);
+    for &size in SIZES.iter() {
+        let data = vec![0x61u8; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_function(size.to_string(), |b| {
+            b.iter(|| hash(data.as_slice()))
+        });
+    }
+    group.finish();
+}
+
+fn bench_sha1(c: &mut Criterion) {
+    bench_hash(c, "sha1", sha1);
+}
+
+fn bench_md4(c: &mut Criterion) {
+    bench_hash(c, "md4", md4);
+}
+
+fn bench_sha256(c: &mut Criterion) {
+    bench_hash(c, "sha256", sha256);
+}
+
+criterion_group!(benches, bench_sha1, bench_md4, bench_sha256);
+criterion_main!(benches);
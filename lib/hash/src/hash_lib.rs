@@ -0,0 +1,311 @@
+/* Hash functions and MACs library
+ *
+ * The hash functions are implemented in pure Rust and expose their internal
+ * state so the hashing can be resumed from a given state. Every hash function
+ * is the Merkle-Damgard core over its compression function.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+use std::fmt;
+use std::mem;
+
+pub use sha1::{Sha1, Sha1Compress, sha1, SHA1_BLOCK_SIZE, SHA1_DIGEST_SIZE};
+pub use md4::{Md4, Md4Compress, md4, MD4_BLOCK_SIZE, MD4_DIGEST_SIZE};
+pub use sha256::{Sha256, Sha256Compress, sha256, SHA256_BLOCK_SIZE,
+                 SHA256_DIGEST_SIZE};
+pub use mac::{prefix_mac, prefix_mac_verify, sha1_mac, sha1_mac_verify,
+              md4_mac, md4_mac_verify};
+pub use hmac::{hmac, hmac_verify, hmac_sha1, hmac_sha1_verify, hmac_sha256,
+               hmac_sha256_verify, constant_time_compare, insecure_compare};
+pub use length_extension::{Forgery, PrefixMacOracle, glue_padding, forge,
+                           forge_with_oracle};
+
+pub mod sha1;
+pub mod md4;
+pub mod sha256;
+pub mod mac;
+pub mod hmac;
+pub mod length_extension;
+
+// Block size of the hash functions with the Merkle-Damgard padding
+const MD_BLOCK_SIZE: usize = 64;
+
+/*
+ * Hash function of the Merkle-Damgard construction
+ *
+ * The digest is the state after the padded message, so the hashing can be
+ * resumed from the digest.
+ */
+pub trait MerkleDamgard: Sized {
+    const BLOCK_SIZE: usize;
+    const DIGEST_SIZE: usize;
+
+    fn new() -> Self;
+
+    /*
+     * Resume the hashing from the digest of the message of the length, the
+     * length includes the padding
+     */
+    fn from_digest(digest: &[u8], length: u64) -> Self;
+
+    fn update(&mut self, data: &[u8]);
+
+    fn finalize(self) -> Vec<u8>;
+
+    /*
+     * Padding of the message of the length
+     */
+    fn padding(length: u64) -> Vec<u8>;
+
+    fn digest(data: &[u8]) -> Vec<u8> {
+        let mut hash = Self::new();
+        hash.update(data);
+        hash.finalize()
+    }
+}
+
+/*
+ * Compression function of the hash function with 32-bit registers and
+ * 64-byte blocks
+ */
+pub trait Compress {
+    // Registers
+    type State: AsRef<[u32]> + AsMut<[u32]> + Copy + Default + Eq
+        + fmt::Debug;
+
+    const INIT_STATE: Self::State;
+    // Byte order of the digest and the message length
+    const BIG_ENDIAN: bool;
+
+    fn compress(state: &mut Self::State, block: &[u8]);
+}
+
+/*
+ * Merkle-Damgard hash function over the compression function
+ *
+ * The state is the registers and the length of the processed message, the
+ * hashing can be resumed from the state of any block boundary.
+ */
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MdHash<C: Compress> {
+    state: C::State,
+    // Length of the processed message in bytes including the buffer
+    length: u64,
+    // Incomplete block
+    buffer: Vec<u8>
+}
+
+impl<C: Compress> Default for MdHash<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Compress> MdHash<C> {
+    pub fn new() -> MdHash<C> {
+        MdHash::from_state(C::INIT_STATE, 0)
+    }
+
+    /*
+     * Resume the hashing from the registers after the message of the length
+     */
+    pub fn from_state(state: C::State, length: u64) -> MdHash<C> {
+        if !length.is_multiple_of(MD_BLOCK_SIZE as u64) {
+            panic!("Length isn't a multiple of the block size: {}", length);
+        }
+        MdHash{state, length, buffer: Vec::with_capacity(MD_BLOCK_SIZE)}
+    }
+
+    /*
+     * Resume the hashing from the digest of the message of the length, the
+     * length includes the padding
+     */
+    pub fn from_digest(digest: &[u8], length: u64) -> MdHash<C> {
+        if digest.len() != mem::size_of::<C::State>() {
+            panic!("Invalid digest size: {}", digest.len());
+        }
+        let mut state = C::State::default();
+        for (register, word) in state.as_mut().iter_mut()
+                .zip(digest.chunks(4)) {
+            let word = [word[0], word[1], word[2], word[3]];
+            *register = match C::BIG_ENDIAN {
+                true => u32::from_be_bytes(word),
+                false => u32::from_le_bytes(word)
+            };
+        }
+        MdHash::from_state(state, length)
+    }
+
+    /*
+     * Registers after the last complete block
+     */
+    pub fn state(&self) -> C::State {
+        self.state
+    }
+
+    /*
+     * Length of the processed message in bytes
+     */
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.length += data.len() as u64;
+        let state = &mut self.state;
+        update_blocks(&mut self.buffer, data, MD_BLOCK_SIZE,
+                      |block| C::compress(state, block));
+    }
+
+    pub fn finalize(mut self) -> Vec<u8> {
+        let padding = md_padding(self.length, C::BIG_ENDIAN);
+        self.update(padding.as_slice());
+        self.state.as_ref().iter().flat_map(|r| match C::BIG_ENDIAN {
+            true => r.to_be_bytes(),
+            false => r.to_le_bytes()
+        }).collect()
+    }
+}
+
+impl<C: Compress> MerkleDamgard for MdHash<C> {
+    const BLOCK_SIZE: usize = MD_BLOCK_SIZE;
+    const DIGEST_SIZE: usize = mem::size_of::<C::State>();
+
+    fn new() -> Self {
+        MdHash::new()
+    }
+
+    fn from_digest(digest: &[u8], length: u64) -> Self {
+        MdHash::from_digest(digest, length)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        MdHash::update(self, data)
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        MdHash::finalize(self)
+    }
+
+    /*
+     * 0x80, zero bytes and the length in bits as a 64-bit integer
+     */
+    fn padding(length: u64) -> Vec<u8> {
+        md_padding(length, C::BIG_ENDIAN)
+    }
+}
+
+/*
+ * Fill the buffer with the data and process all the complete blocks, the
+ * rest of the data is kept in the buffer
+ */
+fn update_blocks<F>(buffer: &mut Vec<u8>, data: &[u8], block_size: usize,
+                    mut process: F)
+        where F: FnMut(&[u8]) {
+    let mut data = data;
+    if !buffer.is_empty() {
+        let len = (block_size - buffer.len()).min(data.len());
+        buffer.extend_from_slice(&data[..len]);
+        data = &data[len..];
+        if buffer.len() < block_size {
+            return;
+        }
+        process(buffer.as_slice());
+        buffer.clear();
+    }
+    let mut blocks = data.chunks_exact(block_size);
+    for block in blocks.by_ref() {
+        process(block);
+    }
+    buffer.extend_from_slice(blocks.remainder());
+}
+
+/*
+ * Padding of the message of the length for 64-byte blocks: 0x80, zero bytes
+ * and the length in bits as a 64-bit integer
+ */
+fn md_padding(length: u64, big_endian: bool) -> Vec<u8> {
+    let zeros = (128 - 9 - length % 64) % 64;
+    let mut padding = Vec::with_capacity(zeros as usize + 9);
+    padding.push(0x80);
+    padding.resize(zeros as usize + 1, 0);
+    let bits = length.wrapping_mul(8);
+    padding.extend_from_slice(&match big_endian {
+        true => bits.to_be_bytes(),
+        false => bits.to_le_bytes()
+    });
+    padding
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use super::{Compress, MdHash, MerkleDamgard, Sha1Compress, Md4Compress,
+                Sha256Compress};
+
+    fn check_update<C: Compress>() {
+        let data: Vec<u8> = (0..300).map(|i| i as u8).collect();
+        for &step in [1, 7, 63, 64, 65, 200].iter() {
+            let mut hash = MdHash::<C>::new();
+            for chunk in data.chunks(step) {
+                hash.update(chunk);
+            }
+            assert_eq!(hash.length(), 300);
+            assert_eq!(hash.finalize(), MdHash::<C>::digest(data.as_slice()));
+        }
+    }
+
+    fn check_padding<C: Compress>() {
+        for length in 0..200u64 {
+            let padding = MdHash::<C>::padding(length);
+            assert_eq!((length as usize + padding.len()) % 64, 0);
+            assert_eq!(padding[0], 0x80);
+        }
+        assert_eq!(MdHash::<C>::padding(55).len(), 9);
+        assert_eq!(MdHash::<C>::padding(56).len(), 72);
+    }
+
+    fn check_resume<C: Compress>() {
+        let mut hash = MdHash::<C>::new();
+        hash.update(&[0x61; 64]);
+        let mut resumed = MdHash::<C>::from_state(hash.state(),
+                                                  hash.length());
+        resumed.update(b"tail");
+        hash.update(b"tail");
+        assert_eq!(resumed.finalize(), hash.finalize());
+
+        // The digest is the state after the padded message
+        let digest = MdHash::<C>::digest(b"abc");
+        assert_eq!(digest.len(), MdHash::<C>::DIGEST_SIZE);
+        let mut resumed = MdHash::<C>::from_digest(digest.as_slice(), 64);
+        resumed.update(b"def");
+        let padded = [b"abc".to_vec(), MdHash::<C>::padding(3),
+                      b"def".to_vec()].concat();
+        assert_eq!(resumed.finalize(),
+                   MdHash::<C>::digest(padded.as_slice()));
+    }
+
+    fn check_hash<C: Compress>() {
+        check_update::<C>();
+        check_padding::<C>();
+        check_resume::<C>();
+    }
+
+    #[test]
+    fn test_sha1() {
+        check_hash::<Sha1Compress>();
+    }
+
+    #[test]
+    fn test_md4() {
+        check_hash::<Md4Compress>();
+    }
+
+    #[test]
+    fn test_sha256() {
+        check_hash::<Sha256Compress>();
+    }
+}
@@ -0,0 +1,187 @@
+/* HMAC
+ *
+ * RFC 2104: https://tools.ietf.org/html/rfc2104
+ *
+ * HMAC(key, message) = H((key ^ opad) || H((key ^ ipad) || message))
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+use std::thread;
+use std::time::Duration;
+
+use crate::MerkleDamgard;
+use crate::sha1::Sha1;
+use crate::sha256::Sha256;
+
+const IPAD: u8 = 0x36;
+const OPAD: u8 = 0x5c;
+
+pub fn hmac<H: MerkleDamgard>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    // Keys longer than the block size are hashed first
+    let mut block_key = match key.len() > H::BLOCK_SIZE {
+        true => H::digest(key),
+        false => key.to_vec()
+    };
+    block_key.resize(H::BLOCK_SIZE, 0);
+
+    let mut inner = H::new();
+    inner.update(block_key.iter().map(|b| b ^ IPAD)
+                 .collect::<Vec<u8>>().as_slice());
+    inner.update(message);
+    let inner = inner.finalize();
+
+    let mut outer = H::new();
+    outer.update(block_key.iter().map(|b| b ^ OPAD)
+                 .collect::<Vec<u8>>().as_slice());
+    outer.update(inner.as_slice());
+    outer.finalize()
+}
+
+pub fn hmac_verify<H: MerkleDamgard>(key: &[u8], message: &[u8],
+                                     mac: &[u8]) -> bool {
+    constant_time_compare(hmac::<H>(key, message).as_slice(), mac)
+}
+
+pub fn hmac_sha1(key: &[u8], message: &[u8]) -> Vec<u8> {
+    hmac::<Sha1>(key, message)
+}
+
+pub fn hmac_sha1_verify(key: &[u8], message: &[u8], mac: &[u8]) -> bool {
+    hmac_verify::<Sha1>(key, message, mac)
+}
+
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    hmac::<Sha256>(key, message)
+}
+
+pub fn hmac_sha256_verify(key: &[u8], message: &[u8], mac: &[u8]) -> bool {
+    hmac_verify::<Sha256>(key, message, mac)
+}
+
+/*
+ * Compare the byte strings in the time depending only on their lengths
+ */
+pub fn constant_time_compare(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/*
+ * Compare the byte strings and return on the first mismatch sleeping for
+ * the delay after every matching byte. The comparison leaks the length of
+ * the matching prefix through the timing, use it only for the timing attack
+ * demos.
+ */
+pub fn insecure_compare(a: &[u8], b: &[u8], delay: Duration) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    for (x, y) in a.iter().zip(b.iter()) {
+        if x != y {
+            return false;
+        }
+        thread::sleep(delay);
+    }
+    true
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use codec::Encoding;
+    use super::{hmac_sha1, hmac_sha1_verify, hmac_sha256, hmac_sha256_verify,
+                constant_time_compare, insecure_compare};
+
+    fn hex(data: &[u8]) -> String {
+        Encoding::Hex.encode(data)
+    }
+
+    // Keys and messages of the common RFC 2202 and RFC 4231 test cases
+    fn test_cases() -> Vec<(Vec<u8>, Vec<u8>)> {
+        vec![
+            (vec![0x0b; 20], b"Hi There".to_vec()),
+            (b"Jefe".to_vec(), b"what do ya want for nothing?".to_vec()),
+            (vec![0xaa; 20], vec![0xdd; 50]),
+            ((1..=25).collect(), vec![0xcd; 50]),
+            (vec![0x0c; 20], b"Test With Truncation".to_vec())]
+    }
+
+    #[test]
+    fn test_hmac_sha1() {
+        let macs = [
+            "b617318655057264e28bc0b6fb378c8ef146be00",
+            "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79",
+            "125d7342b9ac11cd91a39af48aa17b4f63f175d3",
+            "4c9007f4026250c6bc8414f9bf50c86c2d7235da",
+            "4c1a03424b55e07fe7f27be1d58bb9324a9a5a04"];
+        for ((key, message), &mac) in test_cases().iter().zip(macs.iter()) {
+            assert_eq!(hex(hmac_sha1(key, message).as_slice()), mac);
+        }
+        // Keys longer than the block size
+        let key = [0xaa; 80];
+        assert_eq!(hex(hmac_sha1(&key, b"Test Using Larger Than Block-Size \
+                                         Key - Hash Key First").as_slice()),
+                   "aa4ae5e15272d00e95705637ce8a3b55ed402112");
+        assert_eq!(hex(hmac_sha1(&key, b"Test Using Larger Than Block-Size \
+                                         Key and Larger Than One Block-Size \
+                                         Data").as_slice()),
+                   "e8e99d0f45237d786d6bbaa7965c7808bbff1a91");
+    }
+
+    #[test]
+    fn test_hmac_sha256() {
+        let macs = [
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe",
+            "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b",
+            "a3b6167473100ee06e0c796c2955552b"];
+        for ((key, message), &mac) in test_cases().iter().zip(macs.iter()) {
+            let digest = hmac_sha256(key, message);
+            // The truncation test case checks only the first 128 bits
+            assert_eq!(hex(&digest[..mac.len() / 2]), mac);
+        }
+        let key = [0xaa; 131];
+        assert_eq!(hex(hmac_sha256(&key, b"Test Using Larger Than Block-Size \
+                                           Key - Hash Key First").as_slice()),
+                   "60e431591ee0b67f0d8a26aacbf5b77f\
+                    8e0bc6213728c5140546040f0ee37f54");
+        assert_eq!(hex(hmac_sha256(&key, b"This is a test using a larger \
+            than block-size key and a larger than block-size data. The key \
+            needs to be hashed before being used by the HMAC algorithm.")
+                       .as_slice()),
+                   "9b09ffa71b942fcb27635fbcd5b0e944\
+                    bfdc63644f0713938a7f51535c3a35e2");
+    }
+
+    #[test]
+    fn test_verify() {
+        let mac = hmac_sha1(b"key", b"message");
+        assert!(hmac_sha1_verify(b"key", b"message", mac.as_slice()));
+        assert!(!hmac_sha1_verify(b"key", b"messagf", mac.as_slice()));
+        assert!(!hmac_sha1_verify(b"key", b"message", &mac[..19]));
+        let mac = hmac_sha256(b"key", b"message");
+        assert!(hmac_sha256_verify(b"key", b"message", mac.as_slice()));
+        assert!(!hmac_sha256_verify(b"kez", b"message", mac.as_slice()));
+    }
+
+    fn check_compare<F: Fn(&[u8], &[u8]) -> bool>(compare: F) {
+        assert!(compare(b"", b""));
+        assert!(compare(b"abc", b"abc"));
+        assert!(!compare(b"abc", b"abd"));
+        assert!(!compare(b"abc", b"xbc"));
+        assert!(!compare(b"abc", b"ab"));
+    }
+
+    #[test]
+    fn test_compare() {
+        check_compare(constant_time_compare);
+        check_compare(|a, b| insecure_compare(a, b, Duration::ZERO));
+    }
+}
@@ -0,0 +1,165 @@
+/* Length extension of the secret-prefix MAC
+ *
+ * The MAC of key || message is the state of the hash function after the
+ * padded message, so the hashing can be resumed to get the MAC of
+ * key || message || glue padding || extension without the key. Only the
+ * length of the key is needed and it can be guessed with the verification
+ * oracle.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::MerkleDamgard;
+use crate::mac::{prefix_mac, prefix_mac_verify};
+
+// Forged message and its MAC for the guessed key length
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Forgery {
+    pub key_len: usize,
+    pub message: Vec<u8>,
+    pub mac: Vec<u8>
+}
+
+impl fmt::Display for Forgery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Key length: {}", self.key_len)?;
+        writeln!(f, "Message: {:?}",
+                 String::from_utf8_lossy(self.message.as_slice()))?;
+        write!(f, "MAC: ")?;
+        for c in self.mac.iter() {
+            write!(f, "{:02x}", c)?;
+        }
+        Ok(())
+    }
+}
+
+/*
+ * Secret-prefix MAC with the key unknown to the attacker, only the MACs of
+ * the messages and the verification are available
+ */
+pub struct PrefixMacOracle<H> {
+    key: Vec<u8>,
+    hash: PhantomData<H>
+}
+
+impl<H: MerkleDamgard> PrefixMacOracle<H> {
+    pub fn new(key: &[u8]) -> PrefixMacOracle<H> {
+        PrefixMacOracle{key: key.to_vec(), hash: PhantomData}
+    }
+
+    pub fn mac(&self, message: &[u8]) -> Vec<u8> {
+        prefix_mac::<H>(self.key.as_slice(), message)
+    }
+
+    pub fn verify(&self, message: &[u8], mac: &[u8]) -> bool {
+        prefix_mac_verify::<H>(self.key.as_slice(), message, mac)
+    }
+
+    /*
+     * Forge the MAC of the message with the extension from the MAC of the
+     * message, the key lengths up to the maximum are tried
+     */
+    pub fn extend(&self, message: &[u8], extension: &[u8],
+                  max_key_len: usize) -> Option<Forgery> {
+        let mac = self.mac(message);
+        forge_with_oracle::<H, _, _>(message, mac.as_slice(), extension,
+                                     0..=max_key_len,
+                                     |message, mac| self.verify(message, mac))
+    }
+}
+
+/*
+ * Padding which the hash function appends to key || message
+ */
+pub fn glue_padding<H: MerkleDamgard>(key_len: usize, message_len: usize)
+        -> Vec<u8> {
+    H::padding((key_len + message_len) as u64)
+}
+
+/*
+ * Forge the MAC of key || message || glue padding || extension from the MAC
+ * of key || message
+ */
+pub fn forge<H: MerkleDamgard>(message: &[u8], mac: &[u8], key_len: usize,
+                               extension: &[u8]) -> Forgery {
+    let glue = glue_padding::<H>(key_len, message.len());
+    let length = (key_len + message.len() + glue.len()) as u64;
+    let mut hash = H::from_digest(mac, length);
+    hash.update(extension);
+    let forged = [message, glue.as_slice(), extension].concat();
+    Forgery{key_len, message: forged, mac: hash.finalize()}
+}
+
+/*
+ * Try the key lengths until the verification oracle accepts the forgery
+ */
+pub fn forge_with_oracle<H, I, V>(message: &[u8], mac: &[u8],
+                                  extension: &[u8], key_lens: I,
+                                  mut verify: V) -> Option<Forgery>
+        where H: MerkleDamgard, I: IntoIterator<Item=usize>,
+              V: FnMut(&[u8], &[u8]) -> bool {
+    key_lens.into_iter()
+        .map(|key_len| forge::<H>(message, mac, key_len, extension))
+        .find(|forgery| verify(forgery.message.as_slice(),
+                               forgery.mac.as_slice()))
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use crate::{MerkleDamgard, Sha1, Md4, Sha256};
+    use crate::mac::{prefix_mac, prefix_mac_verify};
+    use super::{forge, forge_with_oracle, glue_padding, PrefixMacOracle};
+
+    static MESSAGE: &[u8] = b"comment1=cooking%20MCs;userdata=foo;\
+                              comment2=%20like%20a%20pound%20of%20bacon";
+
+    fn check_forge<H: MerkleDamgard>() {
+        let key = b"secret key";
+        let mac = prefix_mac::<H>(key, MESSAGE);
+        let forgery = forge::<H>(MESSAGE, mac.as_slice(), key.len(),
+                                 b";admin=true");
+        assert!(forgery.message.starts_with(MESSAGE));
+        assert!(forgery.message.ends_with(b";admin=true"));
+        let glue = glue_padding::<H>(key.len(), MESSAGE.len());
+        assert_eq!((key.len() + MESSAGE.len() + glue.len()) % H::BLOCK_SIZE,
+                   0);
+        assert!(prefix_mac_verify::<H>(key, forgery.message.as_slice(),
+                                       forgery.mac.as_slice()));
+
+        let verify = |message: &[u8], mac: &[u8]| {
+            prefix_mac_verify::<H>(key, message, mac)
+        };
+        let forgery = forge_with_oracle::<H, _, _>(
+            MESSAGE, mac.as_slice(), b";admin=true", 0..64, verify).unwrap();
+        assert_eq!(forgery.key_len, key.len());
+        assert!(forgery.to_string().starts_with("Key length: 10\n"));
+        assert!(forge_with_oracle::<H, _, _>(
+            MESSAGE, mac.as_slice(), b";admin=true", 0..8, verify).is_none());
+
+        let oracle = PrefixMacOracle::<H>::new(key);
+        assert_eq!(oracle.extend(MESSAGE, b";admin=true", 64),
+                   Some(forgery));
+        assert_eq!(oracle.extend(MESSAGE, b";admin=true", 8), None);
+    }
+
+    #[test]
+    fn test_forge_sha1() {
+        check_forge::<Sha1>();
+    }
+
+    #[test]
+    fn test_forge_md4() {
+        check_forge::<Md4>();
+    }
+
+    #[test]
+    fn test_forge_sha256() {
+        check_forge::<Sha256>();
+    }
+}
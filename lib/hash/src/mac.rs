@@ -0,0 +1,73 @@
+/* Secret-prefix MAC
+ *
+ * MAC(key, message) = H(key || message) is vulnerable to the length
+ * extension since the digest is the whole state of the hash function.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+use crate::MerkleDamgard;
+use crate::md4::Md4;
+use crate::sha1::Sha1;
+
+pub fn prefix_mac<H: MerkleDamgard>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut hash = H::new();
+    hash.update(key);
+    hash.update(message);
+    hash.finalize()
+}
+
+pub fn prefix_mac_verify<H: MerkleDamgard>(key: &[u8], message: &[u8],
+                                           mac: &[u8]) -> bool {
+    prefix_mac::<H>(key, message).as_slice() == mac
+}
+
+pub fn sha1_mac(key: &[u8], message: &[u8]) -> Vec<u8> {
+    prefix_mac::<Sha1>(key, message)
+}
+
+pub fn sha1_mac_verify(key: &[u8], message: &[u8], mac: &[u8]) -> bool {
+    prefix_mac_verify::<Sha1>(key, message, mac)
+}
+
+pub fn md4_mac(key: &[u8], message: &[u8]) -> Vec<u8> {
+    prefix_mac::<Md4>(key, message)
+}
+
+pub fn md4_mac_verify(key: &[u8], message: &[u8], mac: &[u8]) -> bool {
+    prefix_mac_verify::<Md4>(key, message, mac)
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use crate::md4::md4;
+    use crate::sha1::sha1;
+    use super::{sha1_mac, sha1_mac_verify, md4_mac, md4_mac_verify};
+
+    #[test]
+    fn test_sha1_mac() {
+        let key = b"YELLOW SUBMARINE";
+        let message = b"comment1=cooking%20MCs;userdata=foo";
+        let mac = sha1_mac(key, message);
+        assert_eq!(mac, sha1([&key[..], &message[..]].concat().as_slice()));
+        assert!(sha1_mac_verify(key, message, mac.as_slice()));
+        assert!(!sha1_mac_verify(b"YELLOW SUBMARINF", message,
+                                 mac.as_slice()));
+        assert!(!sha1_mac_verify(key, b"comment1=cooking%20MCs",
+                                 mac.as_slice()));
+        let mut tampered = mac.clone();
+        tampered[0] ^= 1;
+        assert!(!sha1_mac_verify(key, message, tampered.as_slice()));
+    }
+
+    #[test]
+    fn test_md4_mac() {
+        let mac = md4_mac(b"key", b"message");
+        assert_eq!(mac, md4(b"keymessage"));
+        assert!(md4_mac_verify(b"key", b"message", mac.as_slice()));
+        assert!(!md4_mac_verify(b"kez", b"message", mac.as_slice()));
+    }
+}
@@ -0,0 +1,101 @@
+/* MD4 hash function
+ *
+ * RFC 1320: https://tools.ietf.org/html/rfc1320
+ *
+ * The state is the four registers, the message words and the length are
+ * little-endian.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+use crate::{Compress, MdHash, MerkleDamgard};
+
+pub const MD4_BLOCK_SIZE: usize = 64;
+pub const MD4_DIGEST_SIZE: usize = 16;
+
+const INIT_STATE: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+// Order of the words and the shifts of the second and third rounds
+static ROUND2_ORDER: [usize; 16] = [0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14,
+                                    3, 7, 11, 15];
+static ROUND3_ORDER: [usize; 16] = [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13,
+                                    3, 11, 7, 15];
+static SHIFTS: [[u32; 4]; 3] = [[3, 7, 11, 19], [3, 5, 9, 13],
+                                [3, 9, 11, 15]];
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Md4Compress;
+
+impl Compress for Md4Compress {
+    type State = [u32; 4];
+
+    const INIT_STATE: [u32; 4] = INIT_STATE;
+    const BIG_ENDIAN: bool = false;
+
+    fn compress(state: &mut [u32; 4], block: &[u8]) {
+        compress(state, block)
+    }
+}
+
+pub type Md4 = MdHash<Md4Compress>;
+
+fn compress(state: &mut [u32; 4], block: &[u8]) {
+    let mut x = [0u32; 16];
+    for (i, word) in block.chunks(4).enumerate() {
+        x[i] = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    let mut r = *state;
+    for (round, shifts) in SHIFTS.iter().enumerate() {
+        for i in 0..16 {
+            // The updated register moves to the left on every step
+            let a = (4 - i % 4) % 4;
+            let (b, c, d) = (r[(a + 1) % 4], r[(a + 2) % 4], r[(a + 3) % 4]);
+            let (f, k, word) = match round {
+                0 => ((b & c) | (!b & d), 0, x[i]),
+                1 => ((b & c) | (b & d) | (c & d), 0x5a827999,
+                      x[ROUND2_ORDER[i]]),
+                _ => (b ^ c ^ d, 0x6ed9eba1, x[ROUND3_ORDER[i]])
+            };
+            r[a] = r[a].wrapping_add(f).wrapping_add(word).wrapping_add(k)
+                .rotate_left(shifts[i % 4]);
+        }
+    }
+    for (register, value) in state.iter_mut().zip(r.iter()) {
+        *register = register.wrapping_add(*value);
+    }
+}
+
+pub fn md4(data: &[u8]) -> Vec<u8> {
+    Md4::digest(data)
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use codec::Encoding;
+    use crate::MerkleDamgard;
+    use super::{Md4, md4};
+
+    #[test]
+    fn test_md4() {
+        let vectors: [(&[u8], &str); 7] = [
+            (b"", "31d6cfe0d16ae931b73c59d7e0c089c0"),
+            (b"a", "bde52cb31de33e46245e05fbdbd6fb24"),
+            (b"abc", "a448017aaf21d8525fc10ae87aa6729d"),
+            (b"message digest", "d9130a8164549fe818874806e1c7014b"),
+            (b"abcdefghijklmnopqrstuvwxyz",
+             "d79e1c308aa5bbcdeea8ed63df412da9"),
+            (b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+             "043f8582f241db351ce627e153e7f0e4"),
+            (b"1234567890123456789012345678901234567890\
+               1234567890123456789012345678901234567890",
+             "e33b4ddc9c38f2199c3e7b164fcc0536")];
+        for &(data, digest) in vectors.iter() {
+            assert_eq!(Encoding::Hex.encode(md4(data).as_slice()), digest);
+        }
+        // The length is little-endian
+        assert_eq!(&Md4::padding(3)[53..], &[24, 0, 0, 0, 0, 0, 0, 0]);
+    }
+}
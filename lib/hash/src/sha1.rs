@@ -0,0 +1,96 @@
+/* SHA-1 hash function
+ *
+ * FIPS 180-4: https://csrc.nist.gov/publications/detail/fips/180/4/final
+ *
+ * The state is the five registers, the message words and the length are
+ * big-endian.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+use crate::{Compress, MdHash, MerkleDamgard};
+
+pub const SHA1_BLOCK_SIZE: usize = 64;
+pub const SHA1_DIGEST_SIZE: usize = 20;
+
+const INIT_STATE: [u32; 5] = [
+    0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Sha1Compress;
+
+impl Compress for Sha1Compress {
+    type State = [u32; 5];
+
+    const INIT_STATE: [u32; 5] = INIT_STATE;
+    const BIG_ENDIAN: bool = true;
+
+    fn compress(state: &mut [u32; 5], block: &[u8]) {
+        compress(state, block)
+    }
+}
+
+pub type Sha1 = MdHash<Sha1Compress>;
+
+fn compress(state: &mut [u32; 5], block: &[u8]) {
+    let mut w = [0u32; 80];
+    for (i, word) in block.chunks(4).enumerate() {
+        w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    for i in 16..80 {
+        w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+    }
+    let [mut a, mut b, mut c, mut d, mut e] = *state;
+    for (i, &word) in w.iter().enumerate() {
+        let (f, k) = match i {
+            0..=19 => ((b & c) | (!b & d), 0x5a827999),
+            20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+            40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+            _ => (b ^ c ^ d, 0xca62c1d6)
+        };
+        let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e)
+            .wrapping_add(k).wrapping_add(word);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = temp;
+    }
+    for (register, value) in state.iter_mut().zip([a, b, c, d, e].iter()) {
+        *register = register.wrapping_add(*value);
+    }
+}
+
+pub fn sha1(data: &[u8]) -> Vec<u8> {
+    Sha1::digest(data)
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use codec::Encoding;
+    use super::sha1;
+
+    fn hex(data: &[u8]) -> String {
+        Encoding::Hex.encode(data)
+    }
+
+    #[test]
+    fn test_vectors() {
+        let vectors: [(&[u8], &str); 4] = [
+            (b"", "da39a3ee5e6b4b0d3255bfef95601890afd80709"),
+            (b"abc", "a9993e364706816aba3e25717850c26c9cd0d89d"),
+            (b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+             "84983e441c3bd26ebaae4aa1f95129e5e54670f1"),
+            (b"The quick brown fox jumps over the lazy dog",
+             "2fd4e1c67a2d28fced849ee1bb76e7391b93eb12")];
+        for &(data, digest) in vectors.iter() {
+            assert_eq!(hex(sha1(data).as_slice()), digest);
+        }
+        let million = vec![b'a'; 1000000];
+        assert_eq!(hex(sha1(million.as_slice()).as_slice()),
+                   "34aa973cd4c4daa4f61eeb2bdbad27316534016f");
+    }
+}
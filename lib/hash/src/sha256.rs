@@ -0,0 +1,127 @@
+/* SHA-256 hash function
+ *
+ * FIPS 180-4: https://csrc.nist.gov/publications/detail/fips/180/4/final
+ *
+ * The state is the eight registers, the message words and the length are
+ * big-endian.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+use crate::{Compress, MdHash, MerkleDamgard};
+
+pub const SHA256_BLOCK_SIZE: usize = 64;
+pub const SHA256_DIGEST_SIZE: usize = 32;
+
+const INIT_STATE: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c,
+    0x1f83d9ab, 0x5be0cd19];
+
+// First 32 bits of the fractional parts of the cube roots of the first 64
+// primes
+static K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1,
+    0x923f82a4, 0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3,
+    0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786,
+    0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147,
+    0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
+    0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+    0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a,
+    0x5b9cca4f, 0x682e6ff3, 0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208,
+    0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2];
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Sha256Compress;
+
+impl Compress for Sha256Compress {
+    type State = [u32; 8];
+
+    const INIT_STATE: [u32; 8] = INIT_STATE;
+    const BIG_ENDIAN: bool = true;
+
+    fn compress(state: &mut [u32; 8], block: &[u8]) {
+        compress(state, block)
+    }
+}
+
+pub type Sha256 = MdHash<Sha256Compress>;
+
+fn compress(state: &mut [u32; 8], block: &[u8]) {
+    let mut w = [0u32; 64];
+    for (i, word) in block.chunks(4).enumerate() {
+        w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    for i in 16..64 {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18)
+            ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19)
+            ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7])
+            .wrapping_add(s1);
+    }
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for (&word, &k) in w.iter().zip(K.iter()) {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+        let temp1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(k)
+            .wrapping_add(word);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let temp2 = s0.wrapping_add(maj);
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(temp1);
+        d = c;
+        c = b;
+        b = a;
+        a = temp1.wrapping_add(temp2);
+    }
+    for (register, value) in state.iter_mut()
+            .zip([a, b, c, d, e, f, g, h].iter()) {
+        *register = register.wrapping_add(*value);
+    }
+}
+
+pub fn sha256(data: &[u8]) -> Vec<u8> {
+    Sha256::digest(data)
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use codec::Encoding;
+    use super::sha256;
+
+    fn hex(data: &[u8]) -> String {
+        Encoding::Hex.encode(data)
+    }
+
+    #[test]
+    fn test_vectors() {
+        let vectors: [(&[u8], &str); 4] = [
+            (b"", "e3b0c44298fc1c149afbf4c8996fb924\
+                   27ae41e4649b934ca495991b7852b855"),
+            (b"abc", "ba7816bf8f01cfea414140de5dae2223\
+                      b00361a396177a9cb410ff61f20015ad"),
+            (b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+             "248d6a61d20638b8e5c026930c3e6039\
+              a33ce45964ff2167f6ecedd419db06c1"),
+            (b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmn\
+               hijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu",
+             "cf5b16a778af8380036ce59e7b049237\
+              0b249b11e8f07a51afac45037afee9d1")
+        ];
+        for &(data, digest) in vectors.iter() {
+            assert_eq!(hex(sha256(data).as_slice()), digest);
+        }
+        let million = vec![b'a'; 1000000];
+        assert_eq!(hex(sha256(million.as_slice()).as_slice()),
+                   "cdc76e5c9914fb9281a1c7e284d73e67\
+                    f1809a48a497200e046d39ccc7112cd0");
+    }
+}
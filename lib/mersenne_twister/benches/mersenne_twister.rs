@@ -0,0 +1,28 @@
+/* Mersenne Twister benchmarks
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+#[macro_use]
+extern crate criterion;
+extern crate mersenne_twister;
+
+use criterion::Criterion;
+
+use mersenne_twister::MersenneTwister;
+
+fn bench_rand_u32(c: &mut Criterion) {
+    let init_key = [0x123, 0x234, 0x345, 0x456];
+    let mut rng = MersenneTwister::new(&init_key[..]);
+    c.bench_function("rand_u32", |b| b.iter(|| rng.rand_u32()));
+}
+
+fn bench_new(c: &mut Criterion) {
+    let init_key = [0x123, 0x234, 0x345, 0x456];
+    c.bench_function("new", |b| {
+        b.iter(|| MersenneTwister::new(&init_key[..]))
+    });
+}
+
+criterion_group!(benches, bench_rand_u32, bench_new);
+criterion_main!(benches);
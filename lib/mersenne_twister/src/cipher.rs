@@ -0,0 +1,213 @@
+/* MT19937 stream cipher
+ *
+ * The key stream is the output of the MT19937 RNG seeded with the key, every
+ * 32-bit output gives four bytes of the key stream.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+use super::{MersenneTwister, MersenneTwisterSeed};
+
+// Order of the bytes taken from a 32-bit output of the RNG
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ByteOrder {
+    BigEndian,
+    LittleEndian
+}
+
+pub struct KeyStream {
+    prng: MersenneTwister,
+    order: ByteOrder,
+    buffer: Vec<u8>
+}
+
+impl KeyStream {
+    pub fn new<S: MersenneTwisterSeed>(seed: S, order: ByteOrder) -> Self {
+        let prng = MersenneTwister::new(seed);
+        let buffer = Vec::with_capacity(4);
+        KeyStream{prng, order, buffer}
+    }
+}
+
+impl Iterator for KeyStream {
+    type Item = u8;
+    fn next(&mut self) -> Option<u8> {
+        if self.buffer.is_empty() {
+            let rnd = self.prng.rand_u32();
+            // The bytes are taken from the end of the buffer
+            let shifts = match self.order {
+                ByteOrder::BigEndian => [0, 8, 16, 24],
+                ByteOrder::LittleEndian => [24, 16, 8, 0]
+            };
+            let buf = shifts.iter().map(|&shift| (rnd >> shift) as u8);
+            self.buffer.extend(buf);
+        }
+        self.buffer.pop()
+    }
+}
+
+pub fn encrypt<S: MersenneTwisterSeed>(data: &[u8], key: S, order: ByteOrder)
+        -> Vec<u8> {
+    let ks = KeyStream::new(key, order);
+    data.iter().zip(ks).map(|(&d, k)| d ^ k).collect()
+}
+
+pub fn decrypt<S: MersenneTwisterSeed>(data: &[u8], key: S, order: ByteOrder)
+        -> Vec<u8> {
+    encrypt(data, key, order)
+}
+
+/*
+ * Known plaintext key search
+ *
+ * Return every candidate key which decrypts the data at the given position
+ * to the known plaintext.
+ */
+pub fn find_keys<S, I>(encrypted: &[u8], known: &[u8], pos: usize,
+                       candidates: I, order: ByteOrder) -> Vec<S>
+        where S: MersenneTwisterSeed + Clone, I: Iterator<Item=S> {
+    if pos + known.len() > encrypted.len() {
+        return Vec::new();
+    }
+    let enc = &encrypted[pos..pos + known.len()];
+    candidates.filter(|key| {
+        let ks = KeyStream::new(key.clone(), order).skip(pos);
+        enc.iter().zip(ks).map(|(&c, k)| c ^ k).eq(known.iter().cloned())
+    }).collect()
+}
+
+/*
+ * Known plaintext key search which stops at the first matching key
+ */
+pub fn find_key<S, I>(encrypted: &[u8], known: &[u8], pos: usize,
+                      mut candidates: I, order: ByteOrder) -> Option<S>
+        where S: MersenneTwisterSeed + Clone, I: Iterator<Item=S> {
+    if pos + known.len() > encrypted.len() {
+        return None;
+    }
+    let enc = &encrypted[pos..pos + known.len()];
+    candidates.find(|key| {
+        let ks = KeyStream::new(key.clone(), order).skip(pos);
+        enc.iter().zip(ks).map(|(&c, k)| c ^ k).eq(known.iter().cloned())
+    })
+}
+
+/*
+ * Create a token from the first bytes of the key stream
+ */
+pub fn create_token<S: MersenneTwisterSeed>(seed: S, len: usize,
+                                            order: ByteOrder) -> Vec<u8> {
+    KeyStream::new(seed, order).take(len).collect()
+}
+
+/*
+ * Return every candidate seed which produces the token
+ */
+pub fn find_token_seeds<S, I>(token: &[u8], candidates: I, order: ByteOrder)
+        -> Vec<S>
+        where S: MersenneTwisterSeed + Clone, I: Iterator<Item=S> {
+    candidates.filter(|seed| {
+        let ks = KeyStream::new(seed.clone(), order);
+        ks.take(token.len()).eq(token.iter().cloned())
+    }).collect()
+}
+
+/*
+ * Return the first candidate seed which produces the token
+ */
+pub fn find_token_seed<S, I>(token: &[u8], mut candidates: I,
+                             order: ByteOrder)
+        -> Option<S>
+        where S: MersenneTwisterSeed + Clone, I: Iterator<Item=S> {
+    candidates.find(|seed| {
+        let ks = KeyStream::new(seed.clone(), order);
+        ks.take(token.len()).eq(token.iter().cloned())
+    })
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use super::super::MersenneTwister;
+    use super::{KeyStream, ByteOrder, encrypt, decrypt, find_keys, find_key};
+    use super::{create_token, find_token_seeds, find_token_seed};
+
+    #[test]
+    fn test_key_stream() {
+        let ks: Vec<u8> = KeyStream::new(0u32, ByteOrder::BigEndian)
+            .take(100).collect();
+        let ks2: Vec<u8> = KeyStream::new(0u32, ByteOrder::BigEndian)
+            .take(100).collect();
+        let ks3: Vec<u8> = KeyStream::new(100u32, ByteOrder::BigEndian)
+            .take(100).collect();
+        assert_eq!(ks, ks2);
+        assert!(ks != ks3);
+    }
+
+    #[test]
+    fn test_key_stream_byte_order() {
+        let be: Vec<u8> = KeyStream::new(5489u32, ByteOrder::BigEndian)
+            .take(8).collect();
+        let le: Vec<u8> = KeyStream::new(5489u32, ByteOrder::LittleEndian)
+            .take(8).collect();
+        let rnd = MersenneTwister::new(5489u32).rand_u32();
+        assert_eq!(&be[..4], &[(rnd >> 24) as u8, (rnd >> 16) as u8,
+                               (rnd >> 8) as u8, rnd as u8]);
+        assert_eq!(&le[..4], &[be[3], be[2], be[1], be[0]]);
+        assert_eq!(&be[4..], &[le[7], le[6], le[5], le[4]]);
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let data = b"Hello, new cipher!";
+        for &order in [ByteOrder::BigEndian, ByteOrder::LittleEndian].iter() {
+            let enc = encrypt(data, 12345u16, order);
+            assert!(enc[..] != data[..]);
+            assert_eq!(decrypt(&enc, 12345u16, order), data.to_vec());
+        }
+    }
+
+    #[test]
+    fn test_find_keys() {
+        let data = b"random prefix AAAAAAAAAAAAAA";
+        let enc = encrypt(data, 4321u16, ByteOrder::LittleEndian);
+        let candidates = (0u32..0x10000).map(|k| k as u16);
+        assert_eq!(find_keys(&enc, b"AAAAAAAAAAAAAA", 14, candidates,
+                             ByteOrder::LittleEndian), vec![4321u16]);
+        // Short known plaintext matches more than one key
+        let candidates = (0u32..0x10000).map(|k| k as u16);
+        let keys = find_keys(&enc, b"A", 14, candidates,
+                             ByteOrder::LittleEndian);
+        assert!(keys.len() > 1);
+        assert!(keys.contains(&4321u16));
+    }
+
+    #[test]
+    fn test_find_key() {
+        let data = b"random prefix AAAAAAAAAAAAAA";
+        let enc = encrypt(data, 4321u16, ByteOrder::LittleEndian);
+        let candidates = (0u32..0x10000).map(|k| k as u16);
+        assert_eq!(find_key(&enc, b"AAAAAAAAAAAAAA", 14, candidates,
+                            ByteOrder::LittleEndian), Some(4321u16));
+        let candidates = (0u32..0x10000).map(|k| k as u16);
+        assert_eq!(find_key(&enc, b"AAAAAAAAAAAAAA", 20, candidates,
+                            ByteOrder::LittleEndian), None::<u16>);
+    }
+
+    #[test]
+    fn test_find_token_seeds() {
+        let token = create_token(1420000123i64, 8, ByteOrder::BigEndian);
+        let candidates = (0i64..1000).map(|i| 1420000500 - i);
+        assert_eq!(find_token_seeds(&token, candidates, ByteOrder::BigEndian),
+                   vec![1420000123i64]);
+        // The search stops at the first seed
+        let mut tried = 0;
+        let candidates = (0i64..1000).map(|i| 1420000500 - i)
+            .inspect(|_| tried += 1);
+        assert_eq!(find_token_seed(&token, candidates, ByteOrder::BigEndian),
+                   Some(1420000123i64));
+        assert_eq!(tried, 378);
+    }
+}
@@ -6,25 +6,37 @@
  * Dmitry Vasiliev <dima@hlabs.org>
  */
 
-#![crate_name="mersenne_twister"]
-#![crate_type="lib"]
-
-extern crate test;
-
 use std::cmp::max;
-use std::iter::range_step;
 
+pub mod cipher;
 
 static N: usize = 624;
 static M: usize = 397;
 
+/*
+ * Common interface of the pseudo-random number generators
+ */
+pub trait Prng {
+    /*
+     * Return the next raw output of the generator
+     */
+    fn rand_u32(&mut self) -> u32;
+
+    /*
+     * Return the next value in [0, 1) interval
+     */
+    fn rand_f64(&mut self) -> f64 {
+        self.rand_u32() as f64 / 4294967296.0
+    }
+}
+
 pub trait MersenneTwisterSeed {
     fn get_state(&self) -> Vec<u32>;
 }
 
-impl<'a> MersenneTwisterSeed for &'a[u32] {
+impl MersenneTwisterSeed for &[u32] {
     fn get_state(&self) -> Vec<u32> {
-        init_by_vec(*self)
+        init_by_vec(self)
     }
 }
 
@@ -76,7 +88,7 @@ impl MersenneTwister {
 
     #[inline]
     fn init(&mut self) {
-        for i in range(0, N) {
+        for i in 0..N {
             let y = (self.state[i] & 0x80000000)
                     | (self.state[(i + 1) % N] & 0x7fffffff);
             self.state[i] = match y % 2 {
@@ -91,27 +103,62 @@ impl MersenneTwister {
      * Split the RNG by guessing the internal state.
      */
     pub fn split(&mut self) -> Self {
-        let state: Vec<u32>  = range(0, N).map(|_| {
-            let mut v = self.rand_u32();
-            v ^= v >> 18;
-            v ^= (v << 15) & 0xefc60000;
-            // Recover correct bits step by step
-            v = range_step(0, 32, 7).fold(v, |v, shift| {
-                v ^ (((v << 7) & 0x9d2c5680) & (0x3f80 << shift))
-            });
-            range_step(0, 32, 11).fold(v, |v, shift| {
-                v ^ ((v >> 11) & (0xffe00000 >> shift))
-            })
-        }).collect();
-        MersenneTwister{state: state, index: N}
+        let outputs: Vec<u32> = (0..N).map(|_| self.rand_u32()).collect();
+        MersenneTwister::from_outputs(outputs.as_slice()).unwrap()
+    }
+
+    /*
+     * Clone the RNG from at least 624 consecutive outputs
+     *
+     * The internal state is recovered from the first 624 outputs and the
+     * rest of the outputs are checked against the recovered RNG. The
+     * returned RNG continues after the last output.
+     */
+    pub fn from_outputs(outputs: &[u32]) -> Option<Self> {
+        if outputs.len() < N {
+            return None;
+        }
+        let state = outputs[..N].iter().map(|&v| untemper(v)).collect();
+        let mut rng = MersenneTwister{state, index: N};
+        if outputs[N..].iter().all(|&v| rng.rand_u32() == v) {
+            Some(rng)
+        } else {
+            None
+        }
+    }
+}
+
+impl Prng for MersenneTwister {
+    fn rand_u32(&mut self) -> u32 {
+        MersenneTwister::rand_u32(self)
     }
+
+    fn rand_f64(&mut self) -> f64 {
+        MersenneTwister::rand_f64(self)
+    }
+}
+
+/*
+ * Invert the tempering of the output to get the state value
+ */
+fn untemper(mut v: u32) -> u32 {
+    v ^= v >> 18;
+    v ^= (v << 15) & 0xefc60000;
+    // Recover correct bits step by step
+    v = (0..32).step_by(7).fold(v, |v, shift| {
+        v ^ (((v << 7) & 0x9d2c5680) & (0x3f80 << shift))
+    });
+    (0..32).step_by(11).fold(v, |v, shift| {
+        v ^ ((v >> 11) & (0xffe00000 >> shift))
+    })
 }
 
 #[inline]
 fn init_state(seed: u32) -> Vec<u32> {
-    range(0, N as u32).scan(seed, |state, i| {
+    (0..N as u32).scan(seed, |state, i| {
         let prev = *state;
-        *state = 1812433253 * (*state ^ (*state >> 30)) + i + 1;
+        *state = 1812433253u32.wrapping_mul(*state ^ (*state >> 30))
+            .wrapping_add(i + 1);
         Some(prev)
     }).collect()
 }
@@ -121,22 +168,24 @@ fn init_by_vec(init_key: &[u32]) -> Vec<u32> {
     let mut state = init_state(19650218);
     let len = init_key.len();
     let limit = N - 1;
-    for i in range(0, max(N, len)) {
+    for i in 0..max(N, len) {
         let idx = i % limit;
         let k_idx = i % len;
         state[idx + 1] = (state[idx + 1]
-            ^ ((state[idx] ^ (state[idx] >> 30)) * 1664525))
-            + init_key[k_idx] + k_idx as u32;
+            ^ (state[idx] ^ (state[idx] >> 30)).wrapping_mul(1664525))
+            .wrapping_add(init_key[k_idx]).wrapping_add(k_idx as u32);
         if (i + 1) % limit == 0 {
             state[0] = state[limit];
         }
     }
-    for i in range(2, N) {
+    for i in 2..N {
         state[i] = (state[i]
-            ^ ((state[i - 1] ^ (state[i - 1] >> 30)) * 1566083941)) - i as u32;
+            ^ (state[i - 1] ^ (state[i - 1] >> 30)).wrapping_mul(1566083941))
+            .wrapping_sub(i as u32);
     }
     state[1] = (state[1]
-        ^ ((state[N - 1] ^ (state[N - 1] >> 30)) * 1566083941)) - 1;
+        ^ (state[N - 1] ^ (state[N - 1] >> 30)).wrapping_mul(1566083941))
+        .wrapping_sub(1);
     state[0] = 0x80000000;
     state
 }
@@ -149,7 +198,6 @@ fn init_by_vec(init_key: &[u32]) -> Vec<u32> {
  */
 #[cfg(test)]
 mod tests {
-    use test::Bencher;
 
     use super::MersenneTwister;
 
@@ -191,7 +239,7 @@ mod tests {
         let mut rng = MersenneTwister::new(
             [0x123, 0x234, 0x345, 0x456].as_slice());
         // For the test data we use 1000 values should be skipped
-        for _ in range(0u, 1000) {
+        for _ in 0usize..1000 {
             rng.rand_f64();
         }
         for (i, &exp) in expected.iter().enumerate() {
@@ -199,17 +247,19 @@ mod tests {
         }
     }
 
-    #[bench]
-    fn bench_rand_u32(b: &mut Bencher) {
-        let mut rng = MersenneTwister::new(
-            [0x123, 0x234, 0x345, 0x456].as_slice());
-        b.iter(|| rng.rand_u32());
-    }
-
-    #[bench]
-    fn bench_new(b: &mut Bencher) {
-        let init_key = [0x123, 0x234, 0x345, 0x456];
-        let init_key_slice = init_key.as_slice();
-        b.iter(|| MersenneTwister::new(init_key_slice));
+    #[test]
+    fn test_from_outputs() {
+        let mut rng = MersenneTwister::new(5489u32);
+        let mut outputs: Vec<u32> = (0..700).map(|_| {
+            rng.rand_u32()
+        }).collect();
+        let mut clone = MersenneTwister::from_outputs(outputs.as_slice())
+            .unwrap();
+        for _ in 0usize..1000 {
+            assert_eq!(clone.rand_u32(), rng.rand_u32());
+        }
+        assert!(MersenneTwister::from_outputs(&outputs[..623]).is_none());
+        outputs[650] ^= 1;
+        assert!(MersenneTwister::from_outputs(outputs.as_slice()).is_none());
     }
 }
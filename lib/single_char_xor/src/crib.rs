@@ -0,0 +1,376 @@
+/* Crib dragging for the keystream reuse
+ *
+ * XOR of two ciphertexts encrypted with the same keystream is XOR of their
+ * plaintexts, so a guessed word in one plaintext reveals the fragment of the
+ * other one at the same position. The guesses are kept as the keystream
+ * bytes, so the engine doesn't depend on the source of the keystream and the
+ * recovered keystream decrypts all the ciphertexts.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use crate::fixed_nonce::GuessError;
+use crate::model::PlaintextModel;
+
+// Number of the preceding known bytes used as the context of the fragments
+static CONTEXT: usize = 4;
+
+// The most common English words for the plaintext extension
+pub static COMMON_WORDS: [&str; 100] = [
+    "the", "be", "to", "of", "and", "a", "in", "that", "have", "I", "it",
+    "for", "not", "on", "with", "he", "as", "you", "do", "at", "this", "but",
+    "his", "by", "from", "they", "we", "say", "her", "she", "or", "an",
+    "will", "my", "one", "all", "would", "there", "their", "what", "so",
+    "up", "out", "if", "about", "who", "get", "which", "go", "me", "when",
+    "make", "can", "like", "time", "no", "just", "him", "know", "take",
+    "people", "into", "year", "your", "good", "some", "could", "them", "see",
+    "other", "than", "then", "now", "look", "only", "come", "its", "over",
+    "think", "also", "back", "after", "use", "two", "how", "our", "work",
+    "first", "well", "way", "even", "new", "want", "because", "any", "these",
+    "give", "day", "most", "us"];
+
+/*
+ * Position of the crib which decrypts the other ciphertexts to plaintext
+ */
+#[derive(Clone, PartialEq, Debug)]
+pub struct CribMatch {
+    pub line: usize,
+    pub offset: usize,
+    pub crib: Vec<u8>,
+    // Average score of the fragments
+    pub score: f64,
+    // Fragments of the other ciphertexts decrypted by the crib
+    pub fragments: Vec<(usize, Vec<u8>)>
+}
+
+/*
+ * Place the crib into the ciphertext at the offset, return None if the crib
+ * contradicts the known keystream or no other ciphertext covers the crib
+ */
+pub fn place_crib<M: PlaintextModel + ?Sized>(ciphertexts: &[Vec<u8>],
+        keystream: &[Option<u8>], line: usize, offset: usize, crib: &[u8],
+        model: &M) -> Option<CribMatch> {
+    let end = offset + crib.len();
+    let text = ciphertexts.get(line)?;
+    if crib.is_empty() || end > text.len() {
+        return None;
+    }
+    let key: Vec<u8> = text[offset..end].iter().zip(crib.iter())
+        .map(|(&c, &p)| c ^ p).collect();
+    let consistent = key.iter().zip(keystream[offset..].iter())
+        .all(|(&k, &known)| known.is_none_or(|known| known == k));
+    if !consistent {
+        return None;
+    }
+    let fragments: Vec<(usize, Vec<u8>)> = ciphertexts.iter().enumerate()
+        .filter(|&(i, other)| i != line && other.len() >= end)
+        .map(|(i, other)| {
+            (i, other[offset..end].iter().zip(key.iter())
+                .map(|(&c, &k)| c ^ k).collect())
+        }).collect();
+    if fragments.is_empty() {
+        return None;
+    }
+    // The fragments are scored after the known bytes which precede them
+    let start = (0..offset).rev().take(CONTEXT)
+        .take_while(|&i| keystream[i].is_some()).last().unwrap_or(offset);
+    let score = fragments.iter().fold(0.0, |sum, &(i, ref fragment)| {
+        let mut text: Vec<u8> = ciphertexts[i][start..offset].iter()
+            .zip(keystream[start..].iter())
+            .map(|(&c, &k)| c ^ k.unwrap()).collect();
+        text.extend_from_slice(fragment.as_slice());
+        sum + match start {
+            0 => model.score_start(text.as_slice()),
+            _ => model.score(text.as_slice())
+        }
+    }) / fragments.len() as f64;
+    Some(CribMatch{line, offset, crib: crib.to_vec(), score, fragments})
+}
+
+/*
+ * Slide the crib over every ciphertext and rank the positions by the score
+ * of the fragments, the best match is the first one
+ */
+pub fn drag<M: PlaintextModel + ?Sized>(ciphertexts: &[Vec<u8>],
+        keystream: &[Option<u8>], crib: &[u8], model: &M)
+        -> Vec<CribMatch> {
+    let mut matches: Vec<CribMatch> = ciphertexts.iter().enumerate()
+        .flat_map(|(line, text)| {
+            (0..(text.len() + 1).saturating_sub(crib.len()))
+                .map(move |offset| (line, offset))
+        })
+        .filter_map(|(line, offset)| {
+            place_crib(ciphertexts, keystream, line, offset, crib, model)
+        }).collect();
+    sort_matches(&mut matches);
+    matches
+}
+
+fn sort_matches(matches: &mut [CribMatch]) {
+    matches.sort_by(|first, second| {
+        second.score.partial_cmp(&first.score).unwrap_or(Ordering::Equal)
+    });
+}
+
+/*
+ * Crib dragging engine for a few ciphertexts encrypted with the same
+ * keystream
+ */
+pub struct CribDragger<'a, M: PlaintextModel + ?Sized> {
+    ciphertexts: Vec<Vec<u8>>,
+    model: &'a M,
+    // Keystream bytes recovered from the placed cribs
+    keystream: Vec<Option<u8>>
+}
+
+impl<'a, M: PlaintextModel + ?Sized> CribDragger<'a, M> {
+    pub fn new(ciphertexts: &[Vec<u8>], model: &'a M)
+            -> CribDragger<'a, M> {
+        let len = ciphertexts.iter().map(|t| t.len()).max().unwrap_or(0);
+        CribDragger{ciphertexts: ciphertexts.to_vec(), model,
+                    keystream: vec![None; len]}
+    }
+
+    pub fn keystream(&self) -> &[Option<u8>] {
+        self.keystream.as_slice()
+    }
+
+    /*
+     * Partially recovered plaintexts, the unknown bytes are None
+     */
+    pub fn plaintexts(&self) -> Vec<Vec<Option<u8>>> {
+        self.ciphertexts.iter().map(|text| {
+            text.iter().zip(self.keystream.iter())
+                .map(|(&c, &k)| k.map(|k| c ^ k)).collect()
+        }).collect()
+    }
+
+    pub fn drag(&self, crib: &[u8]) -> Vec<CribMatch> {
+        drag(self.ciphertexts.as_slice(), self.keystream.as_slice(), crib,
+             self.model)
+    }
+
+    /*
+     * Fix the plaintext of the ciphertext at the offset, the later placement
+     * replaces the earlier one
+     */
+    pub fn place(&mut self, line: usize, offset: usize, plaintext: &[u8])
+            -> Result<(), GuessError> {
+        let text = match self.ciphertexts.get(line) {
+            Some(text) => text,
+            None => return Err(GuessError::NoCiphertext(line))
+        };
+        let end = offset + plaintext.len();
+        if end > text.len() {
+            return Err(GuessError::TooLong(end));
+        }
+        for (i, &c) in plaintext.iter().enumerate() {
+            self.keystream[offset + i] = Some(text[offset + i] ^ c);
+        }
+        Ok(())
+    }
+
+    /*
+     * Continue the words at the right ends of the known plaintext of the
+     * ciphertext with the dictionary words followed by a space, the
+     * extensions are ranked by the score of the other fragments and the
+     * extensions which complete the words of the other plaintexts which
+     * aren't in the dictionary are skipped
+     */
+    pub fn extend(&self, line: usize, dictionary: &[&str])
+            -> Vec<CribMatch> {
+        let text = match self.ciphertexts.get(line) {
+            Some(text) => text,
+            None => return Vec::new()
+        };
+        let words: HashSet<Vec<u8>> = dictionary.iter()
+            .map(|word| word.as_bytes().to_ascii_lowercase()).collect();
+        let prefixes: HashSet<Vec<u8>> = words.iter()
+            .flat_map(|word| (1..=word.len()).map(|i| word[..i].to_vec()))
+            .collect();
+        let plaintexts = self.plaintexts();
+        let plaintext = &plaintexts[line];
+        let mut matches = Vec::new();
+        for end in 1..text.len() {
+            if plaintext[end].is_some() || plaintext[end - 1].is_none() {
+                continue;
+            }
+            // The word is the known letters before the end
+            let start = (0..end).rev()
+                .take_while(|&i| plaintext[i].is_some_and(is_word_char))
+                .last().unwrap_or(end);
+            let word: Vec<u8> = plaintext[start..end].iter()
+                .map(|c| c.unwrap()).collect();
+            for entry in dictionary.iter() {
+                let entry = entry.as_bytes();
+                if entry.len() <= word.len() || !entry.starts_with(&word) {
+                    continue;
+                }
+                let mut crib = entry[word.len()..].to_vec();
+                crib.push(b' ');
+                crib.truncate(text.len() - end);
+                let found = place_crib(
+                    self.ciphertexts.as_slice(), self.keystream.as_slice(),
+                    line, end, crib.as_slice(), self.model);
+                matches.extend(found.filter(|found| {
+                    found.fragments.iter().all(|&(i, ref fragment)| {
+                        let mut other = plaintexts[i].clone();
+                        for (j, &c) in fragment.iter().enumerate() {
+                            other[end + j] = Some(c);
+                        }
+                        known_words(other.as_slice(), end,
+                                    end + fragment.len(), &words, &prefixes)
+                    })
+                }));
+            }
+        }
+        sort_matches(&mut matches);
+        matches
+    }
+
+    /*
+     * Greedily place the best extensions of all the ciphertexts while their
+     * score is at least the minimum, return the number of placed bytes
+     */
+    pub fn extend_all(&mut self, dictionary: &[&str], min_score: f64)
+            -> usize {
+        let mut placed = 0;
+        loop {
+            let mut matches: Vec<CribMatch> = (0..self.ciphertexts.len())
+                .flat_map(|line| self.extend(line, dictionary)).collect();
+            sort_matches(&mut matches);
+            let best = match matches.into_iter().next() {
+                Some(best) if best.score >= min_score => best,
+                _ => return placed
+            };
+            placed += best.crib.len();
+            // The extension is consistent with the known keystream
+            self.place(best.line, best.offset, best.crib.as_slice())
+                .unwrap();
+        }
+    }
+}
+
+#[inline]
+fn is_word_char(c: u8) -> bool {
+    c.is_ascii_alphabetic() || c == b'\''
+}
+
+/*
+ * Check the complete words which overlap the range are in the dictionary and
+ * the word cut by the unknown bytes on the right is a prefix of a dictionary
+ * word
+ */
+fn known_words(text: &[Option<u8>], start: usize, end: usize,
+               words: &HashSet<Vec<u8>>, prefixes: &HashSet<Vec<u8>>)
+        -> bool {
+    let first = (0..start).rev()
+        .take_while(|&i| text[i].is_some_and(is_word_char))
+        .last().unwrap_or(start);
+    let last = (end..text.len())
+        .take_while(|&i| text[i].is_some_and(is_word_char))
+        .last().map_or(end, |i| i + 1);
+    let closed_left = first == 0 || text[first - 1].is_some();
+    let closed_right = last == text.len() || text[last].is_some();
+    let segment: Vec<u8> = text[first..last].iter()
+        .map(|c| c.unwrap_or(0)).collect();
+    let pieces: Vec<&[u8]> = segment.split(|&c| !is_word_char(c)).collect();
+    pieces.iter().enumerate().all(|(i, piece)| {
+        let piece = piece.to_ascii_lowercase();
+        match (i > 0 || closed_left, i + 1 < pieces.len() || closed_right) {
+            _ if piece.is_empty() => true,
+            (true, true) => words.contains(&piece),
+            // The word continues after the known bytes
+            (true, false) => prefixes.contains(&piece),
+            (false, _) => true
+        }
+    })
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use aes_lib::encrypt_aes_ctr;
+    use mersenne_twister::cipher::{ByteOrder, KeyStream};
+    use crate::model::{EnglishModel, Scoring};
+    use crate::repeating::xor_with_key;
+    use super::{CribDragger, COMMON_WORDS};
+
+    static FIRST: &[u8] = b"we will attack the north gate at dawn";
+    static SECOND: &[u8] = b"the king has left the castle tonight";
+
+    fn recovered(dragger: &CribDragger<EnglishModel>, line: usize)
+            -> Vec<u8> {
+        dragger.plaintexts()[line].iter()
+            .map(|c| c.unwrap_or(b'?')).collect()
+    }
+
+    #[test]
+    fn test_drag() {
+        let model = EnglishModel::new(Scoring::LogLikelihood);
+        let key = b"0123456789abcdef0123456789abcdef01234";
+        let encrypted = vec![xor_with_key(FIRST, key),
+                             xor_with_key(SECOND, key)];
+        let mut dragger = CribDragger::new(encrypted.as_slice(), &model);
+        let matches = dragger.drag(b" the ");
+        assert!(matches.windows(2).all(|pair| {
+            pair[0].score >= pair[1].score
+        }));
+        // Two ciphertexts give the same fragments for the crib in any of them
+        assert_eq!(matches[0].score, matches[1].score);
+        let texts = [FIRST, SECOND];
+        let best = matches[..2].iter().find(|m| {
+            &texts[m.line][m.offset..m.offset + 5] == b" the "
+        }).unwrap();
+        assert_eq!(best.fragments[0].1.as_slice(),
+                   &texts[1 - best.line][best.offset..best.offset + 5]);
+        assert!(dragger.drag(b"").is_empty());
+
+        dragger.place(0, 0, b"we will").unwrap();
+        assert_eq!(&recovered(&dragger, 1)[..8], b"the kin?");
+        // The placed crib excludes the inconsistent positions
+        assert!(dragger.drag(b"ab").iter().all(|m| m.offset >= 6));
+    }
+
+    #[test]
+    fn test_extend() {
+        let model = EnglishModel::new(Scoring::LogLikelihood);
+        let seed = 0x5eedu32;
+        let encrypted: Vec<Vec<u8>> = [FIRST, SECOND].iter().map(|text| {
+            let stream = KeyStream::new(seed, ByteOrder::BigEndian);
+            text.iter().zip(stream).map(|(&c, k)| c ^ k).collect()
+        }).collect();
+        let mut dragger = CribDragger::new(encrypted.as_slice(), &model);
+        dragger.place(1, 0, b"the k").unwrap();
+        let matches = dragger.extend(1, &["we", "will", "attack", "king",
+                                          "kind", "keep"]);
+        assert_eq!(matches[0].crib.as_slice(), b"ing ");
+        assert_eq!(matches[0].offset, 5);
+        assert!(dragger.extend(5, &COMMON_WORDS).is_empty());
+    }
+
+    #[test]
+    fn test_extend_all() {
+        let model = EnglishModel::new(Scoring::LogLikelihood);
+        let key = b"YELLOW SUBMARINE";
+        let encrypted = vec![encrypt_aes_ctr(FIRST, key, 7),
+                             encrypt_aes_ctr(SECOND, key, 7)];
+        let mut dragger = CribDragger::new(encrypted.as_slice(), &model);
+        let mut dictionary = COMMON_WORDS.to_vec();
+        dictionary.extend_from_slice(&["attack", "north", "gate", "dawn",
+                                       "king", "has", "left", "castle",
+                                       "tonight"]);
+        dragger.place(0, 0, b"we ").unwrap();
+        let placed = dragger.extend_all(dictionary.as_slice(), -3.0);
+        assert_eq!(placed, 30);
+        assert_eq!(recovered(&dragger, 0).as_slice(),
+                   b"we will attack the north gate at ????");
+        assert_eq!(recovered(&dragger, 1).as_slice(),
+                   b"the king has left the castle toni???");
+    }
+}
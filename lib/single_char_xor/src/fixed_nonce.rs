@@ -0,0 +1,268 @@
+/* Fixed-nonce keystream breaker
+ *
+ * The ciphertexts are encrypted with the same keystream so the bytes at the
+ * same position form a single-character XOR column. Every column is scored
+ * in the context of the preceding bytes of its ciphertexts, so the columns
+ * near the end of the longest ciphertexts with only a few samples still get
+ * the bigram statistics. The plaintext guesses fix the keystream bytes and
+ * propagate to all the ciphertexts.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::crib::{drag, CribMatch};
+use crate::model::PlaintextModel;
+use crate::repeating::column_confidence;
+
+// Number of the preceding bytes used as the context
+static CONTEXT: usize = 4;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum GuessError {
+    // Index of the missing ciphertext
+    NoCiphertext(usize),
+    // The guess ends after the end of the ciphertext
+    TooLong(usize)
+}
+
+impl fmt::Display for GuessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GuessError::NoCiphertext(line) =>
+                write!(f, "No ciphertext at line {}", line),
+            GuessError::TooLong(end) =>
+                write!(f, "Guess ends after the ciphertext at {}", end)
+        }
+    }
+}
+
+/*
+ * Recovered keystream
+ */
+#[derive(Clone, PartialEq, Debug)]
+pub struct Keystream {
+    pub key: Vec<u8>,
+    // Confidence of every key byte from 0 to 1, the guessed bytes have 1
+    pub confidence: Vec<f64>,
+    // Number of the ciphertexts which cover every key byte
+    pub samples: Vec<usize>
+}
+
+impl Keystream {
+    pub fn decrypt(&self, ciphertexts: &[Vec<u8>]) -> Vec<Vec<u8>> {
+        ciphertexts.iter().map(|text| {
+            text.iter().zip(self.key.iter()).map(|(&c, &k)| c ^ k).collect()
+        }).collect()
+    }
+
+    /*
+     * Return positions of the key bytes with confidence below the threshold
+     */
+    pub fn low_confidence(&self, threshold: f64) -> Vec<usize> {
+        self.confidence.iter().enumerate()
+            .filter(|&(_, &conf)| conf < threshold)
+            .map(|(i, _)| i).collect()
+    }
+}
+
+pub struct FixedNonceBreaker<'a, M: PlaintextModel + ?Sized> {
+    ciphertexts: Vec<Vec<u8>>,
+    model: &'a M,
+    // Keystream bytes fixed by the guesses
+    fixed: Vec<Option<u8>>
+}
+
+impl<'a, M: PlaintextModel + ?Sized> FixedNonceBreaker<'a, M> {
+    pub fn new(ciphertexts: &[Vec<u8>], model: &'a M)
+            -> FixedNonceBreaker<'a, M> {
+        let len = ciphertexts.iter().map(|t| t.len()).max().unwrap_or(0);
+        FixedNonceBreaker{ciphertexts: ciphertexts.to_vec(), model,
+                          fixed: vec![None; len]}
+    }
+
+    /*
+     * Guess the plaintext of the ciphertext at the offset, the later guess
+     * replaces the earlier one
+     */
+    pub fn guess(&mut self, line: usize, offset: usize, plaintext: &[u8])
+            -> Result<(), GuessError> {
+        let text = match self.ciphertexts.get(line) {
+            Some(text) => text,
+            None => return Err(GuessError::NoCiphertext(line))
+        };
+        let end = offset + plaintext.len();
+        if end > text.len() {
+            return Err(GuessError::TooLong(end));
+        }
+        for (i, &c) in plaintext.iter().enumerate() {
+            self.fixed[offset + i] = Some(text[offset + i] ^ c);
+        }
+        Ok(())
+    }
+
+    /*
+     * Recover the keystream column by column from the left
+     */
+    pub fn solve(&self) -> Keystream {
+        let len = self.fixed.len();
+        let mut key = Vec::with_capacity(len);
+        let mut confidence = Vec::with_capacity(len);
+        let mut samples = Vec::with_capacity(len);
+        for i in 0..len {
+            samples.push(self.ciphertexts.iter()
+                         .filter(|text| text.len() > i).count());
+            let (k, conf) = match self.fixed[i] {
+                Some(k) => (k, 1.0),
+                None => self.solve_column(i, key.as_slice())
+            };
+            key.push(k);
+            confidence.push(conf);
+        }
+        Keystream{key, confidence, samples}
+    }
+
+    /*
+     * Score the key byte by the total log-likelihood of the column bytes
+     * appended to the preceding plaintext bytes, the windows at the start of
+     * the texts are scored as the start of the plaintext
+     */
+    fn solve_column(&self, index: usize, key: &[u8]) -> (u8, f64) {
+        let start = index.saturating_sub(CONTEXT);
+        let mut scores: Vec<(u8, f64)> = (0..256u16).map(|k| {
+            let score = self.ciphertexts.iter()
+                .filter(|text| text.len() > index)
+                .fold(0.0, |sum, text| {
+                    let mut window: Vec<u8> = text[start..index].iter()
+                        .zip(key[start..].iter())
+                        .map(|(&c, &k)| c ^ k).collect();
+                    window.push(text[index] ^ k as u8);
+                    let score = match start {
+                        0 => self.model.score_start(window.as_slice()),
+                        _ => self.model.score(window.as_slice())
+                    };
+                    sum + score * window.len() as f64
+                });
+            (k as u8, score)
+        }).collect();
+        scores.sort_by(|first, second| {
+            second.1.partial_cmp(&first.1).unwrap_or(Ordering::Equal)
+        });
+        let values: Vec<f64> = scores.iter().map(|s| s.1).collect();
+        (scores[0].0, column_confidence(values.as_slice(), 1))
+    }
+
+    /*
+     * Slide the crib over every ciphertext and rank the positions by the
+     * score of the other ciphertexts decrypted with the implied keystream,
+     * the positions which contradict the guesses are skipped
+     */
+    pub fn drag(&self, crib: &[u8]) -> Vec<CribMatch> {
+        drag(self.ciphertexts.as_slice(), self.fixed.as_slice(), crib,
+             self.model)
+    }
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use mersenne_twister::MersenneTwister;
+    use crate::model::{EnglishModel, Scoring};
+    use super::{FixedNonceBreaker, GuessError};
+
+    static TEXTS: [&[u8]; 12] = [
+        b"I have met them at close of day",
+        b"Coming with vivid faces",
+        b"From counter or desk among grey",
+        b"Eighteenth-century houses.",
+        b"I have passed with a nod of the head",
+        b"Or polite meaningless words,",
+        b"Or have lingered awhile and said",
+        b"Polite meaningless words,",
+        b"And thought before I had done",
+        b"Of a mocking tale or a gibe",
+        b"To please a companion",
+        b"Around the fire at the club, being certain that they and I"];
+
+    fn encrypt() -> Vec<Vec<u8>> {
+        let mut rng = MersenneTwister::new(0x2545f491u32);
+        let key: Vec<u8> = (0..64).map(|_| rng.rand_u32() as u8).collect();
+        TEXTS.iter().map(|text| {
+            text.iter().zip(key.iter()).map(|(&c, &k)| c ^ k).collect()
+        }).collect()
+    }
+
+    fn correct(decrypted: &[Vec<u8>], start: usize, end: usize) -> usize {
+        decrypted.iter().zip(TEXTS.iter()).map(|(dec, text)| {
+            (start..end.min(text.len()))
+                .filter(|&i| dec[i] == text[i]).count()
+        }).sum()
+    }
+
+    #[test]
+    fn test_solve() {
+        let model = EnglishModel::new(Scoring::LogLikelihood);
+        let encrypted = encrypt();
+        let breaker = FixedNonceBreaker::new(encrypted.as_slice(), &model);
+        let keystream = breaker.solve();
+        assert_eq!(keystream.key.len(), TEXTS[11].len());
+        assert_eq!(keystream.samples[0], 12);
+        assert_eq!(keystream.samples[40], 1);
+        let decrypted = keystream.decrypt(encrypted.as_slice());
+        let total: usize = TEXTS.iter().map(|t| t.len().min(23)).sum();
+        assert!(correct(decrypted.as_slice(), 0, 23) * 10 >= total * 9);
+        let total: usize = TEXTS.iter().map(|t| t.len().min(31)).sum();
+        assert!(correct(decrypted.as_slice(), 0, 31) * 100 >= total * 95);
+        // The single sample columns are uncertain
+        let low = keystream.low_confidence(0.5);
+        assert!(low.iter().filter(|&&i| i > 36).count() > 10);
+        assert!(keystream.confidence.iter().all(|&c| c > 0.0 && c <= 1.0));
+        // The texts start with the capital letters
+        assert!(decrypted.iter().zip(TEXTS.iter())
+                .all(|(dec, text)| dec[0] == text[0]));
+    }
+
+    #[test]
+    fn test_guess() {
+        let model = EnglishModel::new(Scoring::LogLikelihood);
+        let encrypted = encrypt();
+        let mut breaker = FixedNonceBreaker::new(encrypted.as_slice(),
+                                                 &model);
+        assert_eq!(breaker.guess(12, 0, b"I"),
+                   Err(GuessError::NoCiphertext(12)));
+        assert_eq!(breaker.guess(10, 16, b"anion!"),
+                   Err(GuessError::TooLong(22)));
+        breaker.guess(4, 0, TEXTS[4]).unwrap();
+        breaker.guess(11, 36, &TEXTS[11][36..]).unwrap();
+        let keystream = breaker.solve();
+        assert!(keystream.low_confidence(1.0).len() < 8);
+        let decrypted = keystream.decrypt(encrypted.as_slice());
+        assert_eq!(decrypted[0].as_slice(), TEXTS[0]);
+        assert_eq!(decrypted[11].as_slice(), TEXTS[11]);
+    }
+
+    #[test]
+    fn test_drag() {
+        let model = EnglishModel::new(Scoring::LogLikelihood);
+        let encrypted = encrypt();
+        let mut breaker = FixedNonceBreaker::new(encrypted.as_slice(),
+                                                 &model);
+        let matches = breaker.drag(b" the ");
+        assert!(matches.windows(2).all(|pair| {
+            pair[0].score >= pair[1].score
+        }));
+        let best = &matches[0];
+        assert_eq!(&TEXTS[best.line][best.offset..best.offset + 5],
+                   b" the ");
+        assert!(breaker.drag(b"").is_empty());
+
+        // The guess excludes the inconsistent positions
+        breaker.guess(0, 0, b"I have").unwrap();
+        assert!(breaker.drag(b"Coming").iter().all(|m| m.offset > 0
+                                                   || m.line == 1));
+    }
+}
@@ -0,0 +1,286 @@
+/* Key size estimation for repeating-key XOR
+ *
+ * Every estimator ranks the candidate key sizes, the most probable key size
+ * is the first one. Key sizes which don't give at least two full blocks of
+ * the ciphertext are skipped.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ops::Range;
+
+// Index of coincidence of English text bytes
+static ENGLISH_IOC: f64 = 0.066;
+// Index of coincidence of uniformly distributed bytes
+static RANDOM_IOC: f64 = 1.0 / 256.0;
+// Penalty of the index of coincidence for every column
+static COLUMN_PENALTY: f64 = 0.01;
+// Length of the repeated sequences used by Kasiski examination
+static KASISKI_SEQ_LEN: usize = 3;
+
+// Key size estimation method
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Estimator {
+    // Normalized Hamming distance between all the pairs of blocks
+    Hamming,
+    // Average index of coincidence of the columns
+    Coincidence,
+    // Key size computed from the index of coincidence of the whole text
+    Friedman,
+    // Distances between repeated sequences
+    Kasiski
+}
+
+pub static ESTIMATORS: [Estimator; 4] = [Estimator::Hamming,
+                                         Estimator::Coincidence,
+                                         Estimator::Friedman,
+                                         Estimator::Kasiski];
+
+/*
+ * Calculate Hamming distance between two equal-length buffers.
+ */
+pub fn hamming_distance(s1: &[u8], s2: &[u8]) -> usize {
+    if s1.len() != s2.len() {
+        panic!("Not equal length buffers");
+    }
+    s1.iter().zip(s2.iter()).fold(0, |sum, (&c1, &c2)| {
+        sum + (c1 ^ c2).count_ones() as usize
+    })
+}
+
+/*
+ * Return key sizes ranked by the estimator
+ */
+pub fn rank_keysizes(buffer: &[u8], keysizes: Range<usize>,
+                     estimator: Estimator) -> Vec<usize> {
+    let keysizes: Vec<usize> = keysizes
+        .filter(|&size| size > 0 && size * 2 <= buffer.len()).collect();
+    let mut scores: Vec<(usize, f64)> = match estimator {
+        Estimator::Hamming => keysizes.iter().map(|&size| {
+            (size, -hamming_score(buffer, size))
+        }).collect(),
+        Estimator::Coincidence => keysizes.iter().map(|&size| {
+            (size, coincidence_score(buffer, size))
+        }).collect(),
+        Estimator::Friedman => {
+            let estimate = friedman_estimate(buffer);
+            keysizes.iter().map(|&size| {
+                (size, -(size as f64 / estimate).ln().abs())
+            }).collect()
+        },
+        Estimator::Kasiski => {
+            let distances = repeat_distances(buffer);
+            keysizes.iter().map(|&size| {
+                (size, kasiski_score(distances.as_slice(), size))
+            }).collect()
+        }
+    };
+    // The sort is stable so the smaller key size wins on equal scores
+    scores.sort_by(|first, second| {
+        second.1.partial_cmp(&first.1).unwrap_or(Ordering::Equal)
+    });
+    scores.iter().map(|&(size, _)| size).collect()
+}
+
+/*
+ * Return key sizes ranked by all the estimators
+ *
+ * The rankings are combined by the sum of the reciprocal positions of every
+ * key size, so a key size ranked first by some estimators wins over a key
+ * size ranked high enough by all of them.
+ */
+pub fn guess_keysizes(buffer: &[u8], keysizes: Range<usize>) -> Vec<usize> {
+    let mut scores: Vec<(usize, f64)> = keysizes.clone()
+        .map(|size| (size, 0.0)).collect();
+    for &estimator in ESTIMATORS.iter() {
+        let ranking = rank_keysizes(buffer, keysizes.clone(), estimator);
+        for (pos, &size) in ranking.iter().enumerate() {
+            scores[size - keysizes.start].1 += 1.0 / (pos + 1) as f64;
+        }
+    }
+    scores.retain(|&(_, score)| score > 0.0);
+    scores.sort_by(|first, second| {
+        second.1.partial_cmp(&first.1).unwrap_or(Ordering::Equal)
+    });
+    scores.iter().map(|&(size, _)| size).collect()
+}
+
+/*
+ * Average Hamming distance per bit between all the pairs of full blocks
+ *
+ * The bytes encrypted by the same key byte are closer to each other than
+ * random bytes because the key cancels out.
+ */
+fn hamming_score(buffer: &[u8], size: usize) -> f64 {
+    let blocks: Vec<&[u8]> = buffer.chunks(size)
+        .filter(|block| block.len() == size).collect();
+    let mut dist = 0;
+    let mut pairs = 0;
+    for (i, b1) in blocks.iter().enumerate() {
+        for b2 in blocks[i + 1..].iter() {
+            dist += hamming_distance(b1, b2);
+            pairs += 1;
+        }
+    }
+    dist as f64 / (pairs * size * 8) as f64
+}
+
+/*
+ * Index of coincidence: probability that two random bytes of the buffer
+ * are equal
+ */
+fn index_of_coincidence<I: Iterator<Item=u8>>(bytes: I) -> f64 {
+    let mut counts = [0usize; 256];
+    let mut len = 0;
+    for c in bytes {
+        counts[c as usize] += 1;
+        len += 1;
+    }
+    if len < 2 {
+        return 0.0;
+    }
+    let pairs = counts.iter().fold(0, |sum, &n| {
+        sum + n * n.saturating_sub(1)
+    });
+    pairs as f64 / (len * (len - 1)) as f64
+}
+
+/*
+ * Average index of coincidence of the columns encrypted by the same key byte
+ *
+ * A multiple of the real key size gives the same index of coincidence, so
+ * the score is reduced for every additional column to prefer the smaller
+ * key size.
+ */
+fn coincidence_score(buffer: &[u8], size: usize) -> f64 {
+    let total = (0..size).fold(0.0, |sum, col| {
+        let column = buffer[col..].iter().enumerate()
+            .filter(|&(i, _)| i % size == 0).map(|(_, &c)| c);
+        sum + index_of_coincidence(column)
+    });
+    total / size as f64 / (1.0 + COLUMN_PENALTY * size as f64)
+}
+
+/*
+ * Friedman test
+ *
+ * Bytes encrypted by different key bytes rarely coincide so the index of
+ * coincidence of the whole ciphertext falls from the English value to the
+ * random value as the key size grows.
+ */
+fn friedman_estimate(buffer: &[u8]) -> f64 {
+    let ioc = index_of_coincidence(buffer.iter().copied());
+    if ioc <= RANDOM_IOC {
+        return buffer.len() as f64;
+    }
+    ((ENGLISH_IOC - RANDOM_IOC) / (ioc - RANDOM_IOC)).max(1.0)
+}
+
+/*
+ * Distances between the adjacent occurrences of the repeated sequences
+ */
+fn repeat_distances(buffer: &[u8]) -> Vec<usize> {
+    let mut last_seen: HashMap<&[u8], usize> = HashMap::new();
+    let mut distances = Vec::new();
+    for (pos, seq) in buffer.windows(KASISKI_SEQ_LEN).enumerate() {
+        if let Some(prev) = last_seen.insert(seq, pos) {
+            distances.push(pos - prev);
+        }
+    }
+    distances
+}
+
+/*
+ * Kasiski examination
+ *
+ * Repeated plaintext sequences encrypted by the same part of the key give
+ * repeated ciphertext sequences at a distance which is a multiple of the key
+ * size. The score is the share of such distances over the share expected by
+ * chance.
+ */
+fn kasiski_score(distances: &[usize], size: usize) -> f64 {
+    if distances.is_empty() {
+        return 0.0;
+    }
+    let divisible = distances.iter().filter(|&&d| d % size == 0).count();
+    divisible as f64 / distances.len() as f64 - 1.0 / size as f64
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use mersenne_twister::MersenneTwister;
+    use crate::repeating::xor_with_key;
+    use super::{hamming_distance, rank_keysizes, guess_keysizes};
+    use super::{Estimator, ESTIMATORS};
+
+    static TEXT: &[u8] = b"It was the best of times, it was the worst \
+        of times, it was the age of wisdom, it was the age of foolishness, it \
+        was the epoch of belief, it was the epoch of incredulity, it was the \
+        season of Light, it was the season of Darkness, it was the spring of \
+        hope, it was the winter of despair, we had everything before us, we \
+        had nothing before us, we were all going direct to Heaven, we were \
+        all going direct the other way - in short, the period was so far like \
+        the present period, that some of its noisiest authorities insisted on \
+        its being received, for good or for evil, in the superlative degree \
+        of comparison only.";
+
+    // Deterministic pseudo-random key
+    fn make_key(size: usize, seed: u32) -> Vec<u8> {
+        let mut rng = MersenneTwister::new(seed);
+        (0..size).map(|_| rng.rand_u32() as u8).collect()
+    }
+
+    #[test]
+    fn test_hamming_distance() {
+        let s1 = b"this is a test";
+        let s2 = b"wokka wokka!!!";
+        assert_eq!(hamming_distance(s1, s2), 37);
+    }
+
+    #[test]
+    fn test_rank_keysizes_bounds() {
+        for &estimator in ESTIMATORS.iter() {
+            assert!(rank_keysizes(b"123", 2..10, estimator).is_empty());
+            let mut sizes = rank_keysizes(b"1234512345", 0..10, estimator);
+            sizes.sort();
+            assert_eq!(sizes, vec![1, 2, 3, 4, 5]);
+        }
+        assert_eq!(guess_keysizes(b"1234512345", 2..6)[0], 5);
+        assert_eq!(rank_keysizes(b"1234512345", 2..6, Estimator::Hamming),
+                   vec![5, 4, 2, 3]);
+    }
+
+    /*
+     * Share of the synthetic ciphertexts with the real key size ranked first
+     */
+    fn accuracy<F>(rank: F) -> f64 where F: Fn(&[u8]) -> Vec<usize> {
+        let mut total = 0;
+        let mut correct = 0;
+        for size in 2..21 {
+            for &len in [200, 400, TEXT.len()].iter() {
+                let key = make_key(size, (size * len) as u32);
+                let encrypted = xor_with_key(&TEXT[..len], key.as_slice());
+                if rank(encrypted.as_slice()).first() == Some(&size) {
+                    correct += 1;
+                }
+                total += 1;
+            }
+        }
+        correct as f64 / total as f64
+    }
+
+    #[test]
+    fn test_keysize_accuracy() {
+        let combined = accuracy(|buffer| guess_keysizes(buffer, 2..41));
+        let hamming = accuracy(|buffer| {
+            rank_keysizes(buffer, 2..41, Estimator::Hamming)
+        });
+        assert!(combined > hamming, "{} <= {}", combined, hamming);
+        assert!(combined >= 0.75, "Combined accuracy: {}", combined);
+    }
+}
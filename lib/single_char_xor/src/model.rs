@@ -0,0 +1,522 @@
+/* Plaintext models used to score decryption candidates
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+use std::cmp::min;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/*
+ * Model of the expected plaintext
+ */
+pub trait PlaintextModel {
+    /*
+     * Score the text, the greater score is better
+     */
+    fn score(&self, text: &[u8]) -> f64;
+
+    /*
+     * Score the text which starts the plaintext, by default the start isn't
+     * different from any other text
+     */
+    fn score_start(&self, text: &[u8]) -> f64 {
+        self.score(text)
+    }
+}
+
+// English letters frequencies in hundredths of percents
+static ENGLISH_LETTERS_FREQ: [f64; 26] = [
+    804.0, 148.0, 334.0, 382.0, 1249.0, 240.0, 187.0, 505.0, 757.0, 16.0,
+    54.0, 407.0, 251.0, 723.0, 764.0, 214.0, 12.0, 628.0, 651.0, 928.0,
+    273.0, 105.0, 168.0, 23.0, 166.0, 9.0];
+
+// The most frequent English bigrams and their frequencies in percents
+static ENGLISH_BIGRAMS_FREQ: [(&str, f64); 50] = [
+    ("th", 3.56), ("he", 3.07), ("in", 2.43), ("er", 2.05), ("an", 1.99),
+    ("re", 1.85), ("on", 1.76), ("at", 1.49), ("en", 1.45), ("nd", 1.35),
+    ("ti", 1.34), ("es", 1.34), ("or", 1.28), ("te", 1.20), ("of", 1.17),
+    ("ed", 1.17), ("is", 1.13), ("it", 1.12), ("al", 1.09), ("ar", 1.07),
+    ("st", 1.05), ("to", 1.04), ("nt", 1.04), ("ng", 0.95), ("se", 0.93),
+    ("ha", 0.93), ("as", 0.87), ("ou", 0.87), ("io", 0.83), ("le", 0.83),
+    ("ve", 0.83), ("co", 0.79), ("me", 0.79), ("de", 0.76), ("hi", 0.76),
+    ("ri", 0.73), ("ro", 0.73), ("ic", 0.70), ("ne", 0.69), ("ea", 0.69),
+    ("ra", 0.69), ("ce", 0.65), ("li", 0.62), ("ch", 0.60), ("ll", 0.58),
+    ("be", 0.58), ("ma", 0.57), ("si", 0.55), ("om", 0.55), ("ur", 0.54)];
+
+// Frequency of the bigrams which aren't in the table above
+static RARE_BIGRAM_FREQ: f64 = 0.05;
+
+// Share of the characters in English text
+static SPACE_SHARE: f64 = 0.17;
+static LETTERS_SHARE: f64 = 0.75;
+static UPPERCASE_SHARE: f64 = 0.08;
+static DIGITS_SHARE: f64 = 0.015;
+static PUNCTUATION_SHARE: f64 = 0.05;
+static OTHER_PRINTABLE_SHARE: f64 = 0.01;
+static WHITESPACE_SHARE: f64 = 0.005;
+static NON_PRINTABLE_SHARE: f64 = 0.0001;
+
+static PUNCTUATION: &[u8] = b".,'\"-!?;:()";
+
+// Share of the capital letters at the start of the text
+static START_UPPERCASE_SHARE: f64 = 0.9;
+
+// Scoring method of the English model
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Scoring {
+    ChiSquared,
+    LogLikelihood
+}
+
+/*
+ * English text model based on unigram and bigram frequency tables
+ */
+pub struct EnglishModel {
+    scoring: Scoring,
+    // Probability of every byte value
+    unigram: Vec<f64>,
+    // Expected share of the character classes used by chi-squared test
+    classes: Vec<f64>,
+    // Log-likelihood ratio of the letter bigrams over independent letters
+    bigram: Vec<f64>
+}
+
+impl EnglishModel {
+    pub fn new(scoring: Scoring) -> EnglishModel {
+        EnglishModel{scoring, unigram: unigram_table(),
+                     classes: classes_table(), bigram: bigram_table()}
+    }
+
+    /*
+     * Pearson's chi-squared statistic of the character classes
+     *
+     * The lower value means the text is closer to English, the empty text
+     * has the worst value.
+     */
+    pub fn chi_squared(&self, text: &[u8]) -> f64 {
+        if text.is_empty() {
+            return f64::INFINITY;
+        }
+        let mut observed = vec![0.0; self.classes.len()];
+        for &c in text.iter() {
+            observed[char_class(c)] += 1.0;
+        }
+        let len = text.len() as f64;
+        observed.iter().zip(self.classes.iter()).map(|(&obs, &share)| {
+            let expected = share * len;
+            (obs - expected) * (obs - expected) / expected
+        }).fold(0.0, |sum, v| sum + v)
+    }
+
+    /*
+     * Average log-likelihood of the text per character
+     *
+     * Unigram model over all the byte values corrected by bigram statistics
+     * for adjacent letters, the empty text has the worst value.
+     */
+    pub fn log_likelihood(&self, text: &[u8]) -> f64 {
+        if text.is_empty() {
+            return f64::NEG_INFINITY;
+        }
+        let unigram = text.iter().fold(0.0, |sum, &c| {
+            sum + self.unigram[c as usize].ln()
+        });
+        let bigram = text.windows(2).fold(0.0, |sum, pair| {
+            match (letter_index(pair[0]), letter_index(pair[1])) {
+                (Some(first), Some(second)) =>
+                    sum + self.bigram[first * 26 + second],
+                _ => sum
+            }
+        });
+        (unigram + bigram) / text.len() as f64
+    }
+}
+
+impl PlaintextModel for EnglishModel {
+    fn score(&self, text: &[u8]) -> f64 {
+        match self.scoring {
+            Scoring::ChiSquared => -self.chi_squared(text),
+            Scoring::LogLikelihood => self.log_likelihood(text)
+        }
+    }
+
+    /*
+     * The first letter of the text is usually capital, the chi-squared
+     * classes don't depend on the case
+     */
+    fn score_start(&self, text: &[u8]) -> f64 {
+        let score = self.score(text);
+        let first = match (self.scoring, text.first()) {
+            (Scoring::LogLikelihood, Some(&c)) if letter_index(c).is_some() =>
+                c,
+            _ => return score
+        };
+        let letter = self.unigram[first.to_ascii_lowercase() as usize]
+            + self.unigram[first.to_ascii_uppercase() as usize];
+        let share = match first.is_ascii_uppercase() {
+            true => START_UPPERCASE_SHARE,
+            false => 1.0 - START_UPPERCASE_SHARE
+        };
+        let correction = (letter * share).ln()
+            - self.unigram[first as usize].ln();
+        score + correction / text.len() as f64
+    }
+}
+
+#[inline]
+fn letter_index(c: u8) -> Option<usize> {
+    match c {
+        b'a'..=b'z' => Some((c - b'a') as usize),
+        b'A'..=b'Z' => Some((c - b'A') as usize),
+        _ => None
+    }
+}
+
+/*
+ * Character class for chi-squared test: 26 letters, space, digits,
+ * punctuation and the rest of printable characters, whitespace and
+ * non-printable characters
+ */
+#[inline]
+fn char_class(c: u8) -> usize {
+    match letter_index(c) {
+        Some(i) => i,
+        None => match c {
+            b' ' => 26,
+            b'0'..=b'9' => 27,
+            b'\n' | b'\r' | b'\t' => 30,
+            c if PUNCTUATION.contains(&c) => 28,
+            0x21..=0x7e => 29,
+            _ => 31
+        }
+    }
+}
+
+fn classes_table() -> Vec<f64> {
+    let letters_sum = ENGLISH_LETTERS_FREQ.iter().fold(0.0, |s, &f| s + f);
+    let mut classes: Vec<f64> = ENGLISH_LETTERS_FREQ.iter().map(|&f| {
+        LETTERS_SHARE * f / letters_sum
+    }).collect();
+    classes.extend_from_slice(&[SPACE_SHARE, DIGITS_SHARE, PUNCTUATION_SHARE,
+                       OTHER_PRINTABLE_SHARE, WHITESPACE_SHARE,
+                       NON_PRINTABLE_SHARE]);
+    normalize(classes)
+}
+
+fn unigram_table() -> Vec<f64> {
+    let letters_sum = ENGLISH_LETTERS_FREQ.iter().fold(0.0, |s, &f| s + f);
+    let other_printable = 0x7f - 0x21 - 10 - 26 * 2 - PUNCTUATION.len();
+    let table = (0..256u16).map(|c| {
+        let c = c as u8;
+        match c {
+            b'a'..=b'z' => LETTERS_SHARE * (1.0 - UPPERCASE_SHARE)
+                * ENGLISH_LETTERS_FREQ[(c - b'a') as usize] / letters_sum,
+            b'A'..=b'Z' => LETTERS_SHARE * UPPERCASE_SHARE
+                * ENGLISH_LETTERS_FREQ[(c - b'A') as usize] / letters_sum,
+            b' ' => SPACE_SHARE,
+            b'0'..=b'9' => DIGITS_SHARE / 10.0,
+            b'\n' | b'\r' | b'\t' => WHITESPACE_SHARE / 3.0,
+            c if PUNCTUATION.contains(&c) =>
+                PUNCTUATION_SHARE / PUNCTUATION.len() as f64,
+            0x21..=0x7e => OTHER_PRINTABLE_SHARE / other_printable as f64,
+            // Control characters without the whitespace above and the
+            // bytes from 0x7f
+            _ => NON_PRINTABLE_SHARE / (256 - 0x7f + 0x20 - 3) as f64
+        }
+    }).collect();
+    normalize(table)
+}
+
+fn bigram_table() -> Vec<f64> {
+    let letters_sum = ENGLISH_LETTERS_FREQ.iter().fold(0.0, |s, &f| s + f);
+    let mut table: Vec<f64> = (0..26 * 26).map(|_| RARE_BIGRAM_FREQ).collect();
+    for &(bigram, freq) in ENGLISH_BIGRAMS_FREQ.iter() {
+        let bytes = bigram.as_bytes();
+        table[(bytes[0] - b'a') as usize * 26 + (bytes[1] - b'a') as usize] =
+            freq;
+    }
+    let bigrams_sum = table.iter().fold(0.0, |s, &f| s + f);
+    (0..26 * 26).map(|i| {
+        let first = ENGLISH_LETTERS_FREQ[i / 26] / letters_sum;
+        let second = ENGLISH_LETTERS_FREQ[i % 26] / letters_sum;
+        let ratio = (table[i] / bigrams_sum / (first * second)).ln();
+        // The flat frequency of the rare bigrams is above the product of
+        // the rare letters frequencies and would reward the bigrams like
+        // "qj" over the common ones, so a rare bigram never scores above
+        // the independent letters
+        match table[i] == RARE_BIGRAM_FREQ {
+            true => ratio.min(0.0),
+            false => ratio
+        }
+    }).collect()
+}
+
+#[inline]
+fn normalize(table: Vec<f64>) -> Vec<f64> {
+    let sum = table.iter().fold(0.0, |s, &v| s + v);
+    table.iter().map(|&v| v / sum).collect()
+}
+
+// Sample of JSON and similar ASCII-structured data for the built-in model
+static JSON_SAMPLE: &str = r#"{"id": 1024, "name": "admin",
+"email": "admin@example.com", "roles": ["user", "admin"], "active": true,
+"created_at": "2015-01-20T12:34:56Z", "settings": {"theme": "dark",
+"lang": "en-US", "timeout": 3600, "retries": 3, "ratio": 0.75},
+"tags": [], "parent": null}
+{"status": "ok", "code": 200, "data": [{"key": "value", "count": 12},
+{"key": "other_value", "count": 7, "enabled": false}], "next": null}
+{"user": {"uid": 10, "role": "user", "token": "c2VjcmV0LXRva2Vu"},
+"items": [{"sku": "A-100", "qty": 2, "price": 19.99},
+{"sku": "B-200", "qty": 1, "price": 5.5}], "total": 45.48}
+"#;
+
+/*
+ * Byte n-gram model trained on a corpus
+ *
+ * Probabilities of the orders from 1 to n are interpolated, the weight of a
+ * higher order depends on how often its context was seen in the corpus and
+ * the unigram probabilities are smoothed so every byte gets a non-zero
+ * probability.
+ */
+pub struct NgramModel {
+    n: usize,
+    // Counts of all the n-grams of length from 1 to n
+    counts: HashMap<Vec<u8>, u32>,
+    total: u32
+}
+
+impl NgramModel {
+    pub fn train(corpus: &[u8], n: usize) -> NgramModel {
+        if n == 0 {
+            panic!("Invalid n-gram order: {}", n);
+        }
+        let mut counts = HashMap::new();
+        for i in 0..corpus.len() {
+            for len in 1..min(n, corpus.len() - i) + 1 {
+                let gram = corpus[i..i + len].to_vec();
+                *counts.entry(gram).or_insert(0) += 1;
+            }
+        }
+        NgramModel{n, counts, total: corpus.len() as u32}
+    }
+
+    /*
+     * Train the model on a corpus file
+     */
+    pub fn from_file(path: &Path, n: usize) -> io::Result<NgramModel> {
+        let corpus = fs::read(path)?;
+        Ok(NgramModel::train(corpus.as_slice(), n))
+    }
+
+    /*
+     * Built-in model for JSON and other ASCII-structured data
+     */
+    pub fn json() -> NgramModel {
+        NgramModel::train(JSON_SAMPLE.as_bytes(), 3)
+    }
+
+    #[inline]
+    fn count(&self, gram: &[u8]) -> u32 {
+        self.counts.get(gram).cloned().unwrap_or(0)
+    }
+
+    /*
+     * Probability of the byte after the context
+     */
+    fn probability(&self, context: &[u8], c: u8) -> f64 {
+        let mut p = (self.count(&[c]) as f64 + 1.0)
+            / (self.total as f64 + 256.0);
+        let mut gram = Vec::with_capacity(self.n);
+        for len in 1..min(self.n, context.len() + 1) {
+            let ctx = &context[context.len() - len..];
+            let ctx_count = self.count(ctx) as f64;
+            if ctx_count == 0.0 {
+                break;
+            }
+            gram.clear();
+            gram.extend_from_slice(ctx);
+            gram.push(c);
+            let weight = ctx_count / (ctx_count + 2.0);
+            p = weight * self.count(gram.as_slice()) as f64 / ctx_count
+                + (1.0 - weight) * p;
+        }
+        p
+    }
+}
+
+impl PlaintextModel for NgramModel {
+    /*
+     * Average log-likelihood of the text per byte, the empty text has the
+     * worst value
+     */
+    fn score(&self, text: &[u8]) -> f64 {
+        if text.is_empty() {
+            return f64::NEG_INFINITY;
+        }
+        let sum = (0..text.len()).fold(0.0, |sum, i| {
+            let start = match i < self.n {
+                true => 0,
+                false => i + 1 - self.n
+            };
+            sum + self.probability(&text[start..i], text[i]).ln()
+        });
+        sum / text.len() as f64
+    }
+}
+
+/*
+ * UTF-8 text model for any language
+ *
+ * Valid UTF-8 sequences of printable characters are preferred, control
+ * characters and invalid sequences are penalized.
+ */
+pub struct Utf8Model;
+
+impl PlaintextModel for Utf8Model {
+    /*
+     * Average log-likelihood of the text per byte, the empty text has the
+     * worst value
+     */
+    fn score(&self, text: &[u8]) -> f64 {
+        if text.is_empty() {
+            return f64::NEG_INFINITY;
+        }
+        let mut sum = 0.0;
+        let mut i = 0;
+        while i < text.len() {
+            let (len, p): (usize, f64) = match utf8_char_len(&text[i..]) {
+                Some(1) => (1, match text[i] {
+                    b' ' => 0.15,
+                    b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' => 0.02,
+                    b'\n' | b'\r' | b'\t' => 0.005,
+                    0x21..=0x7e => 0.005,
+                    _ => 0.000001
+                }),
+                Some(len) => (len, 0.02),
+                None => (1, 0.000001)
+            };
+            sum += p.ln();
+            i += len;
+        }
+        sum / text.len() as f64
+    }
+}
+
+/*
+ * Length of the valid UTF-8 sequence at the start of the buffer
+ */
+fn utf8_char_len(buffer: &[u8]) -> Option<usize> {
+    let (len, second_min, second_max) = match buffer[0] {
+        0x00..=0x7f => return Some(1),
+        0xc2..=0xdf => (2, 0x80, 0xbf),
+        0xe0 => (3, 0xa0, 0xbf),
+        // Surrogates aren't allowed
+        0xed => (3, 0x80, 0x9f),
+        0xe1..=0xef => (3, 0x80, 0xbf),
+        0xf0 => (4, 0x90, 0xbf),
+        0xf1..=0xf3 => (4, 0x80, 0xbf),
+        0xf4 => (4, 0x80, 0x8f),
+        _ => return None
+    };
+    if buffer.len() < len || buffer[1] < second_min || buffer[1] > second_max
+            || buffer[2..len].iter().any(|&c| !(0x80..=0xbf).contains(&c)) {
+        return None;
+    }
+    Some(len)
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod test {
+    use super::{PlaintextModel, EnglishModel, Scoring, NgramModel, Utf8Model};
+    use super::{utf8_char_len, char_class};
+    use crate::decrypt_with;
+
+    fn xor_by_key(buffer: &[u8], key: u8) -> Vec<u8> {
+        buffer.iter().map(|&c| c ^ key).collect()
+    }
+
+    #[test]
+    fn test_english_model() {
+        let english = b"Now that the party is jumping";
+        let garbage = xor_by_key(english, 0x5a);
+        for &scoring in [Scoring::ChiSquared, Scoring::LogLikelihood].iter() {
+            let model = EnglishModel::new(scoring);
+            assert!(model.score(english) > model.score(garbage.as_slice()));
+        }
+        let model = EnglishModel::new(Scoring::ChiSquared);
+        assert!(model.chi_squared(english)
+                < model.chi_squared(garbage.as_slice()));
+        assert_eq!(model.chi_squared(b""), f64::INFINITY);
+        assert!(model.score(b"") < model.score(garbage.as_slice()));
+        let model = EnglishModel::new(Scoring::LogLikelihood);
+        assert_eq!(model.log_likelihood(b""), f64::NEG_INFINITY);
+        assert!(model.score(b"") < model.score(garbage.as_slice()));
+        // The empty text is the worst for every model
+        let models: [Box<dyn PlaintextModel>; 2] =
+            [Box::new(NgramModel::json()), Box::new(Utf8Model)];
+        for model in models.iter() {
+            assert_eq!(model.score(b""), f64::NEG_INFINITY);
+            assert!(model.score(b"") < model.score(garbage.as_slice()));
+        }
+    }
+
+    #[test]
+    fn test_english_tables() {
+        // Every non-letter class has the same share in both the tables
+        let model = EnglishModel::new(Scoring::ChiSquared);
+        for class in 26..model.classes.len() {
+            let share = (0..=255u8).filter(|&c| char_class(c) == class)
+                .fold(0.0, |sum, c| sum + model.unigram[c as usize]);
+            assert!((share - model.classes[class]).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_english_model_start() {
+        let model = EnglishModel::new(Scoring::LogLikelihood);
+        assert!(model.score(b"i have") > model.score(b"I have"));
+        assert!(model.score_start(b"I have") > model.score_start(b"i have"));
+        assert_eq!(model.score_start(b" have"), model.score(b" have"));
+        assert!(model.score_start(b"have") < model.score(b"have"));
+        let model = EnglishModel::new(Scoring::ChiSquared);
+        assert_eq!(model.score_start(b"i have"), model.score(b"I have"));
+    }
+
+    #[test]
+    fn test_rare_bigrams() {
+        let model = EnglishModel::new(Scoring::LogLikelihood);
+        assert!(model.score(b" king") > model.score(b" kqjg"));
+        let encrypted = xor_by_key(b"Hello", 0x3c);
+        let best = &decrypt_with(encrypted.as_slice(), &model)[0];
+        assert_eq!(best.2.as_slice(), b"Hello");
+    }
+
+    #[test]
+    fn test_ngram_model() {
+        let model = NgramModel::train(b"abcabcabcabd", 3);
+        assert!(model.score(b"abcab") > model.score(b"acbba"));
+        assert!(model.score(b"zzz").is_finite());
+        let json = NgramModel::json();
+        let text = b"{\"uid\": 42, \"role\": \"admin\"}";
+        assert!(json.score(text) > json.score(xor_by_key(text, 1).as_slice()));
+    }
+
+    #[test]
+    fn test_utf8_model() {
+        let text = "Привет, мир! Γειά σου".as_bytes();
+        let model = Utf8Model;
+        let garbage = xor_by_key(text, 1);
+        assert!(model.score(text) > model.score(garbage.as_slice()));
+        assert_eq!(utf8_char_len(b"a"), Some(1));
+        assert_eq!(utf8_char_len("ж".as_bytes()), Some(2));
+        assert_eq!(utf8_char_len("€".as_bytes()), Some(3));
+        assert_eq!(utf8_char_len(b"\xed\xa0\x80"), None);
+        assert_eq!(utf8_char_len(b"\xe2\x82"), None);
+    }
+}
@@ -0,0 +1,186 @@
+/* Repeating-key XOR breaker
+ *
+ * The ciphertext is split into columns of the bytes encrypted by the same
+ * key byte and every column is solved as a single-character XOR cipher.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+use std::cmp::Ordering;
+
+use crate::model::PlaintextModel;
+use super::decrypt_with;
+
+/*
+ * Repeating key candidate
+ */
+pub struct KeyCandidate {
+    pub key: Vec<u8>,
+    // Decrypted text
+    pub text: Vec<u8>,
+    // Score of the whole decrypted text
+    pub score: f64,
+    /* Confidence of every key byte from 0 to 1
+     *
+     * Probability of the chosen key byte over all the 256 values if the score
+     * is the average log-likelihood of the text per character, so the value
+     * close to 0 means other key bytes are almost as good as the chosen one.
+     */
+    pub confidence: Vec<f64>
+}
+
+impl KeyCandidate {
+    /*
+     * Return positions of the key bytes with confidence below the threshold
+     */
+    pub fn low_confidence(&self, threshold: f64) -> Vec<usize> {
+        self.confidence.iter().enumerate()
+            .filter(|&(_, &conf)| conf < threshold)
+            .map(|(i, _)| i).collect()
+    }
+}
+
+/*
+ * Try every key size and return key candidates ranked by the score of the
+ * decrypted text, the best candidate is the first one
+ *
+ * Key sizes which are zero or longer than the ciphertext are skipped. On equal
+ * scores the key size which comes first wins, so with an ascending range the
+ * real key wins over its repetitions.
+ */
+pub fn break_repeating_xor<M, I>(ciphertext: &[u8], keysize_range: I,
+                                 model: &M) -> Vec<KeyCandidate>
+        where M: PlaintextModel + ?Sized, I: IntoIterator<Item=usize> {
+    let mut candidates: Vec<KeyCandidate> = keysize_range.into_iter()
+        .filter(|&keysize| keysize > 0 && keysize <= ciphertext.len())
+        .map(|keysize| break_with_keysize(ciphertext, keysize, model))
+        .collect();
+    candidates.sort_by(|first, second| {
+        second.score.partial_cmp(&first.score).unwrap_or(Ordering::Equal)
+    });
+    candidates
+}
+
+/*
+ * XOR buffer by the repeating key
+ */
+pub fn xor_with_key(buffer: &[u8], key: &[u8]) -> Vec<u8> {
+    buffer.iter().zip(key.iter().cycle()).map(|(&c, &k)| c ^ k).collect()
+}
+
+/*
+ * Split the buffer into columns of the bytes at the same position modulo
+ * the key size
+ */
+pub fn transpose(buffer: &[u8], keysize: usize) -> Vec<Vec<u8>> {
+    let col_len = buffer.len().div_ceil(keysize);
+    let mut columns: Vec<Vec<u8>> = (0..keysize).map(|_|
+        Vec::with_capacity(col_len)).collect();
+    for block in buffer.chunks(keysize) {
+        for (i, &c) in block.iter().enumerate() {
+            columns[i].push(c);
+        }
+    }
+    columns
+}
+
+fn break_with_keysize<M: PlaintextModel + ?Sized>(ciphertext: &[u8],
+        keysize: usize, model: &M) -> KeyCandidate {
+    let mut key: Vec<u8> = Vec::with_capacity(keysize);
+    let mut confidence: Vec<f64> = Vec::with_capacity(keysize);
+    let mut text = vec![0u8; ciphertext.len()];
+    for (i, column) in transpose(ciphertext, keysize).iter().enumerate() {
+        let candidates = decrypt_with(column.as_slice(), model);
+        let scores: Vec<f64> = candidates.iter().map(|c| c.1).collect();
+        let (k, _, ref decrypted) = candidates[0];
+        key.push(k);
+        confidence.push(column_confidence(scores.as_slice(), column.len()));
+        for (j, &c) in decrypted.iter().enumerate() {
+            text[i + j * keysize] = c;
+        }
+    }
+    let score = model.score(text.as_slice());
+    KeyCandidate{key, text, score, confidence}
+}
+
+/*
+ * Confidence of the best candidate from the per character scores sorted in
+ * descending order
+ */
+pub(crate) fn column_confidence(scores: &[f64], len: usize) -> f64 {
+    let best = scores[0];
+    let total = scores.iter().fold(0.0, |sum, &score| {
+        sum + ((score - best) * len as f64).exp()
+    });
+    1.0 / total
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use crate::model::{EnglishModel, Scoring};
+    use super::{break_repeating_xor, xor_with_key, transpose};
+
+    static TEXT: &[u8] = b"I'm back and I'm ringin' the bell \n\
+        A rockin' on the mike while the fly girls yell \n\
+        In ecstasy in the back of me \n\
+        Well that's my DJ Deshay cuttin' all them Z's \n\
+        Hittin' hard and the girlies goin' crazy \n\
+        Vanilla's on the mike, man I'm not lazy.";
+
+    #[test]
+    fn test_transpose() {
+        let columns = transpose(b"abcdefgh", 3);
+        assert_eq!(columns, vec![b"adg".to_vec(), b"beh".to_vec(),
+                                 b"cf".to_vec()]);
+    }
+
+    #[test]
+    fn test_xor_with_key() {
+        let encrypted = xor_with_key(b"abcd", b"\x01\x02");
+        assert_eq!(encrypted, b"``bf".to_vec());
+        assert_eq!(xor_with_key(encrypted.as_slice(), b"\x01\x02"),
+                   b"abcd".to_vec());
+    }
+
+    #[test]
+    fn test_break_repeating_xor() {
+        let model = EnglishModel::new(Scoring::LogLikelihood);
+        let key = b"ICE-9";
+        let encrypted = xor_with_key(TEXT, key);
+        let candidates = break_repeating_xor(encrypted.as_slice(), 2..11,
+                                             &model);
+        assert_eq!(candidates.len(), 9);
+        assert!(candidates.windows(2).all(|pair| {
+            pair[0].score >= pair[1].score
+        }));
+        let best = &candidates[0];
+        assert_eq!(best.key.as_slice(), key.as_slice());
+        assert_eq!(best.text.as_slice(), TEXT);
+        assert_eq!(best.confidence.len(), key.len());
+        assert!(best.confidence.iter().all(|&c| c > 0.0 && c <= 1.0));
+        assert!(break_repeating_xor(b"", 1..5, &model).is_empty());
+        // Only about 19 bytes per column of the longer key
+        let key = b"Terminator X";
+        let encrypted = xor_with_key(TEXT, key);
+        let candidates = break_repeating_xor(encrypted.as_slice(), 12..13,
+                                             &model);
+        assert_eq!(candidates[0].key.as_slice(), key.as_slice());
+    }
+
+    #[test]
+    fn test_low_confidence() {
+        let model = EnglishModel::new(Scoring::LogLikelihood);
+        let encrypted = xor_with_key(TEXT, b"ICE-9");
+        let candidates = break_repeating_xor(encrypted.as_slice(), 5..6,
+                                             &model);
+        assert!(candidates[0].low_confidence(0.9).is_empty());
+        assert_eq!(candidates[0].low_confidence(1.1).len(), 5);
+        // Two bytes per column aren't enough to be sure about the key
+        let candidates = break_repeating_xor(&encrypted[..10], 5..6,
+                                             &model);
+        assert!(candidates[0].low_confidence(0.9).len() >= 3);
+    }
+}
@@ -3,45 +3,48 @@
  * Dmitry Vasiliev <dima@hlabs.org>
  */
 
-#![crate_name="single_char_xor_lib"]
-#![crate_type="lib"]
+use std::cmp::Ordering;
 
-extern crate serialize;
+pub use model::{PlaintextModel, EnglishModel, Scoring, NgramModel, Utf8Model};
+pub use repeating::{KeyCandidate, break_repeating_xor, xor_with_key};
+pub use keysize::{Estimator, guess_keysizes, rank_keysizes};
+pub use fixed_nonce::{FixedNonceBreaker, Keystream, GuessError};
+pub use crib::{CribDragger, CribMatch, COMMON_WORDS};
 
-// English characters sorted by frequency
-static ENGLISH_CHARS_BY_FREQ: &'static[u8] =
-    b" eEtTaAiInNoOsSrRlLdDhHcCuUmMfFpPyYgGwWvVbBkKxXjJqQzZ";
+pub mod model;
+pub mod repeating;
+pub mod keysize;
+pub mod fixed_nonce;
+pub mod crib;
 
-// Result of the decryption
-pub enum SingleCharKey {
-    Found(u8, Vec<u8>),
-    NotFound
-}
+// Ranked decryption candidate: key, score and decrypted text
+pub type Candidate = (u8, f64, Vec<u8>);
 
-// Bytes statistics
-struct ByteStat {
-    byte: u8,
-    num: u64,
+/*
+ * Try all the keys and return decryption candidates ranked by the
+ * log-likelihood score of English text
+ */
+pub fn decrypt(buffer: &[u8]) -> Vec<Candidate> {
+    decrypt_with(buffer, &EnglishModel::new(Scoring::LogLikelihood))
 }
 
 /*
- * Try to decrypt XOR encrypted text in the buffer
+ * Try all the keys and return decryption candidates ranked by the plaintext
+ * model, the best candidate is the first one
  */
-pub fn decrypt(buffer: &[u8]) -> SingleCharKey {
+pub fn decrypt_with<M: PlaintextModel + ?Sized>(buffer: &[u8], model: &M)
+        -> Vec<Candidate> {
     if buffer.is_empty() {
-        return SingleCharKey::NotFound;
-    }
-    let first = get_most_freq_char(buffer);
-    for &c in ENGLISH_CHARS_BY_FREQ.iter() {
-        // Most frequent characters in the encrypted text probably should
-        // correspond to the most frequent characters in English
-        let key = first ^ c;
-        let decrypted = xor_by_key(buffer, key);
-        if is_english(decrypted.as_slice()) {
-            return SingleCharKey::Found(key, decrypted);
-        }
+        return Vec::new();
     }
-    SingleCharKey::NotFound
+    let mut candidates: Vec<Candidate> = (0..256u16).map(|key| {
+        let decrypted = xor_by_key(buffer, key as u8);
+        (key as u8, model.score(decrypted.as_slice()), decrypted)
+    }).collect();
+    candidates.sort_by(|first, second| {
+        second.1.partial_cmp(&first.1).unwrap_or(Ordering::Equal)
+    });
+    candidates
 }
 
 /*
@@ -52,55 +55,15 @@ fn xor_by_key(buffer: &[u8], key: u8) -> Vec<u8> {
     buffer.iter().map(|&c| c ^ key).collect()
 }
 
-/*
- * Return most frequent character from the buffer
- */
-fn get_most_freq_char(buffer: &[u8]) -> u8 {
-    // Statistics map for every possible byte value
-    let mut chars: Vec<ByteStat> = range(0u16, 256).map(|i| {
-            ByteStat{byte: i as u8, num: 0}
-        }).collect();
-    for &c in buffer.iter() {
-        chars[c as uint].num += 1;
-    }
-    // Sort in reverse order so most frequent character will be the first one
-    chars.sort_by(|first, second| second.num.cmp(&first.num));
-    chars[0].byte
-}
-
-/*
- * Is text in the buffer looks like an English text?
- */
-#[inline]
-fn is_english(buffer: &[u8]) -> bool {
-    /* The check is very simple here, we basically expect only printable
-     * characters. But it should work in most of the cases.
-     *
-     * And we can't use for example n-grams because this library also used for
-     * slices of text.
-     */
-    buffer.iter().all(|&c| is_english_char(c as char))
-}
-
-#[inline]
-fn is_english_char(c: char) -> bool {
-    match c {
-        'a'...'z' | 'A'...'Z' | '0'...'9'
-        | ' ' | '.' | ',' | '!' | '?' | '\'' | '-' | '"' | '&' | '%'
-        | '#' | '(' | ')' | '\n' | '\r' | '\t' | '/' | ':' => true,
-        _ => false
-    }
-}
-
 /*
  * Tests
  */
 #[cfg(test)]
 mod test {
     use std::str;
-    use serialize::hex::FromHex;
-    use super::{xor_by_key, get_most_freq_char, is_english};
-    use super::{decrypt, SingleCharKey};
+    use codec::Encoding;
+    use super::{xor_by_key, decrypt, decrypt_with};
+    use super::{EnglishModel, Scoring, NgramModel, Utf8Model};
 
     #[test]
     fn test_xor_by_key() {
@@ -111,29 +74,43 @@ mod test {
     }
 
     #[test]
-    fn test_get_most_freq_char() {
-        let buffer = [1u8, 1u8, 2u8, 2u8, 2u8, 3u8, 3u8, 3u8, 3u8];
-        assert_eq!(get_most_freq_char(buffer.as_slice()), 3u8);
+    fn test_decrypt() {
+        let buffer = "1b37373331363f78151b7f2b783431333d78397828372d363c78\
+                      373e783a393b3736";
+        let buf = Encoding::Hex.decode(buffer.as_bytes()).unwrap();
+        let candidates = decrypt(buf.as_slice());
+        assert_eq!(candidates.len(), 256);
+        assert!(candidates.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        let (key, _, ref decrypted) = candidates[0];
+        assert_eq!('X', key as char);
+        assert_eq!("Cooking MC's like a pound of bacon",
+                   str::from_utf8(decrypted.as_slice()).unwrap());
+        assert!(decrypt(&[]).is_empty());
     }
 
     #[test]
-    fn test_is_english() {
-        let buffer = ['t' as u8, 'e' as u8, 's' as u8, 't' as u8];
-        assert_eq!(is_english(buffer.as_slice()), true);
-        assert_eq!(is_english([1u8, 2u8, 3u8].as_slice()), false);
+    fn test_decrypt_punctuation() {
+        let text = b"Meet me at 10:30; bring 2 keys (the \"red\" ones) & 7%.";
+        let encrypted = xor_by_key(text, 0x91);
+        for &scoring in [Scoring::ChiSquared, Scoring::LogLikelihood].iter() {
+            let candidates = decrypt_with(encrypted.as_slice(),
+                                          &EnglishModel::new(scoring));
+            assert_eq!(candidates[0].0, 0x91);
+            assert_eq!(candidates[0].2.as_slice(), text.as_slice());
+        }
     }
 
     #[test]
-    fn test_decrypt() {
-        let buffer = "1b37373331363f78151b7f2b783431333d78397828372d363c78\
-                      373e783a393b3736";
-        let buf = buffer.from_hex().unwrap();
-        let (key, decrypted) = match decrypt(buf.as_slice()) {
-            SingleCharKey::Found(key, decrypted) => (key, decrypted),
-            SingleCharKey::NotFound => panic!("Key not found")
-        };
-        assert_eq!('X', key as char);
-        assert_eq!("Cooking MC's like a pound of bacon".as_slice(),
-                   str::from_utf8(decrypted.as_slice()).unwrap());
+    fn test_decrypt_with_models() {
+        let json = b"{\"uid\": 10, \"role\": \"user\", \"tags\": []}";
+        let encrypted = xor_by_key(json, 0x3c);
+        let model = NgramModel::json();
+        let candidates = decrypt_with(encrypted.as_slice(), &model);
+        assert_eq!(candidates[0].0, 0x3c);
+
+        let text = "Съешь же ещё этих мягких булок".as_bytes();
+        let encrypted = xor_by_key(text, 0xa7);
+        let candidates = decrypt_with(encrypted.as_slice(), &Utf8Model);
+        assert_eq!(candidates[0].0, 0xa7);
     }
 }
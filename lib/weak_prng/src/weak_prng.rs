@@ -0,0 +1,622 @@
+/* Weak PRNGs library: java.util.Random, glibc and MSVC rand(), V8's
+ * xorshift128+ and PCG32
+ *
+ * Every generator implements the Prng trait shared with MersenneTwister and
+ * provides a state-recovery or seed-cracking routine built from observed
+ * outputs.
+ *
+ * Dmitry Vasiliev <dima@hlabs.org>
+ */
+
+extern crate mersenne_twister;
+
+pub use mersenne_twister::Prng;
+
+
+/*
+ * java.util.Random 48-bit LCG
+ */
+static JAVA_MULTIPLIER: u64 = 0x5deece66d;
+static JAVA_ADDEND: u64 = 0xb;
+static JAVA_MASK: u64 = (1 << 48) - 1;
+
+pub struct JavaRandom {
+    seed: u64
+}
+
+impl JavaRandom {
+    /*
+     * Same as new Random(seed)
+     */
+    pub fn new(seed: i64) -> JavaRandom {
+        JavaRandom{seed: (seed as u64 ^ JAVA_MULTIPLIER) & JAVA_MASK}
+    }
+
+    #[inline]
+    fn next(&mut self, bits: usize) -> i32 {
+        self.seed = self.seed.wrapping_mul(JAVA_MULTIPLIER)
+            .wrapping_add(JAVA_ADDEND) & JAVA_MASK;
+        (self.seed >> (48 - bits)) as u32 as i32
+    }
+
+    pub fn next_int(&mut self) -> i32 {
+        self.next(32)
+    }
+
+    pub fn next_long(&mut self) -> i64 {
+        let high = self.next(32) as i64;
+        let low = self.next(32) as i64;
+        (high << 32).wrapping_add(low)
+    }
+
+    pub fn next_double(&mut self) -> f64 {
+        let high = self.next(26) as i64;
+        let low = self.next(27) as i64;
+        ((high << 27) + low) as f64 / 9007199254740992.0
+    }
+
+    /*
+     * Recover the internal state from consecutive nextInt() outputs
+     *
+     * The first output gives the upper 32 bits of the state so only 16 bits
+     * should be brute forced, the following outputs are used to check the
+     * guess. At least two outputs are required. The returned generator
+     * continues right after the last observed output.
+     */
+    pub fn from_outputs(outputs: &[i32]) -> Option<JavaRandom> {
+        if outputs.len() < 2 {
+            return None;
+        }
+        let high = (outputs[0] as u32 as u64) << 16;
+        for low in 0..0x10000u64 {
+            let mut rng = JavaRandom{seed: high | low};
+            if outputs[1..].iter().all(|&out| rng.next_int() == out) {
+                return Some(rng);
+            }
+        }
+        None
+    }
+}
+
+impl Prng for JavaRandom {
+    fn rand_u32(&mut self) -> u32 {
+        self.next_int() as u32
+    }
+
+    fn rand_f64(&mut self) -> f64 {
+        self.next_double()
+    }
+}
+
+/*
+ * glibc random()/rand() TYPE_3 additive feedback generator
+ */
+static GLIBC_DEGREE: usize = 31;
+static GLIBC_SEP: usize = 3;
+// The seeding keeps 34 words and drops the first 310 outputs
+static GLIBC_STATE_SIZE: usize = 34;
+static GLIBC_DISCARD: usize = 310;
+
+pub struct GlibcRand {
+    state: Vec<u32>,
+    index: usize
+}
+
+impl GlibcRand {
+    /*
+     * Same as srand(seed)
+     */
+    pub fn new(seed: u32) -> GlibcRand {
+        let mut state = Vec::with_capacity(GLIBC_STATE_SIZE);
+        state.push(match seed {
+            0 => 1,
+            seed => seed
+        });
+        for i in 1..GLIBC_DEGREE {
+            // Park-Miller "minimal standard" generator computed without
+            // overflow by the Schrage's method
+            let prev = state[i - 1] as i32 as i64;
+            let word = 16807 * (prev % 127773) - 2836 * (prev / 127773);
+            state.push(match word < 0 {
+                true => (word + 2147483647) as u32,
+                false => word as u32
+            });
+        }
+        for i in GLIBC_DEGREE..GLIBC_STATE_SIZE {
+            let word = state[i - GLIBC_DEGREE];
+            state.push(word);
+        }
+        let mut rng = GlibcRand{state, index: 0};
+        for _ in 0..GLIBC_DISCARD {
+            rng.next_word();
+        }
+        rng
+    }
+
+    #[inline]
+    fn next_word(&mut self) -> u32 {
+        let size = GLIBC_STATE_SIZE;
+        let word = self.state[(self.index + size - GLIBC_DEGREE) % size]
+            .wrapping_add(self.state[(self.index + size - GLIBC_SEP) % size]);
+        self.state[self.index] = word;
+        self.index = (self.index + 1) % size;
+        word
+    }
+
+    /*
+     * Return the next value in [0, RAND_MAX] interval
+     */
+    pub fn rand(&mut self) -> u32 {
+        self.next_word() >> 1
+    }
+
+    /*
+     * Find the seed which produces the outputs
+     *
+     * Try every seed from the candidates, for example all the timestamps
+     * around the time when srand(time(NULL)) was called.
+     */
+    pub fn crack_seed<I: Iterator<Item=u32>>(outputs: &[u32], seeds: I)
+            -> Option<u32> {
+        let mut seeds = seeds;
+        seeds.find(|&seed| {
+            let mut rng = GlibcRand::new(seed);
+            outputs.iter().all(|&out| rng.rand() == out)
+        })
+    }
+}
+
+impl Prng for GlibcRand {
+    fn rand_u32(&mut self) -> u32 {
+        self.rand()
+    }
+
+    fn rand_f64(&mut self) -> f64 {
+        self.rand() as f64 / 2147483648.0
+    }
+}
+
+/*
+ * MSVC rand() LCG
+ */
+pub struct MsvcRand {
+    state: u32
+}
+
+impl MsvcRand {
+    /*
+     * Same as srand(seed)
+     */
+    pub fn new(seed: u32) -> MsvcRand {
+        MsvcRand{state: seed}
+    }
+
+    /*
+     * Return the next value in [0, RAND_MAX] interval
+     */
+    pub fn rand(&mut self) -> u32 {
+        self.state = self.state.wrapping_mul(214013).wrapping_add(2531011);
+        (self.state >> 16) & 0x7fff
+    }
+
+    /*
+     * Recover the internal state from consecutive rand() outputs
+     *
+     * The first output gives bits 16-30 of the state, the lower 16 bits are
+     * brute forced and the bit 31 never affects the output. Usually three
+     * outputs are enough to get the only candidate. The returned generator
+     * continues right after the last observed output.
+     */
+    pub fn from_outputs(outputs: &[u32]) -> Option<MsvcRand> {
+        if outputs.len() < 2 {
+            return None;
+        }
+        let high = (outputs[0] & 0x7fff) << 16;
+        for low in 0..0x10000u32 {
+            let mut rng = MsvcRand{state: high | low};
+            if outputs[1..].iter().all(|&out| rng.rand() == out) {
+                return Some(rng);
+            }
+        }
+        None
+    }
+}
+
+impl Prng for MsvcRand {
+    fn rand_u32(&mut self) -> u32 {
+        self.rand()
+    }
+
+    fn rand_f64(&mut self) -> f64 {
+        self.rand() as f64 / 32768.0
+    }
+}
+
+/*
+ * xorshift128+ as used by V8's Math.random() since V8 7.1 (Node.js 12 and
+ * later, checked against Node.js 20)
+ *
+ * V8 fills a cache of 64 values and returns them in reverse order, so the
+ * values of Math.random() should be put back into the generation order with
+ * v8_cache_order() before they're used for the state recovery.
+ */
+
+pub static V8_CACHE_SIZE: usize = 64;
+
+// Exponent bits of the doubles in [1.0, 2.0)
+static V8_EXPONENT_BITS: u64 = 0x3ff0000000000000;
+static V8_MANTISSA_MASK: u64 = (1 << 52) - 1;
+
+// Linear combination of the 128 unknown state bits
+type BitVec = [u64; 2];
+
+pub struct Xorshift128Plus {
+    state0: u64,
+    state1: u64
+}
+
+impl Xorshift128Plus {
+    pub fn new(state0: u64, state1: u64) -> Xorshift128Plus {
+        Xorshift128Plus{state0, state1}
+    }
+
+    /*
+     * Initialize the state from a seed the same way as Math.random() does
+     * with --random-seed
+     */
+    pub fn from_seed(seed: u64) -> Xorshift128Plus {
+        Xorshift128Plus::new(murmur_hash3(seed), murmur_hash3(!seed))
+    }
+
+    #[inline]
+    fn step(&mut self) {
+        let mut s1 = self.state0;
+        let s0 = self.state1;
+        self.state0 = s0;
+        s1 ^= s1 << 23;
+        s1 ^= s1 >> 17;
+        s1 ^= s0;
+        s1 ^= s0 >> 26;
+        self.state1 = s1;
+    }
+
+    /*
+     * Return the next double the same way as V8 fills the cache of
+     * Math.random(): the upper 52 bits of the state are the mantissa of a
+     * double in [1.0, 2.0)
+     */
+    pub fn next_double(&mut self) -> f64 {
+        self.step();
+        f64::from_bits((self.state0 >> 12) | V8_EXPONENT_BITS) - 1.0
+    }
+
+    /*
+     * Recover the internal state from consecutive rand_u32() outputs
+     *
+     * The generator is linear over GF(2), so every output gives 32 linear
+     * equations on the 128 state bits. At least six outputs are required.
+     */
+    pub fn from_outputs(outputs: &[u32]) -> Option<Xorshift128Plus> {
+        let values: Vec<u64> = outputs.iter().map(|&v| v as u64).collect();
+        recover_xorshift(values.as_slice(), 32)
+    }
+
+    /*
+     * Recover the internal state from consecutive next_double() outputs
+     *
+     * Every double gives the upper 52 bits of the state, at least four
+     * values are required.
+     */
+    pub fn from_v8_doubles(values: &[f64]) -> Option<Xorshift128Plus> {
+        let values: Vec<u64> = values.iter().map(|&v| {
+            (v + 1.0).to_bits() & V8_MANTISSA_MASK
+        }).collect();
+        recover_xorshift(values.as_slice(), 52)
+    }
+}
+
+impl Prng for Xorshift128Plus {
+    fn rand_u32(&mut self) -> u32 {
+        self.step();
+        (self.state0 >> 32) as u32
+    }
+
+    fn rand_f64(&mut self) -> f64 {
+        self.next_double()
+    }
+}
+
+/*
+ * Reverse the order of Math.random() values inside every cache bucket, the
+ * first value should be the first one after a cache refill. The result is
+ * in the order of next_double().
+ */
+pub fn v8_cache_order(values: &[f64]) -> Vec<f64> {
+    values.chunks(V8_CACHE_SIZE)
+        .flat_map(|bucket| bucket.iter().rev().cloned()).collect()
+}
+
+#[inline]
+fn murmur_hash3(value: u64) -> u64 {
+    let mut h = value;
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51afd7ed558ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ceb9fe1a85ec53);
+    h ^ (h >> 33)
+}
+
+/*
+ * Symbolically run the generator and solve the collected equations
+ *
+ * Every output value contains the upper known_bits bits of state0.
+ */
+fn recover_xorshift(outputs: &[u64], known_bits: usize)
+        -> Option<Xorshift128Plus> {
+    let mut state0: Vec<BitVec> = (0..64).map(unit_bitvec).collect();
+    let mut state1: Vec<BitVec> = (64..128).map(unit_bitvec).collect();
+    let mut equations = Vec::with_capacity(outputs.len() * known_bits);
+    for &value in outputs.iter() {
+        // Symbolic version of Xorshift128Plus::step()
+        let mut s1 = state0;
+        let s0 = state1.clone();
+        s1 = xor_words(s1.as_slice(), shl_word(s1.as_slice(), 23).as_slice());
+        s1 = xor_words(s1.as_slice(), shr_word(s1.as_slice(), 17).as_slice());
+        s1 = xor_words(s1.as_slice(), s0.as_slice());
+        s1 = xor_words(s1.as_slice(), shr_word(s0.as_slice(), 26).as_slice());
+        state0 = s0;
+        state1 = s1;
+        let first = 64 - known_bits;
+        for i in 0..known_bits {
+            equations.push((state0[first + i], (value >> i) & 1 == 1));
+        }
+    }
+    solve_gf2(equations).map(|bits| {
+        let mut rng = Xorshift128Plus::new(bits[0], bits[1]);
+        for _ in 0..outputs.len() {
+            rng.step();
+        }
+        rng
+    })
+}
+
+#[inline]
+fn unit_bitvec(bit: usize) -> BitVec {
+    let mut v = [0u64; 2];
+    v[bit / 64] = 1 << (bit % 64);
+    v
+}
+
+fn xor_words(w1: &[BitVec], w2: &[BitVec]) -> Vec<BitVec> {
+    w1.iter().zip(w2.iter()).map(|(a, b)| [a[0] ^ b[0], a[1] ^ b[1]]).collect()
+}
+
+fn shl_word(w: &[BitVec], shift: usize) -> Vec<BitVec> {
+    (0..64).map(|i| match i >= shift {
+        true => w[i - shift],
+        false => [0, 0]
+    }).collect()
+}
+
+fn shr_word(w: &[BitVec], shift: usize) -> Vec<BitVec> {
+    (0..64).map(|i| match i + shift < 64 {
+        true => w[i + shift],
+        false => [0, 0]
+    }).collect()
+}
+
+/*
+ * Gaussian elimination over GF(2)
+ *
+ * Return None if the system is inconsistent or doesn't have the only
+ * solution.
+ */
+fn solve_gf2(mut equations: Vec<(BitVec, bool)>) -> Option<BitVec> {
+    let mut rank = 0;
+    for bit in 0..128 {
+        let (word, mask) = (bit / 64, 1u64 << (bit % 64));
+        let pivot = (rank..equations.len()).find(|&i| {
+                equations[i].0[word] & mask != 0
+            })?;
+        equations.swap(rank, pivot);
+        let (row, value) = equations[rank];
+        for (i, eq) in equations.iter_mut().enumerate() {
+            if i != rank && eq.0[word] & mask != 0 {
+                eq.0 = [eq.0[0] ^ row[0], eq.0[1] ^ row[1]];
+                eq.1 ^= value;
+            }
+        }
+        rank += 1;
+    }
+    if equations[rank..].iter().any(|&(_, value)| value) {
+        return None;
+    }
+    let mut solution = [0u64; 2];
+    for (bit, &(_, value)) in equations[..128].iter().enumerate() {
+        if value {
+            solution[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+    Some(solution)
+}
+
+/*
+ * PCG32 (XSH RR 64/32) generator
+ */
+static PCG_MULTIPLIER: u64 = 6364136223846793005;
+
+pub struct Pcg32 {
+    state: u64,
+    inc: u64
+}
+
+impl Pcg32 {
+    /*
+     * Same as pcg32_srandom_r(rng, initstate, initseq)
+     */
+    pub fn new(initstate: u64, initseq: u64) -> Pcg32 {
+        let mut rng = Pcg32{state: 0, inc: (initseq << 1) | 1};
+        rng.rand();
+        rng.state = rng.state.wrapping_add(initstate);
+        rng.rand();
+        rng
+    }
+
+    pub fn rand(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(PCG_MULTIPLIER).wrapping_add(self.inc);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    /*
+     * Find the initial state which produces the outputs for the known
+     * stream
+     *
+     * The full 64-bit state can't be brute forced, so the candidates should
+     * be limited, for example, by a timestamp or a 32-bit seed.
+     */
+    pub fn crack_seed<I: Iterator<Item=u64>>(outputs: &[u32], initseq: u64,
+                                             seeds: I) -> Option<u64> {
+        let mut seeds = seeds;
+        seeds.find(|&seed| {
+            let mut rng = Pcg32::new(seed, initseq);
+            outputs.iter().all(|&out| rng.rand() == out)
+        })
+    }
+}
+
+impl Prng for Pcg32 {
+    fn rand_u32(&mut self) -> u32 {
+        self.rand()
+    }
+}
+
+/*
+ * Tests
+ */
+#[cfg(test)]
+mod tests {
+    use super::{Prng, JavaRandom, GlibcRand, MsvcRand, Xorshift128Plus, Pcg32};
+    use super::{v8_cache_order, V8_CACHE_SIZE};
+
+    #[test]
+    fn test_java_random() {
+        let mut rng = JavaRandom::new(42);
+        let expected = [-1170105035, 234785527, -1360544799, 205897768];
+        for (i, &exp) in expected.iter().enumerate() {
+            assert_eq!((i, exp), (i, rng.next_int()));
+        }
+    }
+
+    #[test]
+    fn test_java_random_from_outputs() {
+        let mut rng = JavaRandom::new(0x1234567890);
+        let outputs = [rng.next_int(), rng.next_int()];
+        let mut clone = JavaRandom::from_outputs(&outputs).unwrap();
+        for i in 0..100 {
+            assert_eq!((i, rng.next_long()), (i, clone.next_long()));
+        }
+        assert!(JavaRandom::from_outputs(&outputs[..1]).is_none());
+    }
+
+    #[test]
+    fn test_glibc_rand() {
+        let mut rng = GlibcRand::new(1);
+        let expected = [1804289383, 846930886, 1681692777, 1714636915,
+                        1957747793];
+        for (i, &exp) in expected.iter().enumerate() {
+            assert_eq!((i, exp), (i, rng.rand()));
+        }
+    }
+
+    #[test]
+    fn test_glibc_crack_seed() {
+        let mut rng = GlibcRand::new(1420000123);
+        let outputs = [rng.rand(), rng.rand()];
+        let seed = GlibcRand::crack_seed(&outputs, 1420000000..1420001000);
+        assert_eq!(seed, Some(1420000123));
+        assert_eq!(GlibcRand::crack_seed(&outputs, 0..1000), None);
+    }
+
+    #[test]
+    fn test_msvc_rand() {
+        let mut rng = MsvcRand::new(1);
+        let expected = [41, 18467, 6334, 26500, 19169];
+        for (i, &exp) in expected.iter().enumerate() {
+            assert_eq!((i, exp), (i, rng.rand()));
+        }
+    }
+
+    #[test]
+    fn test_msvc_from_outputs() {
+        let mut rng = MsvcRand::new(0xdeadbeef);
+        let outputs = [rng.rand(), rng.rand(), rng.rand(), rng.rand()];
+        let mut clone = MsvcRand::from_outputs(&outputs).unwrap();
+        for i in 0..100 {
+            assert_eq!((i, rng.rand()), (i, clone.rand()));
+        }
+    }
+
+    #[test]
+    fn test_xorshift_from_v8_doubles() {
+        let mut rng = Xorshift128Plus::from_seed(12345);
+        let values: Vec<f64> = (0..4).map(|_| rng.next_double()).collect();
+        let mut clone = Xorshift128Plus::from_v8_doubles(&values).unwrap();
+        assert!(Xorshift128Plus::from_v8_doubles(&values[..3]).is_none());
+        for i in 0..100 {
+            assert_eq!((i, rng.next_double()), (i, clone.next_double()));
+        }
+    }
+
+    #[test]
+    fn test_xorshift_v8() {
+        // node --random-seed=12345 -e 'console.log(Math.random())'
+        let observed = [0.9044192244068718, 0.5645421251397142];
+        let mut rng = Xorshift128Plus::from_seed(12345);
+        let values: Vec<f64> = (0..V8_CACHE_SIZE * 2)
+            .map(|_| rng.next_double()).collect();
+        let math_random = v8_cache_order(values.as_slice());
+        assert_eq!(&math_random[..2], &observed[..]);
+        assert_eq!(&math_random[V8_CACHE_SIZE..V8_CACHE_SIZE + 2],
+                   &[0.3141187814286084, 0.33793103351307496]);
+        assert_eq!(v8_cache_order(math_random.as_slice()), values);
+
+        let ordered = v8_cache_order(&math_random[..V8_CACHE_SIZE]);
+        let mut clone = Xorshift128Plus::from_v8_doubles(&ordered[..4])
+            .unwrap();
+        for _ in 4..V8_CACHE_SIZE {
+            clone.next_double();
+        }
+        assert_eq!(clone.next_double(), values[V8_CACHE_SIZE]);
+    }
+
+    #[test]
+    fn test_xorshift_from_outputs() {
+        let mut rng = Xorshift128Plus::new(0x0123456789abcdef,
+                                           0xfedcba9876543210);
+        let outputs: Vec<u32> = (0..6).map(|_| rng.rand_u32()).collect();
+        let mut clone = Xorshift128Plus::from_outputs(&outputs).unwrap();
+        for i in 0..100 {
+            assert_eq!((i, rng.rand_u32()), (i, clone.rand_u32()));
+        }
+        assert!(Xorshift128Plus::from_outputs(&outputs[..5]).is_none());
+    }
+
+    #[test]
+    fn test_pcg32() {
+        let mut rng = Pcg32::new(42, 54);
+        let expected = [0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293,
+                        0xbfa4784b, 0xcbed606e];
+        for (i, &exp) in expected.iter().enumerate() {
+            assert_eq!((i, exp), (i, rng.rand()));
+        }
+    }
+
+    #[test]
+    fn test_pcg32_crack_seed() {
+        let mut rng = Pcg32::new(31337, 54);
+        let outputs = [rng.rand(), rng.rand()];
+        assert_eq!(Pcg32::crack_seed(&outputs, 54, 0..100000), Some(31337));
+    }
+}